@@ -1,11 +1,19 @@
-/// Static analysis utilities: validation, function list, AST access.
+/// Static analysis utilities: validation, function list, tokens, AST access.
 
-use crate::ast;
+use std::collections::HashMap;
+
+use crate::angle_mode::AngleMode;
+use crate::ast::{self, Ast, BinOp, Token};
+use crate::config::ParseConfig;
+use crate::error::ExathError;
+use crate::evaluator::{value_ast, Cx};
+use crate::symbolic;
 
 // ── is_valid ──────────────────────────────────────────────────────────────────
 
 /// Returns true if the expression parses without error.
 /// Does NOT evaluate it (variables don't need to be defined).
+/// Empty or whitespace-only input is not valid.
 pub fn is_valid(expr: &str) -> bool {
     ast::parse_str(expr).is_ok()
 }
@@ -29,18 +37,20 @@ pub fn supported_functions() -> &'static [&'static str] {
         "sqrt", "cbrt",
         // Magnitude / complex parts
         "abs", "arg", "conj", "real", "imag",
-        "gamma", "lgamma", "erf", "erfc", "digamma", "beta",
+        "gamma", "lgamma", "erf", "erfc", "digamma", "beta", "sigmoid", "logit",
         "isprime", "nextprime", "totient", "powmod", "factorint",
         "mean", "median", "variance", "stddev", "npdf", "ncdf", "binom",
         // Rounding
-        "floor", "ceil", "round", "trunc", "frac",
+        "floor", "ceil", "round", "trunc", "frac", "fracpos",
         // Sign
         "sign", "sgn",
         // Angle conversion
         "deg", "rad",
         // Control flow / multi-argument
-        "if", "piecewise", "min", "max", "clamp", "gcd", "lcm", "assume", "abs",
-        "sum", "product", "deriv", "convert",
+        "if", "piecewise", "min", "max", "clamp", "gcd", "lcm", "idiv", "rem", "assume", "abs",
+        "sum", "product", "fold", "deriv", "convert",
+        // Random (drawn from a session's generator)
+        "rand", "randint",
         // Symbolic / calculus forms (usable via a session, e.g. eval_line)
         "diff", "simplify", "integral", "solve", "factor", "polygcd", "nsolve", "expand", "taylor", "limit",
         "grad", "jacobian", "hessian", "odesolve", "minimize", "maximize", "sumc", "laplace", "dsolve",
@@ -49,8 +59,821 @@ pub fn supported_functions() -> &'static [&'static str] {
     ]
 }
 
+/// The names of [`supported_functions`] grouped for display, as
+/// `(category, names)` pairs, e.g. `("Rounding", ["floor", "ceil", …])`.
+/// Every supported function is in exactly one category.
+pub fn function_categories() -> &'static [(&'static str, &'static [&'static str])] {
+    &[
+        ("Trigonometric", &["sin", "cos", "tan", "cot", "sec", "csc"]),
+        ("Inverse trigonometric", &["asin", "acos", "atan", "acot", "asec", "acsc"]),
+        ("Hyperbolic", &["sinh", "cosh", "tanh", "coth", "sech", "csch"]),
+        ("Inverse hyperbolic", &["asinh", "acosh", "atanh", "acoth", "asech", "acsch"]),
+        ("Exponential / logarithmic", &["exp", "ln", "lg", "log"]),
+        ("Roots", &["sqrt", "cbrt"]),
+        ("Magnitude / complex parts", &["abs", "arg", "conj", "real", "imag"]),
+        (
+            "Special functions",
+            &["gamma", "lgamma", "erf", "erfc", "digamma", "beta", "sigmoid", "logit"],
+        ),
+        ("Number theory", &["isprime", "nextprime", "totient", "powmod", "factorint"]),
+        ("Statistics", &["mean", "median", "variance", "stddev", "npdf", "ncdf", "binom"]),
+        ("Rounding", &["floor", "ceil", "round", "trunc", "frac", "fracpos"]),
+        ("Sign", &["sign", "sgn"]),
+        ("Angle conversion", &["deg", "rad"]),
+        (
+            "Control flow / multi-argument",
+            &[
+                "if", "piecewise", "min", "max", "clamp", "gcd", "lcm", "idiv", "rem", "assume",
+                "sum", "product", "fold", "deriv", "convert",
+            ],
+        ),
+        ("Random", &["rand", "randint"]),
+        (
+            "Symbolic / calculus",
+            &[
+                "diff", "simplify", "integral", "solve", "factor", "polygcd", "nsolve", "expand",
+                "taylor", "limit", "grad", "jacobian", "hessian", "odesolve", "minimize",
+                "maximize", "sumc", "laplace", "dsolve",
+            ],
+        ),
+        (
+            "Matrix",
+            &[
+                "det", "inv", "transpose", "trace", "rank", "norm", "svdvals", "charpoly",
+                "identity", "linsolve", "eigenvalues", "eigenvectors",
+            ],
+        ),
+    ]
+}
+
+/// Alternative spellings accepted for built-in functions, as
+/// `(alias, canonical name)` pairs, e.g. `("arcsin", "asin")`. Aliases are not
+/// listed by [`supported_functions`].
+pub fn function_aliases() -> &'static [(&'static str, &'static str)] {
+    crate::evaluator::FUNCTION_ALIASES
+}
+
+// ── complete ──────────────────────────────────────────────────────────────────
+
+/// Built-in constants offered by [`complete`], in their ASCII spelling
+/// (`epsilon` is the parser's alias for `e`). The imaginary unit is written
+/// `sqrt(-1)`, so `i` is not one of them.
+const CONSTANTS: &[&str] = &["e", "epsilon", "inf", "nan", "phi", "pi"];
+
+/// Built-in function and constant names starting with `prefix`, ignoring
+/// ASCII case, sorted. An empty prefix lists them all. See
+/// [`crate::Session::complete`] to also offer a session's own names.
+///
+/// ```
+/// use exath_engine::analysis::complete;
+/// assert_eq!(complete("Sq"), ["sqrt"]);
+/// assert!(complete("p").contains(&"pi"));
+/// ```
+pub fn complete(prefix: &str) -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = supported_functions()
+        .iter()
+        .chain(CONSTANTS)
+        .copied()
+        .filter(|name| starts_with_ignore_case(name, prefix))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Whether `name` starts with `prefix`, ignoring ASCII case.
+pub(crate) fn starts_with_ignore_case(name: &str, prefix: &str) -> bool {
+    name.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+// ── function_help ─────────────────────────────────────────────────────────────
+
+/// One-line usage and description per built-in, for hover help.
+const FUNCTION_HELP: &[(&str, &str)] = &[
+    ("sin", "sin(x) — sine of an angle"),
+    ("cos", "cos(x) — cosine of an angle"),
+    ("tan", "tan(x) — tangent of an angle"),
+    ("cot", "cot(x) — cotangent of an angle"),
+    ("sec", "sec(x) — secant, 1/cos(x)"),
+    ("csc", "csc(x) — cosecant, 1/sin(x)"),
+    ("asin", "asin(x) — inverse sine, as an angle"),
+    ("acos", "acos(x) — inverse cosine, as an angle"),
+    ("atan", "atan(x) — inverse tangent, as an angle"),
+    ("acot", "acot(x) — inverse cotangent, as an angle"),
+    ("asec", "asec(x) — inverse secant, as an angle"),
+    ("acsc", "acsc(x) — inverse cosecant, as an angle"),
+    ("sinh", "sinh(x) — hyperbolic sine"),
+    ("cosh", "cosh(x) — hyperbolic cosine"),
+    ("tanh", "tanh(x) — hyperbolic tangent"),
+    ("coth", "coth(x) — hyperbolic cotangent"),
+    ("sech", "sech(x) — hyperbolic secant"),
+    ("csch", "csch(x) — hyperbolic cosecant"),
+    ("asinh", "asinh(x) — inverse hyperbolic sine"),
+    ("acosh", "acosh(x) — inverse hyperbolic cosine"),
+    ("atanh", "atanh(x) — inverse hyperbolic tangent"),
+    ("acoth", "acoth(x) — inverse hyperbolic cotangent"),
+    ("asech", "asech(x) — inverse hyperbolic secant"),
+    ("acsch", "acsch(x) — inverse hyperbolic cosecant"),
+    ("exp", "exp(x) — e raised to the power x"),
+    ("ln", "ln(x) — natural logarithm"),
+    ("lg", "lg(x) — base-10 logarithm"),
+    ("log", "log(x) — base-10 logarithm; log:b(x) for base b"),
+    ("sqrt", "sqrt(x) — square root, complex for negative reals"),
+    ("cbrt", "cbrt(x) — principal cube root, complex for negative reals"),
+    ("abs", "abs(x) — absolute value or complex modulus"),
+    ("arg", "arg(z) — phase angle of a complex number"),
+    ("conj", "conj(z) — complex conjugate"),
+    ("real", "real(z) — real part"),
+    ("imag", "imag(z) — imaginary part"),
+    ("gamma", "gamma(x) — gamma function Γ(x)"),
+    ("lgamma", "lgamma(x) — natural log of |Γ(x)|"),
+    ("erf", "erf(x) — error function"),
+    ("erfc", "erfc(x) — complementary error function"),
+    ("digamma", "digamma(x) — digamma function ψ(x)"),
+    ("beta", "beta(a, b) — beta function Γ(a)Γ(b)/Γ(a+b)"),
+    ("sigmoid", "sigmoid(x) — logistic function 1/(1 + e^-x)"),
+    ("logit", "logit(x) — log-odds ln(x/(1 - x)), for 0 < x < 1"),
+    ("isprime", "isprime(n) — 1 if n is prime, else 0"),
+    ("nextprime", "nextprime(n) — smallest prime greater than n"),
+    ("totient", "totient(n) — Euler's totient φ(n)"),
+    ("powmod", "powmod(a, b, m) — a^b mod m"),
+    ("factorint", "factorint(n) — prime factorisation of an integer"),
+    ("mean", "mean(a, b, …) — arithmetic mean"),
+    ("median", "median(a, b, …) — median"),
+    ("variance", "variance(a, b, …) — population variance"),
+    ("stddev", "stddev(a, b, …) — population standard deviation"),
+    ("npdf", "npdf(x, mu, sigma) — normal probability density"),
+    ("ncdf", "ncdf(x, mu, sigma) — normal cumulative distribution"),
+    ("binom", "binom(n, k) — binomial coefficient, n choose k"),
+    ("floor", "floor(x) — round down"),
+    ("ceil", "ceil(x) — round up"),
+    ("round", "round(x) — round to nearest, halves away from zero"),
+    ("trunc", "trunc(x) — round toward zero"),
+    ("frac", "frac(x) — fractional part with the sign of x"),
+    ("fracpos", "fracpos(x) — fractional part in [0, 1)"),
+    ("sign", "sign(x) — signum: -1, 0 or 1"),
+    ("sgn", "sgn(x) — signum: -1, 0 or 1"),
+    ("deg", "deg(x) — convert radians to degrees (ignores the angle mode)"),
+    ("rad", "rad(x) — convert degrees to radians (ignores the angle mode)"),
+    ("if", "if(cond, a, b) — a if cond is nonzero, else b"),
+    ("piecewise", "piecewise(c1, v1, …, default) — value of the first true condition"),
+    ("min", "min(a, b, …) — smallest argument"),
+    ("max", "max(a, b, …) — largest argument"),
+    ("clamp", "clamp(x, lo, hi) — x limited to [lo, hi]"),
+    ("gcd", "gcd(a, b) — greatest common divisor of two integers"),
+    ("lcm", "lcm(a, b) — least common multiple of two integers"),
+    ("idiv", "idiv(a, b) — floor division"),
+    ("rem", "rem(a, b) — remainder of idiv, with the sign of b"),
+    ("assume", "assume(x > 0) — sign assumption used by simplify"),
+    ("sum", "sum(expr, k, a, b) — sum of expr for k = a…b"),
+    ("product", "product(expr, k, a, b) — product of expr for k = a…b"),
+    ("fold", "fold(from, to, init, f) — accumulate f(acc, i) over an integer range"),
+    ("deriv", "deriv(expr, x, x0) — numeric derivative at x0"),
+    ("convert", "convert(value, from, to) — unit conversion"),
+    ("rand", "rand() — uniform random number in [0, 1); needs a session"),
+    ("randint", "randint(a, b) — uniform random integer from a to b inclusive; needs a session"),
+    ("diff", "diff(expr, x) — symbolic derivative"),
+    ("simplify", "simplify(expr) — simplify an expression"),
+    ("integral", "integral(expr, x[, a, b]) — indefinite or definite integral"),
+    ("solve", "solve(eq, x) — solve an equation for x"),
+    ("factor", "factor(expr, x) — factor a polynomial"),
+    ("polygcd", "polygcd(p, q, x) — polynomial greatest common divisor"),
+    ("nsolve", "nsolve(expr, x, x0) — root by Newton's method from x0"),
+    ("expand", "expand(expr) — expand products and powers"),
+    ("taylor", "taylor(expr, x, x0, n) — Taylor polynomial of order n about x0"),
+    ("limit", "limit(expr, x, x0) — limit as x approaches x0"),
+    ("grad", "grad(expr, [x, y, …]) — gradient vector"),
+    ("jacobian", "jacobian([f, g, …], [x, y, …]) — Jacobian matrix"),
+    ("hessian", "hessian(expr, [x, y, …]) — Hessian matrix"),
+    ("odesolve", "odesolve(f, x, y, x0, y0, x1) — solve y' = f(x, y) numerically"),
+    ("minimize", "minimize(f, x, a, b) — minimum point of f on [a, b]"),
+    ("maximize", "maximize(f, x, a, b) — maximum point of f on [a, b]"),
+    ("sumc", "sumc(expr, k, n) — closed-form sum for k = 1…n"),
+    ("laplace", "laplace(expr, t, s) — Laplace transform"),
+    ("dsolve", "dsolve([a_n, …, a_0], x) — linear constant-coefficient ODE"),
+    ("det", "det(M) — determinant"),
+    ("inv", "inv(M) — matrix inverse"),
+    ("transpose", "transpose(M) — matrix transpose"),
+    ("trace", "trace(M) — sum of the diagonal"),
+    ("rank", "rank(M) — matrix rank"),
+    ("norm", "norm(M) — Frobenius norm"),
+    ("svdvals", "svdvals(M) — singular values, descending"),
+    ("charpoly", "charpoly(M, x) — characteristic polynomial"),
+    ("identity", "identity(n) — n×n identity matrix"),
+    ("linsolve", "linsolve(A, b) — solve A·x = b"),
+    ("eigenvalues", "eigenvalues(M) — eigenvalues"),
+    ("eigenvectors", "eigenvectors(M) — eigenvectors"),
+];
+
+/// One-line help for a built-in function, e.g. `"gcd(a, b) — greatest common
+/// divisor of two integers"`. Aliases resolve to their canonical function.
+///
+/// ```
+/// use exath_engine::analysis::function_help;
+/// assert!(function_help("sin").unwrap().starts_with("sin(x)"));
+/// assert_eq!(function_help("arcsin"), function_help("asin"));
+/// assert_eq!(function_help("nope"), None);
+/// ```
+pub fn function_help(name: &str) -> Option<&'static str> {
+    let name = crate::evaluator::canonical_name(name);
+    FUNCTION_HELP
+        .iter()
+        .find(|(entry, _)| *entry == name)
+        .map(|(_, help)| *help)
+}
+
+/// Every supported function paired with its [`function_help`] text, in the
+/// order of [`supported_functions`].
+pub fn function_catalog() -> Vec<(&'static str, &'static str)> {
+    let mut catalog: Vec<(&'static str, &'static str)> = Vec::new();
+    for &name in supported_functions() {
+        if catalog.iter().any(|(seen, _)| *seen == name) {
+            continue;
+        }
+        if let Some(help) = function_help(name) {
+            catalog.push((name, help));
+        }
+    }
+    catalog
+}
+
+// ── to_fraction ───────────────────────────────────────────────────────────────
+
+/// Best rational approximation `p/q` of `value` with `0 < q <= max_denom`,
+/// found by walking the continued-fraction convergents.
+///
+/// Returns `None` unless some convergent is within `1e-10` of `value`
+/// (scaled by `|value|` above 1), so irrationals and values needing a larger
+/// denominator are rejected. The sign is carried by `p`; `q` is positive.
+///
+/// ```
+/// use exath_engine::analysis::to_fraction;
+/// assert_eq!(to_fraction(0.75, 100), Some((3, 4)));
+/// assert_eq!(to_fraction(std::f64::consts::PI, 1000), None);
+/// ```
+pub fn to_fraction(value: f64, max_denom: u64) -> Option<(i64, i64)> {
+    if !value.is_finite() || max_denom == 0 || value.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    let tol = 1e-10 * value.abs().max(1.0);
+    let max_denom = max_denom.min(i64::MAX as u64) as i128;
+    // Convergents h/k with the recurrences h_n = a_n·h_{n-1} + h_{n-2}.
+    let (mut h_prev, mut h) = (0i128, 1i128);
+    let (mut k_prev, mut k) = (1i128, 0i128);
+    let mut x = value.abs();
+    for _ in 0..64 {
+        let a = x.floor();
+        if a >= i64::MAX as f64 {
+            return None;
+        }
+        let a = a as i128;
+        let h_next = a.checked_mul(h)?.checked_add(h_prev)?;
+        let k_next = a.checked_mul(k)?.checked_add(k_prev)?;
+        if k_next > max_denom || h_next > i64::MAX as i128 {
+            return None;
+        }
+        (h_prev, h, k_prev, k) = (h, h_next, k, k_next);
+        if (h as f64 / k as f64 - value.abs()).abs() <= tol {
+            let p = if value < 0.0 { -(h as i64) } else { h as i64 };
+            return Some((p, k as i64));
+        }
+        let frac = x - a as f64;
+        if frac == 0.0 {
+            return None;
+        }
+        x = 1.0 / frac;
+    }
+    None
+}
+
 // ── parse ─────────────────────────────────────────────────────────────────────
 
 /// Parse an expression string into an AST.
 /// The returned AST can be inspected or passed to `eval_ast`.
 pub use ast::parse_str as parse;
+
+// ── free_variables ────────────────────────────────────────────────────────────
+
+/// Names the expression needs values for, sorted and deduplicated.
+///
+/// Unlike [`ast::collect_vars`], built-in function names and the loop or
+/// differentiation variable of `sum`, `product` and `deriv` are left out, so
+/// `sum(k*x, k, 1, n)` needs `x` and `n` but not `k`. Constants such as `pi`
+/// are folded while parsing and never appear.
+pub fn free_variables(expr: &str) -> Result<Vec<String>, ExathError> {
+    let ast = ast::parse_str(expr)?;
+    let mut out = Vec::new();
+    free_vars_rec(&ast, &mut Vec::new(), &mut out);
+    out.sort();
+    out.dedup();
+    Ok(out)
+}
+
+fn free_vars_rec(node: &ast::Ast, bound: &mut Vec<String>, out: &mut Vec<String>) {
+    use ast::Ast;
+    match node {
+        Ast::Var(name) => {
+            if !bound.contains(name) && !ast::is_function(name) {
+                out.push(name.clone());
+            }
+        }
+        Ast::Call(name, args) => {
+            let binds = matches!((name.as_str(), args.len()), ("sum" | "product", 4) | ("deriv", 3));
+            match (binds, args.get(1)) {
+                (true, Some(Ast::Var(v))) => {
+                    bound.push(v.clone());
+                    free_vars_rec(&args[0], bound, out);
+                    bound.pop();
+                    for arg in &args[2..] {
+                        free_vars_rec(arg, bound, out);
+                    }
+                }
+                _ => {
+                    for arg in args {
+                        free_vars_rec(arg, bound, out);
+                    }
+                }
+            }
+        }
+        Ast::BinOp(_, left, right) => {
+            free_vars_rec(left, bound, out);
+            free_vars_rec(right, bound, out);
+        }
+        Ast::Chain(_, operands) => {
+            for operand in operands {
+                free_vars_rec(operand, bound, out);
+            }
+        }
+        Ast::UnaryNeg(inner) | Ast::UnaryNot(inner) | Ast::Factorial(inner) => {
+            free_vars_rec(inner, bound, out);
+        }
+        Ast::Matrix(rows) => {
+            for e in rows.iter().flatten() {
+                free_vars_rec(e, bound, out);
+            }
+        }
+        Ast::Number(_) => {}
+    }
+}
+
+// ── is_constant ───────────────────────────────────────────────────────────────
+
+/// True if the expression has the same value whatever the variables and user
+/// functions are: it has no [`free_variables`] and calls only built-in
+/// functions other than `rand` / `randint`. `2 + 3*pi` and
+/// `sum(k, k, 1, 10)` are constant, `2 + x`, `f(2)` and `rand()` are not.
+pub fn is_constant(expr: &str) -> Result<bool, ExathError> {
+    let ast = ast::parse_str(expr)?;
+    let mut free = Vec::new();
+    free_vars_rec(&ast, &mut Vec::new(), &mut free);
+    Ok(free.is_empty() && calls_only_builtins(&ast))
+}
+
+fn calls_only_builtins(node: &ast::Ast) -> bool {
+    use ast::Ast;
+    match node {
+        Ast::Call(name, args) => {
+            let builtin = ast::is_function(name)
+                || name == "\u{00b0}"
+                || supported_functions().contains(&name.as_str());
+            let builtin = builtin && !ast::is_random(name);
+            builtin && args.iter().all(calls_only_builtins)
+        }
+        Ast::BinOp(_, left, right) => calls_only_builtins(left) && calls_only_builtins(right),
+        Ast::Chain(_, operands) => operands.iter().all(calls_only_builtins),
+        Ast::UnaryNeg(inner) | Ast::UnaryNot(inner) | Ast::Factorial(inner) => {
+            calls_only_builtins(inner)
+        }
+        Ast::Matrix(rows) => rows.iter().flatten().all(calls_only_builtins),
+        Ast::Number(_) | Ast::Var(_) => true,
+    }
+}
+
+// ── trace ─────────────────────────────────────────────────────────────────────
+
+/// One reduction recorded by [`trace`]: an operation applied to operand
+/// values, and the value it produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// The operator (`+`, `*`, `neg`, `!`, `not`, …) or function name.
+    pub op: String,
+    /// The operand values, left to right. A short-circuited `&&` / `||` has
+    /// only its left operand; calls evaluated as a whole have none (see
+    /// [`trace`]).
+    pub operands: Vec<Cx>,
+    pub result: Cx,
+}
+
+/// Evaluate a constant expression, recording each subtree reduction in the
+/// order it happens, for showing how `2 + 3 * 4` reduces step by step.
+///
+/// Operators and built-in math functions record their operands' values.
+/// Calls with unevaluated arguments (`if`, `sum`, `deriv`, …) are one step
+/// with no operands. Variables are not defined, and nothing is cached, so
+/// use [`crate::evaluate`] when only the value is needed.
+///
+/// ```
+/// use exath_engine::{analysis::trace, AngleMode};
+/// let steps = trace("2 + 3 * 4", AngleMode::Rad)?;
+/// let ops: Vec<&str> = steps.iter().map(|s| s.op.as_str()).collect();
+/// assert_eq!(ops, ["*", "+"]);
+/// assert_eq!(steps[1].result.re, 14.0);
+/// # Ok::<(), exath_engine::ExathError>(())
+/// ```
+pub fn trace(expr: &str, mode: AngleMode) -> Result<Vec<TraceStep>, ExathError> {
+    let ast = ast::parse_str(expr)?;
+    let mut steps = Vec::new();
+    trace_node(&ast, mode, &mut steps)?;
+    Ok(steps)
+}
+
+/// Evaluate `node`, recording its reductions. Each step evaluates the node
+/// with its children replaced by their values, so the arithmetic is exactly
+/// that of [`ast::eval_ast`].
+fn trace_node(node: &Ast, mode: AngleMode, steps: &mut Vec<TraceStep>) -> Result<Cx, ExathError> {
+    let (op, operands, reduced) = match node {
+        Ast::Number(_) | Ast::Var(_) | Ast::Matrix(_) => {
+            return ast::eval_ast(node, &HashMap::new(), &ast::UserFns::new(), mode);
+        }
+        Ast::BinOp(op @ (BinOp::And | BinOp::Or), left, right) => {
+            let left = trace_node(left, mode, steps)?;
+            let truthy = left.re != 0.0 || left.im != 0.0;
+            let mut operands = vec![left];
+            let right = if truthy == (*op == BinOp::And) {
+                let right = trace_node(right, mode, steps)?;
+                operands.push(right);
+                value_ast(right)
+            } else {
+                // Short-circuited: the right side is never evaluated.
+                (**right).clone()
+            };
+            let reduced = Ast::BinOp(op.clone(), Box::new(value_ast(left)), Box::new(right));
+            (symbolic::op_symbol(op).trim().to_string(), operands, reduced)
+        }
+        Ast::BinOp(op, left, right) => {
+            let left = trace_node(left, mode, steps)?;
+            let right = trace_node(right, mode, steps)?;
+            let reduced =
+                Ast::BinOp(op.clone(), Box::new(value_ast(left)), Box::new(value_ast(right)));
+            (symbolic::op_symbol(op).trim().to_string(), vec![left, right], reduced)
+        }
+        Ast::UnaryNeg(inner) => {
+            let value = trace_node(inner, mode, steps)?;
+            ("neg".to_string(), vec![value], Ast::UnaryNeg(Box::new(value_ast(value))))
+        }
+        Ast::UnaryNot(inner) => {
+            let value = trace_node(inner, mode, steps)?;
+            ("not".to_string(), vec![value], Ast::UnaryNot(Box::new(value_ast(value))))
+        }
+        Ast::Factorial(inner) => {
+            let value = trace_node(inner, mode, steps)?;
+            ("!".to_string(), vec![value], Ast::Factorial(Box::new(value_ast(value))))
+        }
+        Ast::Chain(ops, operands) => {
+            // One step per comparison, stopping at the first false one; its
+            // result is the chain's.
+            let mut left = trace_node(&operands[0], mode, steps)?;
+            let mut result = Cx::real(1.0);
+            for (op, right) in ops.iter().zip(&operands[1..]) {
+                let right = trace_node(right, mode, steps)?;
+                let reduced =
+                    Ast::BinOp(op.clone(), Box::new(value_ast(left)), Box::new(value_ast(right)));
+                result = ast::eval_ast(&reduced, &HashMap::new(), &ast::UserFns::new(), mode)?;
+                let op = symbolic::op_symbol(op).trim().to_string();
+                steps.push(TraceStep { op, operands: vec![left, right], result });
+                if result.re == 0.0 {
+                    break;
+                }
+                left = right;
+            }
+            return Ok(result);
+        }
+        Ast::Call(name, args) if ast::is_function(name) && name != "if" => {
+            let values = args
+                .iter()
+                .map(|arg| trace_node(arg, mode, steps))
+                .collect::<Result<Vec<_>, _>>()?;
+            let reduced = Ast::Call(name.clone(), values.iter().map(|v| value_ast(*v)).collect());
+            (name.clone(), values, reduced)
+        }
+        Ast::Call(name, _) => (name.clone(), Vec::new(), node.clone()),
+    };
+    let result = ast::eval_ast(&reduced, &HashMap::new(), &ast::UserFns::new(), mode)?;
+    steps.push(TraceStep { op, operands, result });
+    Ok(result)
+}
+
+// ── tokens ────────────────────────────────────────────────────────────────────
+
+/// Coarse token category, intended for syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Number,
+    /// A built-in function name, or any name directly followed by `(`.
+    Function,
+    /// A built-in constant (`e`, `pi`, `π`, `phi`, `ϕ`, `ε`, `inf`, `nan`, `i`).
+    Constant,
+    /// Any other name (variables, user-defined names).
+    Identifier,
+    /// Arithmetic, comparison and logical operators, including `mod`.
+    Operator,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Semicolon,
+    /// One side of an absolute value `|x|`.
+    AbsBar,
+}
+
+/// A single token of an expression with its location in the source.
+///
+/// `start` and `end` are character (not byte) offsets, `end` exclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+    /// The source text the token was read from.
+    pub text: String,
+}
+
+/// Split an expression into classified tokens with source positions.
+///
+/// Only lexical errors are reported; the token stream is not parsed, so
+/// incomplete input such as `sin(2 +` still yields tokens.
+pub fn tokens(expr: &str) -> Result<Vec<TokenInfo>, ExathError> {
+    let (toks, spans) = ast::tokenize_spanned(expr, &ParseConfig::default())?;
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out: Vec<TokenInfo> = Vec::with_capacity(toks.len());
+    for (i, (tok, &(start, end))) in toks.iter().zip(spans.iter()).enumerate() {
+        // `|` expands to `abs (`; report the bar once.
+        if out.last().is_some_and(|t| t.start == start && t.end == end) {
+            continue;
+        }
+        let text: String = chars[start..end].iter().collect();
+        let kind = if text == "|" {
+            TokenKind::AbsBar
+        } else {
+            match tok {
+                Token::Number(_) => TokenKind::Number,
+                Token::Ident(name) => {
+                    let called = matches!(toks.get(i + 1), Some(Token::LParen));
+                    classify_ident(name, called)
+                }
+                Token::LParen => TokenKind::LParen,
+                Token::RParen => TokenKind::RParen,
+                Token::LBracket => TokenKind::LBracket,
+                Token::RBracket => TokenKind::RBracket,
+                Token::Comma => TokenKind::Comma,
+                Token::Semicolon => TokenKind::Semicolon,
+                _ => TokenKind::Operator,
+            }
+        };
+        out.push(TokenInfo { kind, start, end, text });
+    }
+    Ok(out)
+}
+
+fn classify_ident(name: &str, called: bool) -> TokenKind {
+    match name {
+        _ if name.eq_ignore_ascii_case("mod") => TokenKind::Operator,
+        // Synthesized for a degrees-minutes-seconds literal such as `30°15'`.
+        "\u{00b0}" => TokenKind::Number,
+        "i" => TokenKind::Constant,
+        _ if ast::is_builtin_constant(name) => TokenKind::Constant,
+        _ if called || ast::is_function(name) || supported_functions().contains(&name) => {
+            TokenKind::Function
+        }
+        _ => TokenKind::Identifier,
+    }
+}
+
+#[cfg(test)]
+mod tokens_tests {
+    use super::*;
+
+    fn kinds(expr: &str) -> Vec<TokenKind> {
+        tokens(expr).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn classifies_and_locates_tokens() {
+        let toks = tokens("sin(x) + 2.5*pi").unwrap();
+        let summary: Vec<(TokenKind, usize, usize, &str)> = toks
+            .iter()
+            .map(|t| (t.kind, t.start, t.end, t.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TokenKind::Function, 0, 3, "sin"),
+                (TokenKind::LParen, 3, 4, "("),
+                (TokenKind::Identifier, 4, 5, "x"),
+                (TokenKind::RParen, 5, 6, ")"),
+                (TokenKind::Operator, 7, 8, "+"),
+                (TokenKind::Number, 9, 12, "2.5"),
+                (TokenKind::Operator, 12, 13, "*"),
+                (TokenKind::Constant, 13, 15, "pi"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_offsets_are_in_characters() {
+        let toks = tokens("√π × 2").unwrap();
+        assert_eq!(toks[0].kind, TokenKind::Function);
+        assert_eq!(toks[0].text, "√");
+        assert_eq!((toks[1].start, toks[1].end), (1, 2));
+        assert_eq!(toks[2].text, "×");
+        assert_eq!((toks[3].start, toks[3].end), (5, 6));
+    }
+
+    #[test]
+    fn abs_bars_and_incomplete_input() {
+        assert_eq!(
+            kinds("|2-3|"),
+            vec![
+                TokenKind::AbsBar,
+                TokenKind::Number,
+                TokenKind::Operator,
+                TokenKind::Number,
+                TokenKind::AbsBar,
+            ]
+        );
+        assert_eq!(kinds("f(2 +").len(), 4);
+        assert_eq!(kinds("f(2")[0], TokenKind::Function);
+        assert!(tokens("2 $ 3").is_err());
+    }
+}
+
+#[cfg(test)]
+mod complete_tests {
+    use super::*;
+
+    #[test]
+    fn prefix_matches_functions_and_constants() {
+        assert_eq!(complete("si"), ["sigmoid", "sign", "simplify", "sin", "sinh"]);
+        assert_eq!(complete("SI"), complete("si"));
+        assert_eq!(complete("ph"), ["phi"]);
+        assert!(complete("zz").is_empty());
+    }
+
+    #[test]
+    fn empty_prefix_lists_everything_sorted() {
+        let all = complete("");
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(all.contains(&"pi") && all.contains(&"sin") && all.contains(&"eigenvectors"));
+        assert_eq!(all.iter().filter(|name| **name == "abs").count(), 1);
+    }
+
+    #[test]
+    fn offered_constants_evaluate() {
+        for name in CONSTANTS {
+            assert!(crate::evaluate(name, AngleMode::Rad).is_ok(), "{}", name);
+        }
+        assert!(!complete("").contains(&"i"));
+    }
+}
+
+#[cfg(test)]
+mod help_tests {
+    use super::*;
+
+    #[test]
+    fn categories_cover_supported_functions_once() {
+        let mut categorized: Vec<&str> =
+            function_categories().iter().flat_map(|(_, names)| names.iter().copied()).collect();
+        let count = categorized.len();
+        categorized.sort_unstable();
+        categorized.dedup();
+        assert_eq!(categorized.len(), count, "a function is in two categories");
+        let mut supported = supported_functions().to_vec();
+        supported.sort_unstable();
+        supported.dedup();
+        assert_eq!(categorized, supported);
+    }
+
+    #[test]
+    fn every_supported_function_has_help() {
+        for name in supported_functions() {
+            let help = function_help(name).unwrap_or_else(|| panic!("no help for {}", name));
+            assert!(help.starts_with(name), "{}: {}", name, help);
+        }
+        for (name, _) in FUNCTION_HELP {
+            assert!(supported_functions().contains(name), "stale help entry {}", name);
+        }
+    }
+
+    #[test]
+    fn catalog_is_deduplicated() {
+        let catalog = function_catalog();
+        assert_eq!(catalog.iter().filter(|(name, _)| *name == "abs").count(), 1);
+        assert_eq!(catalog.len(), FUNCTION_HELP.len());
+    }
+}
+
+#[cfg(test)]
+mod free_variables_tests {
+    use super::free_variables;
+
+    #[test]
+    fn constants_are_not_free() {
+        assert_eq!(free_variables("G*m/r^2 + pi").unwrap(), vec!["G", "m", "r"]);
+        assert_eq!(free_variables("2*PI*e").unwrap(), Vec::<String>::new());
+        assert_eq!(free_variables("sin(x) + x").unwrap(), vec!["x"]);
+    }
+
+    #[test]
+    fn bound_variables_are_not_free() {
+        assert_eq!(free_variables("sum(k*x, k, 1, n)").unwrap(), vec!["n", "x"]);
+        assert_eq!(free_variables("deriv(t^2, t, a)").unwrap(), vec!["a"]);
+        // Outside the binder the same name is free again.
+        assert_eq!(free_variables("sum(k, k, 1, 3) + k").unwrap(), vec!["k"]);
+        assert!(free_variables("2 +").is_err());
+    }
+
+    #[test]
+    fn constant_expressions() {
+        use super::is_constant;
+        assert!(is_constant("2+3*pi").unwrap());
+        assert!(!is_constant("2+x").unwrap());
+        assert!(is_constant("sin(1)^2 + max(2, 3) + 30°").unwrap());
+        assert!(is_constant("sum(k^2, k, 1, 10)").unwrap());
+        assert!(!is_constant("sum(k^2, k, 1, n)").unwrap());
+        assert!(!is_constant("f(2)").unwrap());
+        assert!(!is_constant("rand()").unwrap());
+        assert!(is_constant("|-3|").unwrap());
+        assert!(is_constant("2 +").is_err());
+    }
+}
+
+#[cfg(test)]
+mod fraction_tests {
+    use super::to_fraction;
+
+    #[test]
+    fn simple_fractions() {
+        assert_eq!(to_fraction(0.5, 1000), Some((1, 2)));
+        assert_eq!(to_fraction(0.3333333333, 1000), Some((1, 3)));
+        assert_eq!(to_fraction(-2.75, 1000), Some((-11, 4)));
+        assert_eq!(to_fraction(3.0, 1000), Some((3, 1)));
+        assert_eq!(to_fraction(0.0, 10), Some((0, 1)));
+        assert_eq!(to_fraction(1.0 / 7.0 + 2.0, 10), Some((15, 7)));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_represent() {
+        assert_eq!(to_fraction(std::f64::consts::PI, 1000), None);
+        assert_eq!(to_fraction(2f64.sqrt(), 1000), None);
+        assert_eq!(to_fraction(1.0 / 3.0, 2), None);
+        assert_eq!(to_fraction(f64::NAN, 10), None);
+        assert_eq!(to_fraction(f64::INFINITY, 10), None);
+        assert_eq!(to_fraction(0.5, 0), None);
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+
+    fn ops(expr: &str) -> Vec<String> {
+        trace(expr, AngleMode::Rad).unwrap().into_iter().map(|s| s.op).collect()
+    }
+
+    #[test]
+    fn multiply_reduces_before_add() {
+        let steps = trace("2 + 3 * 4", AngleMode::Rad).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].op, "*");
+        assert_eq!(steps[0].operands, vec![Cx::real(3.0), Cx::real(4.0)]);
+        assert_eq!(steps[0].result, Cx::real(12.0));
+        assert_eq!(steps[1].op, "+");
+        assert_eq!(steps[1].operands, vec![Cx::real(2.0), Cx::real(12.0)]);
+        assert_eq!(steps[1].result, Cx::real(14.0));
+    }
+
+    #[test]
+    fn functions_and_unary_operators() {
+        assert_eq!(ops("sqrt(-4) * 2"), ["neg", "sqrt", "*"]);
+        assert_eq!(ops("3! + max(1, 2)"), ["!", "max", "+"]);
+        assert_eq!(ops("sum(k, k, 1, 3) + 1"), ["sum", "+"]);
+        let steps = trace("sqrt(-4) * 2", AngleMode::Rad).unwrap();
+        assert_eq!(steps[2].result, Cx::new(0.0, 4.0));
+    }
+
+    #[test]
+    fn short_circuit_skips_the_right_side() {
+        let steps = trace("0 && 1/0 > 1", AngleMode::Rad).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].operands, vec![Cx::real(0.0)]);
+        assert_eq!(ops("1 && 2 > 1"), [">", "&&"]);
+    }
+
+    #[test]
+    fn errors_stop_the_trace() {
+        assert!(trace("1/0 + 1", AngleMode::Rad).is_err());
+        assert!(trace("x + 1", AngleMode::Rad).is_err());
+    }
+}
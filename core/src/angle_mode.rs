@@ -26,7 +26,7 @@ impl AngleMode {
         match self {
             AngleMode::Deg => value.to_radians(),
             AngleMode::Rad => value,
-            AngleMode::Grad => value * std::f64::consts::PI / 200.0,
+            AngleMode::Grad => value * core::f64::consts::PI / 200.0,
         }
     }
 
@@ -34,7 +34,7 @@ impl AngleMode {
         match self {
             AngleMode::Deg => value.to_degrees(),
             AngleMode::Rad => value,
-            AngleMode::Grad => value * 200.0 / std::f64::consts::PI,
+            AngleMode::Grad => value * 200.0 / core::f64::consts::PI,
         }
     }
 }
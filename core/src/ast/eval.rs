@@ -1,61 +1,216 @@
 use crate::angle_mode::AngleMode;
+use crate::config::{DivByZero, EvalConfig, ModConvention};
 use crate::error::ExathError;
-use crate::evaluator::{Cx, apply_function, factorial};
+use crate::evaluator::{Cx, Rng, apply_function, factorial};
 use super::types::{Ast, BinOp};
-use std::collections::HashMap;
+use core::cell::RefCell;
+use core::slice;
+use alloc::sync::Arc;
+use crate::prelude::*;
 
 /// A map of user-defined functions: name → (parameter names, body AST).
 pub type UserFns = HashMap<String, (Vec<String>, Ast)>;
 
+/// A host function callable from expressions, see
+/// [`crate::Session::register_native`].
+pub type NativeFn = Arc<dyn Fn(&[Cx]) -> Result<Cx, ExathError> + Send + Sync>;
+
+/// A map of native functions: name → (arity, implementation).
+pub type NativeFns = HashMap<String, (usize, NativeFn)>;
+
+/// Default values of the trailing parameters of user functions, by function
+/// name. A call may leave those arguments out.
+pub(crate) type FnDefaults = HashMap<String, Vec<Ast>>;
+
 /// Evaluate an AST with a variable map and user-defined functions.
 pub fn eval_ast(
     ast: &Ast,
     vars: &HashMap<String, Cx>,
     fns: &UserFns,
     angle_mode: AngleMode,
+) -> Result<Cx, ExathError> {
+    eval_ast_with(ast, vars, fns, angle_mode, &EvalConfig::default())
+}
+
+/// Like [`eval_ast`], with explicit evaluation policies.
+pub fn eval_ast_with(
+    ast: &Ast,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    cfg: &EvalConfig,
+) -> Result<Cx, ExathError> {
+    eval_node(ast, &Scope::Root(vars), fns, angle_mode, &EvalCtx::new(cfg, None))
+}
+
+/// Like [`eval_ast_with`], also resolving calls to native functions. They are
+/// consulted after user-defined functions and before built-ins. `rand` and
+/// `randint` draw from `rng`.
+#[cfg(feature = "std")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn eval_ast_native(
+    ast: &Ast,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+    natives: &NativeFns,
+    defaults: &FnDefaults,
+    rng: &Rng,
+    angle_mode: AngleMode,
+    cfg: &EvalConfig,
+) -> Result<Cx, ExathError> {
+    let mut ctx = EvalCtx::new(cfg, Some(natives));
+    ctx.defaults = Some(defaults);
+    ctx.rng = Some(rng);
+    eval_node(ast, &Scope::Root(vars), fns, angle_mode, &ctx)
+}
+
+/// The variables a node sees: the caller's map, with the bindings of the
+/// user-function calls and `sum` / `product` / `deriv` loops in progress
+/// layered on top. A call adds one frame instead of cloning the map, so deep
+/// recursion such as `fact(100)` costs memory per level only for the
+/// parameters.
+enum Scope<'a> {
+    Root(&'a HashMap<String, Cx>),
+    Frame {
+        names: &'a [String],
+        values: &'a [Cx],
+        parent: &'a Scope<'a>,
+    },
+}
+
+impl Scope<'_> {
+    fn get(&self, name: &str) -> Option<Cx> {
+        let mut scope = self;
+        loop {
+            match scope {
+                Scope::Root(vars) => return vars.get(name).copied(),
+                Scope::Frame { names, values, parent } => {
+                    // The last binding wins, as with repeated map inserts.
+                    if let Some(i) = names.iter().rposition(|n| n == name) {
+                        return Some(values[i]);
+                    }
+                    scope = parent;
+                }
+            }
+        }
+    }
+}
+
+/// Per-evaluation state shared by every node of one top-level evaluation.
+struct EvalCtx<'a> {
+    cfg: &'a EvalConfig,
+    natives: Option<&'a NativeFns>,
+    defaults: Option<&'a FnDefaults>,
+    /// Source of `rand` and `randint`; only a session provides one.
+    rng: Option<&'a Rng>,
+    /// Results of pure user-function calls, keyed on (name, argument bits).
+    memo: RefCell<HashMap<(String, Vec<u64>), Cx>>,
+    /// Whether each user function is pure (see [`is_pure_fn`]).
+    purity: RefCell<HashMap<String, bool>>,
+}
+
+impl<'a> EvalCtx<'a> {
+    fn new(cfg: &'a EvalConfig, natives: Option<&'a NativeFns>) -> Self {
+        EvalCtx {
+            cfg,
+            natives,
+            defaults: None,
+            rng: None,
+            memo: RefCell::new(HashMap::new()),
+            purity: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The defaults of `name`'s trailing parameters, if any.
+    fn defaults_of(&self, name: &str) -> &'a [Ast] {
+        self.defaults
+            .and_then(|defaults| defaults.get(name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn is_pure(&self, name: &str, fns: &UserFns) -> bool {
+        if let Some(&pure) = self.purity.borrow().get(name) {
+            return pure;
+        }
+        let pure = is_pure_fn(name, fns, self.natives, &mut Vec::new());
+        self.purity.borrow_mut().insert(name.to_string(), pure);
+        pure
+    }
+}
+
+fn eval_node(
+    ast: &Ast,
+    vars: &Scope,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    ctx: &EvalCtx,
+) -> Result<Cx, ExathError> {
+    let value = eval_node_unchecked(ast, vars, fns, angle_mode, ctx)?;
+    if ctx.cfg.strict_finite && !(value.re.is_finite() && value.im.is_finite()) {
+        return Err(ExathError::domain(format!(
+            "Non-finite intermediate value {} (strict finite mode)",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+fn eval_node_unchecked(
+    ast: &Ast,
+    vars: &Scope,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    ctx: &EvalCtx,
 ) -> Result<Cx, ExathError> {
     match ast {
         Ast::Number(value) => Ok(Cx::real(*value)),
 
         Ast::Var(name) => vars
             .get(name)
-            .copied()
             .ok_or_else(|| ExathError::undefined(format!("Undefined variable: {}", name))),
 
         Ast::BinOp(op, left_ast, right_ast) => {
             // Short-circuit for logical operators
             match op {
                 BinOp::And => {
-                    let left = eval_ast(left_ast, vars, fns, angle_mode)?;
+                    let left = eval_node(left_ast, vars, fns, angle_mode, ctx)?;
                     if left.re == 0.0 && left.im == 0.0 {
                         return Ok(Cx::real(0.0));
                     }
-                    let right = eval_ast(right_ast, vars, fns, angle_mode)?;
+                    let right = eval_node(right_ast, vars, fns, angle_mode, ctx)?;
                     let truthy = right.re != 0.0 || right.im != 0.0;
                     return Ok(Cx::real(if truthy { 1.0 } else { 0.0 }));
                 }
                 BinOp::Or => {
-                    let left = eval_ast(left_ast, vars, fns, angle_mode)?;
+                    let left = eval_node(left_ast, vars, fns, angle_mode, ctx)?;
                     if left.re != 0.0 || left.im != 0.0 {
                         return Ok(Cx::real(1.0));
                     }
-                    let right = eval_ast(right_ast, vars, fns, angle_mode)?;
+                    let right = eval_node(right_ast, vars, fns, angle_mode, ctx)?;
                     let truthy = right.re != 0.0 || right.im != 0.0;
                     return Ok(Cx::real(if truthy { 1.0 } else { 0.0 }));
                 }
                 _ => {}
             }
 
-            let left = eval_ast(left_ast, vars, fns, angle_mode)?;
-            let right = eval_ast(right_ast, vars, fns, angle_mode)?;
+            let left = eval_node(left_ast, vars, fns, angle_mode, ctx)?;
+            let right = eval_node(right_ast, vars, fns, angle_mode, ctx)?;
             match op {
                 BinOp::Add => Ok(left.add(right)),
                 BinOp::Sub => Ok(left.sub(right)),
                 BinOp::Mul => Ok(left.mul(right)),
-                BinOp::Div => left.div(right),
-                BinOp::Pow => left.pow(right),
+                BinOp::Div => {
+                    if ctx.cfg.div_by_zero == DivByZero::Ieee && right.re == 0.0 && right.im == 0.0 {
+                        return Ok(ieee_div_by_zero(left, right));
+                    }
+                    left.div(right)
+                }
+                BinOp::Pow => left.pow_in(right, ctx.cfg.branch),
                 BinOp::Mod => {
                     if right.re == 0.0 && right.im == 0.0 {
+                        if ctx.cfg.div_by_zero == DivByZero::Ieee {
+                            return Ok(Cx::real(f64::NAN));
+                        }
                         return Err(ExathError::domain("Modulo by zero"));
                     }
                     if !right.is_real() {
@@ -63,38 +218,53 @@ pub fn eval_ast(
                             "Modulo only defined for real numbers",
                         ));
                     }
-                    Ok(Cx::real(left.re % right.re))
+                    Ok(Cx::real(modulo(left.re, right.re, ctx.cfg.modulo)))
+                }
+                BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                    compare(op, left, right)
                 }
-                BinOp::Eq => cmp_op(left, right, |a, b| (a - b).abs() < 1e-12),
-                BinOp::Ne => cmp_op(left, right, |a, b| (a - b).abs() >= 1e-12),
-                BinOp::Lt => cmp_op(left, right, |a, b| a < b),
-                BinOp::Le => cmp_op(left, right, |a, b| a <= b),
-                BinOp::Gt => cmp_op(left, right, |a, b| a > b),
-                BinOp::Ge => cmp_op(left, right, |a, b| a >= b),
                 BinOp::And | BinOp::Or => unreachable!(),
             }
         }
 
+        Ast::Chain(ops, operands) => {
+            // Each operand once, so `0 < rand() < 1` draws a single number.
+            let mut left = eval_node(&operands[0], vars, fns, angle_mode, ctx)?;
+            for (op, right_ast) in ops.iter().zip(&operands[1..]) {
+                let right = eval_node(right_ast, vars, fns, angle_mode, ctx)?;
+                if compare(op, left, right)?.re == 0.0 {
+                    return Ok(Cx::real(0.0));
+                }
+                left = right;
+            }
+            Ok(Cx::real(1.0))
+        }
+
         Ast::UnaryNeg(inner) => {
-            Ok(eval_ast(inner, vars, fns, angle_mode)?.neg())
+            Ok(eval_node(inner, vars, fns, angle_mode, ctx)?.neg())
         }
 
         Ast::UnaryNot(inner) => {
-            let value = eval_ast(inner, vars, fns, angle_mode)?;
+            let value = eval_node(inner, vars, fns, angle_mode, ctx)?;
             let is_zero = value.re == 0.0 && value.im == 0.0;
             Ok(Cx::real(if is_zero { 1.0 } else { 0.0 }))
         }
 
         Ast::Factorial(inner) => {
-            let value = eval_ast(inner, vars, fns, angle_mode)?;
+            let value = eval_node(inner, vars, fns, angle_mode, ctx)?;
             if !value.is_real() {
                 return Err(ExathError::arg_type("Factorial only for real numbers"));
             }
+            // Non-integers use the gamma function, n! = Γ(n + 1), as on
+            // scientific calculators. Integers keep the exact product.
+            if value.re > 0.0 && value.re.fract() != 0.0 {
+                return apply_function("gamma", Cx::real(value.re + 1.0), angle_mode);
+            }
             Ok(Cx::real(factorial(value.re)?))
         }
 
         Ast::Call(name, args) => {
-            eval_call(name, args, vars, fns, angle_mode)
+            eval_call(name, args, vars, fns, angle_mode, ctx)
         }
 
         Ast::Matrix(_) => Err(ExathError::domain(
@@ -107,13 +277,16 @@ pub fn eval_ast(
 fn eval_call(
     name: &str,
     args: &[Ast],
-    vars: &HashMap<String, Cx>,
+    vars: &Scope,
     fns: &UserFns,
     angle_mode: AngleMode,
+    ctx: &EvalCtx,
 ) -> Result<Cx, ExathError> {
     // User-defined functions
     if let Some((params, body)) = fns.get(name) {
-        if args.len() != params.len() {
+        let defaults = ctx.defaults_of(name);
+        let required = params.len() - defaults.len();
+        if args.len() > params.len() || (defaults.is_empty() && args.len() != params.len()) {
             return Err(ExathError::arg_count(format!(
                 "{}() expects {} argument(s), got {}",
                 name,
@@ -121,30 +294,73 @@ fn eval_call(
                 args.len()
             )));
         }
-        let mut call_vars = vars.clone();
-        for (param, arg_ast) in params.iter().zip(args.iter()) {
-            let value = eval_ast(arg_ast, vars, fns, angle_mode)?;
-            call_vars.insert(param.clone(), value);
+        if args.len() < required {
+            return Err(ExathError::arg_count(format!(
+                "{}() is missing argument '{}'",
+                name,
+                params[args.len()]
+            )));
+        }
+        let mut values = Vec::with_capacity(params.len());
+        for arg_ast in args {
+            values.push(eval_node(arg_ast, vars, fns, angle_mode, ctx)?);
+        }
+        // Left-out trailing arguments take their defaults, evaluated in the
+        // caller's scope.
+        for default in &defaults[args.len() - required..] {
+            values.push(eval_node(default, vars, fns, angle_mode, ctx)?);
         }
-        return eval_ast(body, &call_vars, fns, angle_mode);
+        return call_user_fn(name, params, body, values, vars, fns, angle_mode, ctx);
     }
 
-    // Multi-argument / control-flow built-in functions
-    match name {
-        "if" => {
-            if args.len() != 3 {
-                return Err(ExathError::arg_count(
-                    "if requires 3 arguments: if(condition, true_value, false_value)",
-                ));
-            }
-            let condition = eval_ast(&args[0], vars, fns, angle_mode)?;
-            if condition.re != 0.0 || condition.im != 0.0 {
-                eval_ast(&args[1], vars, fns, angle_mode)
-            } else {
-                eval_ast(&args[2], vars, fns, angle_mode)
-            }
+    // Native (host) functions
+    if let Some((arity, native)) = ctx.natives.and_then(|natives| natives.get(name)) {
+        if args.len() != *arity {
+            return Err(ExathError::arg_count(format!(
+                "{}() expects {} argument(s), got {}",
+                name,
+                arity,
+                args.len()
+            )));
+        }
+        let mut values = Vec::with_capacity(args.len());
+        for arg_ast in args {
+            values.push(eval_node(arg_ast, vars, fns, angle_mode, ctx)?);
+        }
+        return native(&values);
+    }
+
+    // `if` is checked here rather than in `eval_builtin` so a recursive
+    // user function, which always passes through it, does not also pay for
+    // that function's large stack frame on every level.
+    if name == "if" {
+        if args.len() != 3 {
+            return Err(ExathError::arg_count(
+                "if requires 3 arguments: if(condition, true_value, false_value)",
+            ));
         }
+        let condition = eval_node(&args[0], vars, fns, angle_mode, ctx)?;
+        return if condition.re != 0.0 || condition.im != 0.0 {
+            eval_node(&args[1], vars, fns, angle_mode, ctx)
+        } else {
+            eval_node(&args[2], vars, fns, angle_mode, ctx)
+        };
+    }
 
+    eval_builtin(name, args, vars, fns, angle_mode, ctx)
+}
+
+/// Evaluate a call to a built-in function other than `if`.
+fn eval_builtin(
+    name: &str,
+    args: &[Ast],
+    vars: &Scope,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    ctx: &EvalCtx,
+) -> Result<Cx, ExathError> {
+    // Multi-argument / control-flow built-in functions
+    match name {
         "piecewise" => {
             // piecewise(c1, v1, c2, v2, ..., default): first true condition wins.
             if args.len() < 3 || args.len() % 2 == 0 {
@@ -154,22 +370,22 @@ fn eval_call(
             }
             let mut i = 0;
             while i + 1 < args.len() {
-                let cond = eval_ast(&args[i], vars, fns, angle_mode)?;
+                let cond = eval_node(&args[i], vars, fns, angle_mode, ctx)?;
                 if cond.re != 0.0 || cond.im != 0.0 {
-                    return eval_ast(&args[i + 1], vars, fns, angle_mode);
+                    return eval_node(&args[i + 1], vars, fns, angle_mode, ctx);
                 }
                 i += 2;
             }
-            eval_ast(&args[args.len() - 1], vars, fns, angle_mode)
+            eval_node(&args[args.len() - 1], vars, fns, angle_mode, ctx)
         }
 
         "min" => {
             if args.is_empty() {
                 return Err(ExathError::arg_count("min requires at least one argument"));
             }
-            let mut best = eval_real_arg(&args[0], vars, fns, angle_mode, "min")?;
-            for arg in &args[1..] {
-                let value = eval_real_arg(arg, vars, fns, angle_mode, "min")?;
+            let mut best = eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, "min", 1)?;
+            for (i, arg) in args.iter().enumerate().skip(1) {
+                let value = eval_real_arg_at(arg, vars, fns, angle_mode, ctx, "min", i + 1)?;
                 if value < best {
                     best = value;
                 }
@@ -181,9 +397,9 @@ fn eval_call(
             if args.is_empty() {
                 return Err(ExathError::arg_count("max requires at least one argument"));
             }
-            let mut best = eval_real_arg(&args[0], vars, fns, angle_mode, "max")?;
-            for arg in &args[1..] {
-                let value = eval_real_arg(arg, vars, fns, angle_mode, "max")?;
+            let mut best = eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, "max", 1)?;
+            for (i, arg) in args.iter().enumerate().skip(1) {
+                let value = eval_real_arg_at(arg, vars, fns, angle_mode, ctx, "max", i + 1)?;
                 if value > best {
                     best = value;
                 }
@@ -191,24 +407,63 @@ fn eval_call(
             Ok(Cx::real(best))
         }
 
+        "rand" | "randint" => {
+            let rng = ctx.rng.ok_or_else(|| {
+                ExathError::domain(format!(
+                    "{}() needs a random number generator: evaluate it in a Session",
+                    name
+                ))
+            })?;
+            if name == "rand" {
+                if !args.is_empty() {
+                    return Err(ExathError::arg_count("rand takes no arguments: rand()"));
+                }
+                return Ok(Cx::real(rng.next_f64()));
+            }
+            if args.len() != 2 {
+                return Err(ExathError::arg_count(
+                    "randint requires 2 arguments: randint(min, max)",
+                ));
+            }
+            let lo = to_integer(eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, name, 1)?, name)?;
+            let hi = to_integer(eval_real_arg_at(&args[1], vars, fns, angle_mode, ctx, name, 2)?, name)?;
+            if lo > hi {
+                return Err(ExathError::domain(format!(
+                    "randint: min {} is greater than max {}",
+                    lo, hi
+                )));
+            }
+            Ok(Cx::real(rng.next_in(lo, hi) as f64))
+        }
+
         "clamp" => {
             if args.len() != 3 {
                 return Err(ExathError::arg_count(
                     "clamp requires 3 arguments: clamp(x, min, max)",
                 ));
             }
-            let value = eval_real_arg(&args[0], vars, fns, angle_mode, "clamp")?;
-            let lower = eval_real_arg(&args[1], vars, fns, angle_mode, "clamp")?;
-            let upper = eval_real_arg(&args[2], vars, fns, angle_mode, "clamp")?;
-            Ok(Cx::real(value.max(lower).min(upper)))
+            let value = eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, "clamp", 1)?;
+            let lower = eval_real_arg_at(&args[1], vars, fns, angle_mode, ctx, "clamp", 2)?;
+            let upper = eval_real_arg_at(&args[2], vars, fns, angle_mode, ctx, "clamp", 3)?;
+            if lower.is_nan() || upper.is_nan() {
+                return Err(ExathError::domain("clamp bounds must not be NaN"));
+            }
+            if lower > upper {
+                return Err(ExathError::domain(format!(
+                    "clamp lower bound exceeds upper bound ({} > {})",
+                    lower, upper
+                )));
+            }
+            // A NaN value propagates, like any other arithmetic on NaN.
+            Ok(Cx::real(value.clamp(lower, upper)))
         }
 
         "gcd" => {
             if args.len() != 2 {
                 return Err(ExathError::arg_count("gcd requires 2 arguments"));
             }
-            let a = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "gcd")?, "gcd")?;
-            let b = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, "gcd")?, "gcd")?;
+            let a = to_integer(eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, "gcd", 1)?, "gcd")?;
+            let b = to_integer(eval_real_arg_at(&args[1], vars, fns, angle_mode, ctx, "gcd", 2)?, "gcd")?;
             Ok(Cx::real(gcd(a.abs(), b.abs()) as f64))
         }
 
@@ -216,16 +471,38 @@ fn eval_call(
             if args.len() != 2 {
                 return Err(ExathError::arg_count("lcm requires 2 arguments"));
             }
-            let a = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "lcm")?, "lcm")?;
-            let b = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, "lcm")?, "lcm")?;
+            let a = to_integer(eval_real_arg_at(&args[0], vars, fns, angle_mode, ctx, "lcm", 1)?, "lcm")?;
+            let b = to_integer(eval_real_arg_at(&args[1], vars, fns, angle_mode, ctx, "lcm", 2)?, "lcm")?;
             let divisor = gcd(a.abs(), b.abs());
             if divisor == 0 {
                 return Ok(Cx::real(0.0));
             }
             let result = (a as i128 / divisor as i128 * b as i128).unsigned_abs();
+            if result > MAX_EXACT_INT as u128 {
+                return Err(ExathError::overflow(
+                    "lcm result exceeds the exact integer range of a float (2^53)",
+                ));
+            }
             Ok(Cx::real(result as f64))
         }
 
+        "idiv" | "rem" => {
+            // Floor division and the matching remainder: a = b·idiv(a, b) + rem(a, b),
+            // so rem takes the sign of the divisor (idiv(-7, 2) = -4, rem(-7, 2) = 1).
+            if args.len() != 2 {
+                return Err(ExathError::arg_count(format!(
+                    "{} requires 2 arguments: {}(a, b)", name, name
+                )));
+            }
+            let a = eval_real_arg(&args[0], vars, fns, angle_mode, ctx, name)?;
+            let b = eval_real_arg(&args[1], vars, fns, angle_mode, ctx, name)?;
+            if b == 0.0 {
+                return Err(ExathError::domain(format!("{}: division by zero", name)));
+            }
+            let q = (a / b).floor();
+            Ok(Cx::real(if name == "idiv" { q } else { a - b * q }))
+        }
+
         // ── Numerical sum / product / derivative + unit conversion (DSL) ──────
         "sum" | "product" if args.len() == 4 => {
             // sum(expr, var, from, to), integer-stepped accumulation.
@@ -233,40 +510,81 @@ fn eval_call(
                 Ast::Var(name) => name.clone(),
                 _ => return Err(ExathError::arg_type(format!("{}: 2nd argument must be a variable", name))),
             };
-            let from = to_integer(eval_real_arg(&args[2], vars, fns, angle_mode, name)?, name)?;
-            let to = to_integer(eval_real_arg(&args[3], vars, fns, angle_mode, name)?, name)?;
-            if (to - from).abs() > 10_000_000 {
+            let from = to_integer(eval_real_arg(&args[2], vars, fns, angle_mode, ctx, name)?, name)?;
+            let to = to_integer(eval_real_arg(&args[3], vars, fns, angle_mode, ctx, name)?, name)?;
+            // `to < from` is an empty range: 0 for a sum, 1 for a product.
+            if to as i128 - from as i128 > 10_000_000 {
                 return Err(ExathError::domain(format!("{}: range too large", name)));
             }
             let mut acc = if name == "sum" { 0.0 } else { 1.0 };
-            let mut local = vars.clone();
             let mut k = from;
             while k <= to {
-                local.insert(v.clone(), Cx::real(k as f64));
-                let term = eval_ast(&args[0], &local, fns, angle_mode)?.re;
+                let value = [Cx::real(k as f64)];
+                let local = Scope::Frame { names: slice::from_ref(&v), values: &value, parent: vars };
+                let term = eval_node(&args[0], &local, fns, angle_mode, ctx)?.re;
                 if name == "sum" { acc += term } else { acc *= term }
+                // A zero product stays zero; skip the remaining factors.
+                if name == "product" && acc == 0.0 {
+                    break;
+                }
                 k += 1;
             }
             Ok(Cx::real(acc))
         }
+        "fold" => {
+            // fold(from, to, init, f): acc = f(acc, i) for each integer i in
+            // from..=to, with f a user function of two arguments.
+            if args.len() != 4 {
+                return Err(ExathError::arg_count(
+                    "fold requires 4 arguments: fold(from, to, init, f)",
+                ));
+            }
+            let fname = match &args[3] {
+                Ast::Var(n) => n.as_str(),
+                _ => return Err(ExathError::arg_type("fold: 4th argument must be a function name")),
+            };
+            let (params, body) = fns.get(fname).ok_or_else(|| {
+                ExathError::undefined(format!("fold: undefined function '{}'", fname))
+            })?;
+            if params.len() != 2 {
+                return Err(ExathError::arg_count(format!(
+                    "fold: {}() must take 2 arguments (acc, i), it takes {}",
+                    fname,
+                    params.len()
+                )));
+            }
+            let from = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "fold")?, "fold")?;
+            let to = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, ctx, "fold")?, "fold")?;
+            if to - from > 10_000_000 {
+                return Err(ExathError::range_too_large("fold: range too large"));
+            }
+            let mut acc = eval_node(&args[2], vars, fns, angle_mode, ctx)?;
+            for i in from..=to {
+                let values = vec![acc, Cx::real(i as f64)];
+                acc = call_user_fn(fname, params, body, values, vars, fns, angle_mode, ctx)?;
+            }
+            Ok(acc)
+        }
         "deriv" if args.len() == 3 => {
             // deriv(expr, var, x0), central finite difference.
             let v = match &args[1] {
                 Ast::Var(name) => name.clone(),
                 _ => return Err(ExathError::arg_type("deriv: 2nd argument must be a variable")),
             };
-            let x0 = eval_real_arg(&args[2], vars, fns, angle_mode, "deriv")?;
+            let x0 = eval_real_arg(&args[2], vars, fns, angle_mode, ctx, "deriv")?;
             let h = (x0.abs() * 1e-7).max(1e-10);
-            let mut local = vars.clone();
-            local.insert(v.clone(), Cx::real(x0 + h));
-            let fwd = eval_ast(&args[0], &local, fns, angle_mode)?.re;
-            local.insert(v.clone(), Cx::real(x0 - h));
-            let bwd = eval_ast(&args[0], &local, fns, angle_mode)?.re;
+            let at = |x: f64| {
+                let value = [Cx::real(x)];
+                let local = Scope::Frame { names: slice::from_ref(&v), values: &value, parent: vars };
+                eval_node(&args[0], &local, fns, angle_mode, ctx).map(|y| y.re)
+            };
+            let fwd = at(x0 + h)?;
+            let bwd = at(x0 - h)?;
             Ok(Cx::real((fwd - bwd) / (2.0 * h)))
         }
         "convert" if args.len() == 3 => {
             // convert(value, fromUnit, toUnit), unit names as identifiers.
-            let value = eval_real_arg(&args[0], vars, fns, angle_mode, "convert")?;
+            let value = eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "convert")?;
             let unit_name = |a: &Ast| -> Result<String, ExathError> {
                 match a {
                     Ast::Var(n) => Ok(n.clone()),
@@ -285,7 +603,7 @@ fn eval_call(
             }
             let mut xs = Vec::with_capacity(args.len());
             for a in args {
-                xs.push(eval_real_arg(a, vars, fns, angle_mode, name)?);
+                xs.push(eval_real_arg(a, vars, fns, angle_mode, ctx, name)?);
             }
             let n = xs.len() as f64;
             let mean = xs.iter().sum::<f64>() / n;
@@ -293,7 +611,7 @@ fn eval_call(
                 "mean" => mean,
                 "median" => {
                     let mut s = xs.clone();
-                    s.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                    s.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
                     let m = s.len() / 2;
                     if s.len() % 2 == 0 { (s[m - 1] + s[m]) / 2.0 } else { s[m] }
                 }
@@ -313,17 +631,17 @@ fn eval_call(
                     "{} requires 3 arguments: {}(x, mu, sigma)", name, name
                 )));
             }
-            let x = eval_real_arg(&args[0], vars, fns, angle_mode, name)?;
-            let mu = eval_real_arg(&args[1], vars, fns, angle_mode, name)?;
-            let sigma = eval_real_arg(&args[2], vars, fns, angle_mode, name)?;
+            let x = eval_real_arg(&args[0], vars, fns, angle_mode, ctx, name)?;
+            let mu = eval_real_arg(&args[1], vars, fns, angle_mode, ctx, name)?;
+            let sigma = eval_real_arg(&args[2], vars, fns, angle_mode, ctx, name)?;
             if sigma <= 0.0 {
                 return Err(ExathError::domain(format!("{}: sigma must be positive", name)));
             }
             let z = (x - mu) / sigma;
             let value = if name == "npdf" {
-                (-0.5 * z * z).exp() / (sigma * (2.0 * std::f64::consts::PI).sqrt())
+                (-0.5 * z * z).exp() / (sigma * (2.0 * core::f64::consts::PI).sqrt())
             } else {
-                let e = apply_function("erf", Cx::real(z / std::f64::consts::SQRT_2), angle_mode)?.re;
+                let e = apply_function("erf", Cx::real(z / core::f64::consts::SQRT_2), angle_mode)?.re;
                 0.5 * (1.0 + e)
             };
             Ok(Cx::real(value))
@@ -332,8 +650,8 @@ fn eval_call(
             if args.len() != 2 {
                 return Err(ExathError::arg_count("binom requires 2 arguments: binom(n, k)"));
             }
-            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "binom")?, "binom")?;
-            let k = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, "binom")?, "binom")?;
+            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "binom")?, "binom")?;
+            let k = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, ctx, "binom")?, "binom")?;
             if k < 0 || n < 0 || k > n {
                 return Ok(Cx::real(0.0));
             }
@@ -348,8 +666,8 @@ fn eval_call(
             if args.len() != 2 {
                 return Err(ExathError::arg_count("beta requires 2 arguments: beta(a, b)"));
             }
-            let a = eval_real_arg(&args[0], vars, fns, angle_mode, "beta")?;
-            let b = eval_real_arg(&args[1], vars, fns, angle_mode, "beta")?;
+            let a = eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "beta")?;
+            let b = eval_real_arg(&args[1], vars, fns, angle_mode, ctx, "beta")?;
             // B(a,b) = Γ(a)Γ(b)/Γ(a+b)
             let ga = apply_function("gamma", Cx::real(a), angle_mode)?.re;
             let gb = apply_function("gamma", Cx::real(b), angle_mode)?.re;
@@ -359,18 +677,18 @@ fn eval_call(
 
         // ── Number theory (integer arguments, within i128 range) ──────────────
         "isprime" => {
-            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "isprime")?, "isprime")?;
+            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "isprime")?, "isprime")?;
             Ok(Cx::real(if is_prime(n) { 1.0 } else { 0.0 }))
         }
         "nextprime" => {
-            let mut n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "nextprime")?, "nextprime")? + 1;
+            let mut n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "nextprime")?, "nextprime")? + 1;
             while !is_prime(n) {
                 n += 1;
             }
             Ok(Cx::real(n as f64))
         }
         "totient" => {
-            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "totient")?, "totient")?;
+            let n = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "totient")?, "totient")?;
             if n < 1 {
                 return Err(ExathError::domain("totient requires a positive integer"));
             }
@@ -380,9 +698,9 @@ fn eval_call(
             if args.len() != 3 {
                 return Err(ExathError::arg_count("powmod requires 3 arguments: powmod(base, exp, m)"));
             }
-            let a = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, "powmod")?, "powmod")?;
-            let e = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, "powmod")?, "powmod")?;
-            let m = to_integer(eval_real_arg(&args[2], vars, fns, angle_mode, "powmod")?, "powmod")?;
+            let a = to_integer(eval_real_arg(&args[0], vars, fns, angle_mode, ctx, "powmod")?, "powmod")?;
+            let e = to_integer(eval_real_arg(&args[1], vars, fns, angle_mode, ctx, "powmod")?, "powmod")?;
+            let m = to_integer(eval_real_arg(&args[2], vars, fns, angle_mode, ctx, "powmod")?, "powmod")?;
             if m <= 0 || e < 0 {
                 return Err(ExathError::domain("powmod requires modulus > 0 and exponent >= 0"));
             }
@@ -397,20 +715,138 @@ fn eval_call(
                     name
                 )));
             }
-            let value = eval_ast(&args[0], vars, fns, angle_mode)?;
-            apply_function(name, value, angle_mode)
+            let value = eval_node(&args[0], vars, fns, angle_mode, ctx)?;
+            if ctx.cfg.complex_rounding
+                && !value.is_real()
+                && matches!(name, "round" | "floor" | "ceil" | "trunc")
+            {
+                let re = apply_function(name, Cx::real(value.re), angle_mode)?.re;
+                let im = apply_function(name, Cx::real(value.im), angle_mode)?.re;
+                return Ok(Cx { re, im });
+            }
+            match name {
+                "ln" => value.ln_in(ctx.cfg.branch),
+                "sqrt" => Ok(value.sqrt_in(ctx.cfg.branch)),
+                _ => apply_function(name, value, angle_mode),
+            }
+        }
+    }
+}
+
+/// Apply a user function to already-evaluated arguments.
+#[allow(clippy::too_many_arguments)]
+fn call_user_fn(
+    name: &str,
+    params: &[String],
+    body: &Ast,
+    values: Vec<Cx>,
+    vars: &Scope,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    ctx: &EvalCtx,
+) -> Result<Cx, ExathError> {
+    // A pure function's result depends only on its arguments, so repeated
+    // calls (e.g. a naive recursive fib) are answered from the memo.
+    // The key is the raw bits, not `Cx` equality, which treats -0 as 0:
+    // `1/x` tells them apart under `DivByZero::Ieee`.
+    let key = ctx.is_pure(name, fns).then(|| {
+        let bits = values.iter().flat_map(|v| [v.re.to_bits(), v.im.to_bits()]).collect();
+        (name.to_string(), bits)
+    });
+    if let Some(key) = &key {
+        if let Some(&hit) = ctx.memo.borrow().get(key) {
+            return Ok(hit);
         }
     }
+    let call_vars = Scope::Frame { names: params, values: &values, parent: vars };
+    let result = eval_node(body, &call_vars, fns, angle_mode, ctx)?;
+    if let Some(key) = key {
+        ctx.memo.borrow_mut().insert(key, result);
+    }
+    Ok(result)
+}
+
+/// A user function is pure when its body reads only its parameters and calls
+/// only built-ins or other pure user functions. `stack` holds the functions
+/// being checked, so (mutually) recursive definitions are not rejected.
+fn is_pure_fn(
+    name: &str,
+    fns: &UserFns,
+    natives: Option<&NativeFns>,
+    stack: &mut Vec<String>,
+) -> bool {
+    let Some((params, body)) = fns.get(name) else {
+        // Host functions may read external state and random draws differ per
+        // call; other built-ins are pure.
+        return !natives.is_some_and(|natives| natives.contains_key(name)) && !is_random(name);
+    };
+    if stack.iter().any(|n| n == name) {
+        return true;
+    }
+    if !super::collect_vars(body).iter().all(|v| params.contains(v)) {
+        return false;
+    }
+    stack.push(name.to_string());
+    let pure = calls_pure_fns(body, fns, natives, stack);
+    stack.pop();
+    pure
+}
+
+fn calls_pure_fns(
+    ast: &Ast,
+    fns: &UserFns,
+    natives: Option<&NativeFns>,
+    stack: &mut Vec<String>,
+) -> bool {
+    let pure = |a: &Ast, stack: &mut Vec<String>| calls_pure_fns(a, fns, natives, stack);
+    match ast {
+        Ast::Number(_) | Ast::Var(_) => true,
+        Ast::BinOp(_, l, r) => pure(l, stack) && pure(r, stack),
+        Ast::UnaryNeg(u) | Ast::UnaryNot(u) | Ast::Factorial(u) => pure(u, stack),
+        Ast::Call(callee, args) => {
+            is_pure_fn(callee, fns, natives, stack) && args.iter().all(|a| pure(a, stack))
+        }
+        Ast::Chain(_, operands) => operands.iter().all(|a| pure(a, stack)),
+        Ast::Matrix(rows) => rows.iter().flatten().all(|e| pure(e, stack)),
+    }
+}
+
+/// Whether `name` is a built-in that draws a new random number per call.
+pub(crate) fn is_random(name: &str) -> bool {
+    matches!(name, "rand" | "randint")
+}
+
+/// `a % b` under `convention`, for a nonzero `b`.
+fn modulo(a: f64, b: f64, convention: ModConvention) -> f64 {
+    let r = a % b;
+    match convention {
+        ModConvention::Truncated => r,
+        ModConvention::Euclidean if r < 0.0 => r + b.abs(),
+        ModConvention::Floored if r != 0.0 && (r < 0.0) != (b < 0.0) => r + b,
+        ModConvention::Euclidean | ModConvention::Floored => r,
+    }
+}
+
+/// `x / 0` under IEEE 754 rules: signed infinity per component, NaN for `0/0`.
+fn ieee_div_by_zero(left: Cx, right: Cx) -> Cx {
+    if left.im == 0.0 {
+        return Cx::real(left.re / right.re);
+    }
+    Cx {
+        re: left.re / right.re,
+        im: left.im / right.re,
+    }
 }
 
 fn eval_real_arg(
     ast: &Ast,
-    vars: &HashMap<String, Cx>,
+    vars: &Scope,
     fns: &UserFns,
     angle_mode: AngleMode,
+    ctx: &EvalCtx,
     fname: &str,
 ) -> Result<f64, ExathError> {
-    let value = eval_ast(ast, vars, fns, angle_mode)?;
+    let value = eval_node(ast, vars, fns, angle_mode, ctx)?;
     if !value.is_real() {
         return Err(ExathError::arg_type(format!(
             "{} only defined for real arguments",
@@ -420,6 +856,43 @@ fn eval_real_arg(
     Ok(value.re)
 }
 
+/// Like [`eval_real_arg`] for one argument of a multi-argument built-in;
+/// the error names the 1-based argument `index` and the offending value.
+#[allow(clippy::too_many_arguments)]
+fn eval_real_arg_at(
+    ast: &Ast,
+    vars: &Scope,
+    fns: &UserFns,
+    angle_mode: AngleMode,
+    ctx: &EvalCtx,
+    fname: &str,
+    index: usize,
+) -> Result<f64, ExathError> {
+    let value = eval_node(ast, vars, fns, angle_mode, ctx)?;
+    if !value.is_real() {
+        return Err(ExathError::arg_type(format!(
+            "{} argument {} is complex ({})",
+            fname,
+            index,
+            value
+        )));
+    }
+    Ok(value.re)
+}
+
+/// `left op right` for a comparison operator: 1 if it holds, else 0.
+fn compare(op: &BinOp, left: Cx, right: Cx) -> Result<Cx, ExathError> {
+    match op {
+        BinOp::Eq => cmp_op(left, right, |a, b| (a - b).abs() < 1e-12),
+        BinOp::Ne => cmp_op(left, right, |a, b| (a - b).abs() >= 1e-12),
+        BinOp::Lt => cmp_op(left, right, |a, b| a < b),
+        BinOp::Le => cmp_op(left, right, |a, b| a <= b),
+        BinOp::Gt => cmp_op(left, right, |a, b| a > b),
+        BinOp::Ge => cmp_op(left, right, |a, b| a >= b),
+        _ => unreachable!("not a comparison operator"),
+    }
+}
+
 fn cmp_op(left: Cx, right: Cx, compare: impl Fn(f64, f64) -> bool) -> Result<Cx, ExathError> {
     if !left.is_real() || !right.is_real() {
         return Err(ExathError::arg_type(
@@ -429,12 +902,11 @@ fn cmp_op(left: Cx, right: Cx, compare: impl Fn(f64, f64) -> bool) -> Result<Cx,
     Ok(Cx::real(if compare(left.re, right.re) { 1.0 } else { 0.0 }))
 }
 
-fn gcd(a: i64, b: i64) -> i64 {
-    if b == 0 {
-        a
-    } else {
-        gcd(b, a % b)
+fn gcd(mut a: i64, mut b: i64) -> i64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
     }
+    a
 }
 
 /// Deterministic trial-division primality test (fine for i64-range integers).
@@ -496,6 +968,9 @@ fn pow_mod(base: i64, exp: i64, m: i64) -> i64 {
     result.rem_euclid(m) as i64
 }
 
+/// Largest integer magnitude an `f64` represents exactly (2^53).
+const MAX_EXACT_INT: i64 = 1 << 53;
+
 fn to_integer(x: f64, fname: &str) -> Result<i64, ExathError> {
     if !x.is_finite() {
         return Err(ExathError::arg_type(format!(
@@ -510,7 +985,7 @@ fn to_integer(x: f64, fname: &str) -> Result<i64, ExathError> {
             fname, x
         )));
     }
-    if rounded.abs() > 9.007_199_254_740_992e15_f64 {
+    if rounded.abs() > MAX_EXACT_INT as f64 {
         return Err(ExathError::overflow(format!(
             "{} argument too large for integer arithmetic",
             fname
@@ -547,3 +1022,271 @@ mod stats_tests {
         assert!((e("digamma(1)") + 0.5772156649).abs() < 1e-6); // ψ(1) = -γ
     }
 }
+
+#[cfg(test)]
+mod integer_tests {
+    use crate::{evaluate, AngleMode, ErrorKind};
+
+    fn e(s: &str) -> f64 {
+        evaluate(s, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn gcd_of_large_coprimes() {
+        assert_eq!(e("gcd(9007199254740991, 9007199254740990)"), 1.0);
+        assert_eq!(e("gcd(9007199254740992, 4503599627370496)"), 4503599627370496.0);
+        assert_eq!(e("gcd(-12, 18)"), 6.0);
+        assert_eq!(e("gcd(0, 0)"), 0.0);
+    }
+
+    #[test]
+    fn lcm_overflow_is_reported() {
+        assert_eq!(e("lcm(94906263, 94906265)"), 9007198946437695.0);
+        let err = evaluate("lcm(100000007, 100000037)", AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Overflow);
+        let err = evaluate("lcm(9007199254740991, 9007199254740990)", AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Overflow);
+        assert_eq!(e("lcm(4, -6)"), 12.0);
+    }
+}
+
+#[cfg(test)]
+mod idiv_rem_tests {
+    use crate::{evaluate, AngleMode, ErrorKind};
+
+    fn e(s: &str) -> f64 {
+        evaluate(s, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn floor_division_across_signs() {
+        for (a, b, q, r) in [
+            (7.0, 2.0, 3.0, 1.0),
+            (-7.0, 2.0, -4.0, 1.0),
+            (7.0, -2.0, -4.0, -1.0),
+            (-7.0, -2.0, 3.0, -1.0),
+            (6.0, 3.0, 2.0, 0.0),
+        ] {
+            assert_eq!(e(&format!("idiv({}, {})", a, b)), q, "idiv({}, {})", a, b);
+            assert_eq!(e(&format!("rem({}, {})", a, b)), r, "rem({}, {})", a, b);
+            assert_eq!(b * q + r, a);
+        }
+        assert!((e("rem(5.5, 2)") - 1.5).abs() < 1e-12);
+        assert_eq!(e("IDIV(9, 4)"), 2.0);
+    }
+
+    #[test]
+    fn zero_divisor_errors() {
+        assert_eq!(evaluate("idiv(1, 0)", AngleMode::Rad).unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(evaluate("rem(1, 0)", AngleMode::Rad).unwrap_err().kind, ErrorKind::DomainError);
+        assert!(evaluate("rem(1)", AngleMode::Rad).is_err());
+    }
+}
+
+#[cfg(test)]
+mod fold_tests {
+    use crate::{AngleMode, CalcResult, ErrorKind, Session};
+
+    #[test]
+    fn factorial_via_fold() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("mul(acc, i) = acc * i").unwrap();
+        assert_eq!(s.eval("fold(1, 10, 1, mul)").unwrap(), CalcResult::Real(3628800.0));
+        assert_eq!(s.eval("fold(1, 0, 7, mul)").unwrap(), CalcResult::Real(7.0)); // empty range
+        // Deep iteration that would overflow the stack as recursion.
+        s.eval("step(acc, i) = acc + 1").unwrap();
+        assert_eq!(s.eval("fold(1, 50000, 0, step)").unwrap(), CalcResult::Real(50000.0));
+    }
+
+    #[test]
+    fn fold_validates_its_function() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("g(x) = x").unwrap();
+        assert_eq!(s.eval("fold(1, 3, 0, g)").unwrap_err().kind, ErrorKind::ArgumentCount);
+        assert_eq!(s.eval("fold(1, 3, 0, nope)").unwrap_err().kind, ErrorKind::UndefinedName);
+        assert_eq!(s.eval("fold(1, 3, 0, 2)").unwrap_err().kind, ErrorKind::ArgumentType);
+    }
+}
+
+#[cfg(test)]
+mod factorial_tests {
+    use crate::{evaluate, AngleMode};
+
+    fn e(s: &str) -> f64 {
+        evaluate(s, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn non_integer_factorial_uses_gamma() {
+        assert!((e("0.5!") - std::f64::consts::PI.sqrt() / 2.0).abs() < 1e-9); // ≈ 0.8862
+        assert!((e("2.5!") - 3.323_350_970_447_842_6).abs() < 1e-9);
+        assert!((e("1.5!") - 1.329_340_388_179_137).abs() < 1e-9);
+    }
+
+    #[test]
+    fn integer_and_negative_paths_unchanged() {
+        assert_eq!(e("5!"), 120.0);
+        assert_eq!(e("0!"), 1.0);
+        assert!(evaluate("(-3)!", AngleMode::Rad).is_err());
+        assert!(evaluate("(-0.5)!", AngleMode::Rad).is_err());
+    }
+}
+
+#[cfg(test)]
+mod real_arg_tests {
+    use crate::{evaluate_complex, AngleMode, ErrorKind};
+
+    fn message(expr: &str) -> String {
+        let err = evaluate_complex(expr, AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ArgumentType, "{}", expr);
+        err.message
+    }
+
+    #[test]
+    fn complex_argument_errors_name_index_and_value() {
+        assert_eq!(message("min(3 + sqrt(-4), 4)"), "min argument 1 is complex (3 + 2i)");
+        assert_eq!(message("max(1, 2, 5 - sqrt(-1))"), "max argument 3 is complex (5 - i)");
+        assert!(message("clamp(0, sqrt(-1), 1)").starts_with("clamp argument 2 is complex"));
+        assert_eq!(message("gcd(4, sqrt(-4))"), "gcd argument 2 is complex (2i)");
+        assert!(message("lcm(sqrt(-1), 2)").starts_with("lcm argument 1 is complex"));
+    }
+}
+
+#[cfg(test)]
+mod complex_rounding_tests {
+    use crate::ast::{eval_ast_with, parse_str, UserFns};
+    use crate::config::EvalConfig;
+    use crate::{evaluate_complex, AngleMode};
+    use std::collections::HashMap;
+
+    fn round_parts(expr: &str) -> (f64, f64) {
+        let cfg = EvalConfig { complex_rounding: true, ..EvalConfig::default() };
+        let ast = parse_str(expr).unwrap();
+        let z = eval_ast_with(&ast, &HashMap::new(), &UserFns::new(), AngleMode::Rad, &cfg).unwrap();
+        (z.re, z.im)
+    }
+
+    #[test]
+    fn each_component_is_rounded() {
+        // 2.6i as 1.3 * sqrt(-4)
+        assert_eq!(round_parts("round(3.4 + 1.3 * sqrt(-4))"), (3.0, 3.0));
+        assert_eq!(round_parts("floor(3.4 - 1.3 * sqrt(-4))"), (3.0, -3.0));
+        assert_eq!(round_parts("ceil(-3.4 + 1.3 * sqrt(-4))"), (-3.0, 3.0));
+        assert_eq!(round_parts("trunc(-3.6 - 1.3 * sqrt(-4))"), (-3.0, -2.0));
+        assert_eq!(round_parts("round(2.5)"), (3.0, 0.0));
+    }
+
+    #[test]
+    fn off_by_default() {
+        for f in ["round", "floor", "ceil", "trunc"] {
+            assert!(evaluate_complex(&format!("{}(1 + sqrt(-1))", f), AngleMode::Rad).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod branch_tests {
+    use crate::ast::{eval_ast_with, parse_str, UserFns};
+    use crate::config::{BranchPolicy, EvalConfig};
+    use crate::evaluator::Cx;
+    use crate::AngleMode;
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+
+    fn eval_on(branch: BranchPolicy, expr: &str) -> Cx {
+        let cfg = EvalConfig { branch, ..EvalConfig::default() };
+        let ast = parse_str(expr).unwrap();
+        eval_ast_with(&ast, &HashMap::new(), &UserFns::new(), AngleMode::Rad, &cfg).unwrap()
+    }
+
+    fn close(z: Cx, re: f64, im: f64) {
+        assert!((z.re - re).abs() < 1e-12 && (z.im - im).abs() < 1e-12, "{:?}", z);
+    }
+
+    #[test]
+    fn ln_of_minus_one_on_either_side_of_the_cut() {
+        close(eval_on(BranchPolicy::Principal, "ln(-1)"), 0.0, PI);
+        close(eval_on(BranchPolicy::ArgFrom(-PI), "ln(-1)"), 0.0, -PI);
+        close(eval_on(BranchPolicy::ArgFrom(-PI), "loge(-1)"), 0.0, -PI);
+        // Cut along the positive reals: arg in [0, 2π).
+        close(eval_on(BranchPolicy::Principal, "ln(-sqrt(-1))"), 0.0, -PI / 2.0);
+        close(eval_on(BranchPolicy::ArgFrom(0.0), "ln(-sqrt(-1))"), 0.0, 3.0 * PI / 2.0);
+        close(eval_on(BranchPolicy::ArgFrom(0.0), "ln(2)"), 2f64.ln(), 0.0);
+    }
+
+    #[test]
+    fn sqrt_and_pow_follow_the_branch() {
+        close(eval_on(BranchPolicy::Principal, "sqrt(4)"), 2.0, 0.0);
+        close(eval_on(BranchPolicy::ArgFrom(PI), "sqrt(4)"), -2.0, 0.0);
+        close(eval_on(BranchPolicy::ArgFrom(PI), "4^0.5"), -2.0, 0.0);
+        close(eval_on(BranchPolicy::ArgFrom(-PI), "(-4)^0.5"), 0.0, -2.0);
+        // Whole exponents do not depend on the branch.
+        close(eval_on(BranchPolicy::ArgFrom(PI), "(-2)^3"), -8.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod modulo_tests {
+    use crate::ast::{eval_ast_with, parse_str, UserFns};
+    use crate::config::{EvalConfig, ModConvention};
+    use crate::AngleMode;
+    use std::collections::HashMap;
+
+    fn eval_with(modulo: ModConvention, expr: &str) -> f64 {
+        let cfg = EvalConfig { modulo, ..EvalConfig::default() };
+        let ast = parse_str(expr).unwrap();
+        eval_ast_with(&ast, &HashMap::new(), &UserFns::new(), AngleMode::Rad, &cfg).unwrap().re
+    }
+
+    #[test]
+    fn negative_dividend() {
+        assert_eq!(eval_with(ModConvention::Truncated, "(-7) % 3"), -1.0);
+        assert_eq!(eval_with(ModConvention::Euclidean, "(-7) % 3"), 2.0);
+        assert_eq!(eval_with(ModConvention::Floored, "(-7) % 3"), 2.0);
+        assert_eq!(eval_with(ModConvention::Euclidean, "-7 % 3"), 2.0);
+        assert_eq!(eval_with(ModConvention::default(), "-7 % 3"), -1.0);
+    }
+
+    #[test]
+    fn negative_divisor_and_exact_multiples() {
+        assert_eq!(eval_with(ModConvention::Truncated, "7 % -3"), 1.0);
+        assert_eq!(eval_with(ModConvention::Euclidean, "7 % -3"), 1.0);
+        assert_eq!(eval_with(ModConvention::Floored, "7 % -3"), -2.0);
+        assert_eq!(eval_with(ModConvention::Euclidean, "(-7) % -3"), 2.0);
+        assert_eq!(eval_with(ModConvention::Floored, "(-6) % 3"), 0.0);
+        assert_eq!(eval_with(ModConvention::Euclidean, "(-7.5) % 2"), 0.5);
+    }
+}
+
+#[cfg(test)]
+mod clamp_tests {
+    use crate::{evaluate, AngleMode, ErrorKind};
+
+    fn clamp(expr: &str) -> Result<f64, crate::ExathError> {
+        evaluate(expr, AngleMode::Rad)
+    }
+
+    #[test]
+    fn clamps_into_range() {
+        assert_eq!(clamp("clamp(5, 0, 3)").unwrap(), 3.0);
+        assert_eq!(clamp("clamp(-5, 0, 3)").unwrap(), 0.0);
+        assert_eq!(clamp("clamp(1.5, 0, 3)").unwrap(), 1.5);
+        assert_eq!(clamp("clamp(7, 2, 2)").unwrap(), 2.0);
+        assert_eq!(clamp("clamp(inf, 0, 3)").unwrap(), 3.0);
+    }
+
+    #[test]
+    fn reversed_bounds_are_domain_errors() {
+        let err = clamp("clamp(1, 3, 0)").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DomainError);
+        assert!(err.message.starts_with("clamp lower bound exceeds upper bound"));
+    }
+
+    #[test]
+    fn nan_bounds_error_and_nan_value_propagates() {
+        for expr in ["clamp(1, nan, 3)", "clamp(1, 0, nan)"] {
+            assert_eq!(clamp(expr).unwrap_err().kind, ErrorKind::DomainError, "{}", expr);
+        }
+        assert!(clamp("clamp(nan, 0, 3)").unwrap().is_nan());
+    }
+}
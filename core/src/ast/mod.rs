@@ -10,6 +10,12 @@ mod eval;
 mod visitor;
 
 pub use types::{Ast, BinOp};
-pub use parser::parse_str;
-pub use eval::{eval_ast, UserFns};
-pub use visitor::collect_vars;
+pub use parser::{parse_str, parse_str_with};
+pub use eval::{eval_ast, eval_ast_with, NativeFn, NativeFns, UserFns};
+#[cfg(feature = "std")]
+pub(crate) use eval::{eval_ast_native, is_random, FnDefaults};
+pub use visitor::{collect_vars, structurally_equal};
+#[cfg(feature = "std")]
+pub(crate) use tokenizer::{is_identifier, Token, tokenize_spanned};
+#[cfg(feature = "std")]
+pub(crate) use parser::{is_builtin_constant, is_function};
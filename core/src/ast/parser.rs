@@ -1,12 +1,35 @@
+use crate::config::ParseConfig;
 use crate::error::ExathError;
-use super::tokenizer::{Token, tokenize};
+use crate::evaluator::canonical_name;
+use super::tokenizer::{Span, Token, tokenize_spanned};
 use super::types::{Ast, BinOp};
+use crate::prelude::*;
 
 /// Parse an expression string into an AST.
+///
+/// Empty or whitespace-only input is a `ParseError` ("Empty expression").
+/// Unbalanced brackets are reported up front with the 1-based column of the
+/// offending `(`, `)`, `[` or `]`.
 pub fn parse_str(input: &str) -> Result<Ast, ExathError> {
-    let tokens = tokenize(input)?;
+    parse_str_with(input, &ParseConfig::default())
+}
+
+/// Like [`parse_str`], with explicit parse options.
+///
+/// ```
+/// use exath_engine::{ast::parse_str_with, ParseConfig};
+/// let strict = ParseConfig { implicit_mul: false, ..ParseConfig::default() };
+/// assert!(parse_str_with("2x", &strict).is_err());
+/// assert!(parse_str_with("2*x", &strict).is_ok());
+/// ```
+pub fn parse_str_with(input: &str, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let (tokens, spans) = tokenize_spanned(input, cfg)?;
+    if tokens.is_empty() {
+        return Err(ExathError::parse("Empty expression"));
+    }
+    check_balance(&tokens, &spans)?;
     let mut pos = 0;
-    let node = parse_expr(&tokens, &mut pos)?;
+    let node = parse_expr(&tokens, &mut pos, cfg)?;
     if pos < tokens.len() {
         return Err(ExathError::parse("Unexpected token after expression"));
     }
@@ -14,25 +37,56 @@ pub fn parse_str(input: &str) -> Result<Ast, ExathError> {
 }
 
 // Precedence (low → high):
+//   ternary     (c ? a : b, groups from the right)
 //   logical or  (||)
 //   logical and (&&)
 //   comparison  (== != < <= > >=)
 //   addition    (+ -)
 //   term        (* / %)
-//   power       (^)
-//   unary       (- !)
+//   unary       (- + !)
+//   power       (^, postfix !)
 //   primary     (number, ident, call, parens)
+//
+// `!` before an operand is logical not, after one it is factorial: `!0 == 1`,
+// `5! == 120`, `!3! == !(3!)`, and `3!==6` is `3! == 6`.
+//
+// Unary minus binds looser than `^`, as on calculators: `-2^2 == -(2^2)`.
+// A sign is still accepted at the start of an exponent, so `2^-3 == 2^(-3)`
+// and `2^-2^2 == 2^(-(2^2))`. `^` groups from the right (`2^3^2 == 2^9`)
+// unless `ParseConfig::pow_right_assoc` is off, see `parse_power_left`.
+//
+// A function name without parentheses takes exactly one power-level operand:
+// `sin 2^2 == sin(2^2)` and `cos -x == cos(-x)`, but `sin 2 + 3 == sin(2) + 3`
+// and `sin 2x == sin(2) * x`. Anything wider needs explicit parentheses.
 
-fn parse_expr(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    parse_or(tokens, pos)
+fn parse_expr(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    parse_ternary(tokens, pos, cfg)
 }
 
-fn parse_or(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let mut left = parse_and(tokens, pos)?;
+/// `c ? a : b` is `if(c, a, b)`, so only the chosen branch is evaluated.
+/// Both branches are full expressions and the rule groups from the right:
+/// `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+fn parse_ternary(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let condition = parse_or(tokens, pos, cfg)?;
+    if !matches!(tokens.get(*pos), Some(Token::Question)) {
+        return Ok(condition);
+    }
+    *pos += 1;
+    let then = parse_ternary(tokens, pos, cfg)?;
+    if !matches!(tokens.get(*pos), Some(Token::Colon)) {
+        return Err(ExathError::parse("Expected ':' in 'condition ? a : b'"));
+    }
+    *pos += 1;
+    let otherwise = parse_ternary(tokens, pos, cfg)?;
+    Ok(Ast::Call("if".to_string(), vec![condition, then, otherwise]))
+}
+
+fn parse_or(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut left = parse_and(tokens, pos, cfg)?;
     while *pos < tokens.len() {
         if let Token::OrOr = &tokens[*pos] {
             *pos += 1;
-            let right = parse_and(tokens, pos)?;
+            let right = parse_and(tokens, pos, cfg)?;
             left = Ast::BinOp(BinOp::Or, Box::new(left), Box::new(right));
         } else {
             break;
@@ -41,12 +95,12 @@ fn parse_or(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     Ok(left)
 }
 
-fn parse_and(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let mut left = parse_comparison(tokens, pos)?;
+fn parse_and(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut left = parse_comparison(tokens, pos, cfg)?;
     while *pos < tokens.len() {
         if let Token::AndAnd = &tokens[*pos] {
             *pos += 1;
-            let right = parse_comparison(tokens, pos)?;
+            let right = parse_comparison(tokens, pos, cfg)?;
             left = Ast::BinOp(BinOp::And, Box::new(left), Box::new(right));
         } else {
             break;
@@ -55,8 +109,13 @@ fn parse_and(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     Ok(left)
 }
 
-fn parse_comparison(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let mut left = parse_add(tokens, pos)?;
+/// A run of comparisons is a chain, as in mathematics: `a < b <= c` means
+/// `a < b && b <= c`, with `b` evaluated once ([`Ast::Chain`]). A chain
+/// must point one way, so `a < b > c` is rejected, and `!=` only compares
+/// two operands.
+fn parse_comparison(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut operands = vec![parse_add(tokens, pos, cfg)?];
+    let mut ops: Vec<BinOp> = Vec::new();
     while *pos < tokens.len() {
         let op = match &tokens[*pos] {
             Token::EqEq => BinOp::Eq,
@@ -68,24 +127,48 @@ fn parse_comparison(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError
             _ => break,
         };
         *pos += 1;
-        let right = parse_add(tokens, pos)?;
-        left = Ast::BinOp(op, Box::new(left), Box::new(right));
+        ops.push(op);
+        check_chain(&ops)?;
+        operands.push(parse_add(tokens, pos, cfg)?);
     }
-    Ok(left)
+    Ok(match ops.len() {
+        0 => operands.remove(0),
+        1 => {
+            let right = operands.pop().unwrap();
+            let left = operands.pop().unwrap();
+            Ast::BinOp(ops.remove(0), Box::new(left), Box::new(right))
+        }
+        _ => Ast::Chain(ops, operands),
+    })
 }
 
-fn parse_add(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let mut left = parse_term(tokens, pos)?;
+fn check_chain(ops: &[BinOp]) -> Result<(), ExathError> {
+    if ops.len() < 2 {
+        return Ok(());
+    }
+    if ops.contains(&BinOp::Ne) {
+        return Err(ExathError::parse("'!=' cannot be chained with another comparison"));
+    }
+    let up = ops.iter().any(|op| matches!(op, BinOp::Lt | BinOp::Le));
+    let down = ops.iter().any(|op| matches!(op, BinOp::Gt | BinOp::Ge));
+    if up && down {
+        return Err(ExathError::parse("A comparison chain cannot mix '<' and '>'"));
+    }
+    Ok(())
+}
+
+fn parse_add(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut left = parse_term(tokens, pos, cfg)?;
     while *pos < tokens.len() {
         match &tokens[*pos] {
             Token::Plus => {
                 *pos += 1;
-                let right = parse_term(tokens, pos)?;
+                let right = parse_term(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Add, Box::new(left), Box::new(right));
             }
             Token::Minus => {
                 *pos += 1;
-                let right = parse_term(tokens, pos)?;
+                let right = parse_term(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Sub, Box::new(left), Box::new(right));
             }
             _ => break,
@@ -94,28 +177,33 @@ fn parse_add(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     Ok(left)
 }
 
-fn parse_term(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let mut left = parse_power(tokens, pos)?;
+fn parse_term(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut left = parse_power(tokens, pos, cfg)?;
     while *pos < tokens.len() {
         match &tokens[*pos] {
             Token::Mul => {
                 *pos += 1;
-                let right = parse_power(tokens, pos)?;
+                let right = parse_power(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Mul, Box::new(left), Box::new(right));
             }
             Token::Div => {
                 *pos += 1;
-                let right = parse_power(tokens, pos)?;
+                let right = parse_power(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Div, Box::new(left), Box::new(right));
             }
             Token::Mod => {
                 *pos += 1;
-                let right = parse_power(tokens, pos)?;
+                let right = parse_power(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Mod, Box::new(left), Box::new(right));
             }
             // Implicit multiplication: expression followed by ( or identifier
             Token::LParen | Token::Ident(_) => {
-                let right = parse_power(tokens, pos)?;
+                if !cfg.implicit_mul {
+                    return Err(ExathError::parse(
+                        "Implicit multiplication is disabled: write '*' between the operands",
+                    ));
+                }
+                let right = parse_power(tokens, pos, cfg)?;
                 left = Ast::BinOp(BinOp::Mul, Box::new(left), Box::new(right));
             }
             _ => break,
@@ -124,16 +212,23 @@ fn parse_term(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     Ok(left)
 }
 
-fn parse_power(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
-    let base = parse_unary(tokens, pos)?;
+fn parse_power(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let base = parse_unary(tokens, pos, cfg)?;
     if *pos < tokens.len() {
         if let Token::Pow = &tokens[*pos] {
+            if !cfg.pow_right_assoc {
+                return parse_power_left(base, tokens, pos, cfg);
+            }
             *pos += 1;
-            let exponent = parse_power(tokens, pos)?; // right-associative
+            let exponent = parse_power(tokens, pos, cfg)?; // right-associative
             return Ok(Ast::BinOp(BinOp::Pow, Box::new(base), Box::new(exponent)));
         }
     }
-    // Postfix factorial(s)
+    parse_postfix(base, tokens, pos)
+}
+
+/// Postfix factorial(s) after `base`.
+fn parse_postfix(base: Ast, tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     let mut result = base;
     while *pos < tokens.len() {
         if let Token::Factorial = &tokens[*pos] {
@@ -146,32 +241,64 @@ fn parse_power(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
     Ok(result)
 }
 
-fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
+/// Fold a `^` chain from the left (`ParseConfig::pow_right_assoc` off):
+/// `2^3^2 == (2^3)^2`. Each exponent is a signed primary with its postfix
+/// `!`, so `2^-2^2 == (2^-2)^2` and `2^3! == 2^(3!)`.
+fn parse_power_left(base: Ast, tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    let mut result = base;
+    while let Some(Token::Pow) = tokens.get(*pos) {
+        *pos += 1;
+        let exponent = parse_exponent(tokens, pos, cfg)?;
+        result = Ast::BinOp(BinOp::Pow, Box::new(result), Box::new(exponent));
+    }
+    Ok(result)
+}
+
+fn parse_exponent(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
+    match tokens.get(*pos) {
+        Some(Token::Minus) => {
+            *pos += 1;
+            Ok(Ast::UnaryNeg(Box::new(parse_exponent(tokens, pos, cfg)?)))
+        }
+        Some(Token::Plus) => {
+            *pos += 1;
+            parse_exponent(tokens, pos, cfg)
+        }
+        _ => {
+            let operand = parse_primary(tokens, pos, cfg)?;
+            parse_postfix(operand, tokens, pos)
+        }
+    }
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
     if *pos < tokens.len() {
         match &tokens[*pos] {
             Token::Minus => {
                 *pos += 1;
-                let inner = parse_primary(tokens, pos)?;
+                let inner = parse_power(tokens, pos, cfg)?;
                 return Ok(Ast::UnaryNeg(Box::new(inner)));
             }
             Token::Plus => {
                 *pos += 1;
-                return parse_primary(tokens, pos);
+                return parse_power(tokens, pos, cfg);
             }
+            // Prefix `!` is logical not and binds like unary minus, so a
+            // postfix `!` on its operand is applied first: `!3!` is `!(3!)`.
             Token::Factorial => {
                 *pos += 1;
-                let inner = parse_primary(tokens, pos)?;
+                let inner = parse_power(tokens, pos, cfg)?;
                 return Ok(Ast::UnaryNot(Box::new(inner)));
             }
             _ => {}
         }
     }
-    parse_primary(tokens, pos)
+    parse_primary(tokens, pos, cfg)
 }
 
-fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
+fn parse_primary(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Ast, ExathError> {
     if *pos >= tokens.len() {
-        return Err(ExathError::parse("Unexpected end of expression"));
+        return Err(missing_operand(tokens, *pos, "Unexpected end of expression"));
     }
     match &tokens[*pos].clone() {
         Token::Number(value) => {
@@ -179,19 +306,21 @@ fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
             Ok(Ast::Number(*value))
         }
         Token::Ident(name) => {
-            let name = name.clone();
+            let name = builtin_name(name);
             *pos += 1;
             if *pos < tokens.len() && matches!(&tokens[*pos], Token::LParen) {
                 *pos += 1;
-                let args = parse_arg_list(tokens, pos)?;
+                let args = parse_arg_list(tokens, pos, cfg)?;
                 if *pos < tokens.len() && matches!(&tokens[*pos], Token::RParen) {
                     *pos += 1;
                 } else {
                     return Err(ExathError::parse("Missing ')'"));
                 }
+                let lower = name.to_ascii_lowercase();
+                let name = if is_call_form(&lower) { lower } else { name };
                 Ok(Ast::Call(name, args))
             } else if is_function(&name) {
-                let arg = parse_unary(tokens, pos)?;
+                let arg = parse_power(tokens, pos, cfg)?;
                 Ok(Ast::Call(name, vec![arg]))
             } else {
                 resolve_const_or_var(name)
@@ -199,7 +328,7 @@ fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
         }
         Token::LParen => {
             *pos += 1;
-            let inner = parse_expr(tokens, pos)?;
+            let inner = parse_expr(tokens, pos, cfg)?;
             if *pos < tokens.len() && matches!(&tokens[*pos], Token::RParen) {
                 *pos += 1;
             } else {
@@ -211,10 +340,10 @@ fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
             *pos += 1;
             let mut elems = Vec::new();
             if !matches!(tokens.get(*pos), Some(Token::RBracket)) {
-                elems.push(parse_expr(tokens, pos)?);
+                elems.push(parse_expr(tokens, pos, cfg)?);
                 while matches!(tokens.get(*pos), Some(Token::Comma)) {
                     *pos += 1;
-                    elems.push(parse_expr(tokens, pos)?);
+                    elems.push(parse_expr(tokens, pos, cfg)?);
                 }
             }
             if matches!(tokens.get(*pos), Some(Token::RBracket)) {
@@ -240,25 +369,123 @@ fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Ast, ExathError> {
                 Ok(Ast::Matrix(vec![elems]))
             }
         }
-        _ => Err(ExathError::parse("Unexpected token")),
+        _ => Err(missing_operand(tokens, *pos, "Unexpected token")),
     }
 }
 
-fn parse_arg_list(tokens: &[Token], pos: &mut usize) -> Result<Vec<Ast>, ExathError> {
+/// The error for a missing operand at `pos`, naming the operator next to
+/// the gap: `2 +` is "Expected expression after '+'", `^2` is "Expected
+/// expression before '^'". Falls back to `fallback` when no operator is
+/// involved.
+fn missing_operand(tokens: &[Token], pos: usize, fallback: &str) -> ExathError {
+    let before = pos.checked_sub(1).and_then(|i| tokens.get(i)).and_then(operator_text);
+    if let Some(op) = before {
+        return ExathError::parse(format!("Expected expression after '{}'", op));
+    }
+    if let Some(op) = tokens.get(pos).and_then(operator_text) {
+        return ExathError::parse(format!("Expected expression before '{}'", op));
+    }
+    ExathError::parse(fallback)
+}
+
+/// Source spelling of an operator token, `None` for operands and brackets.
+fn operator_text(token: &Token) -> Option<&'static str> {
+    Some(match token {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Mul => "*",
+        Token::Div => "/",
+        Token::Pow => "^",
+        Token::Mod => "%",
+        Token::Factorial => "!",
+        Token::EqEq => "==",
+        Token::Ne => "!=",
+        Token::Lt => "<",
+        Token::Le => "<=",
+        Token::Gt => ">",
+        Token::Ge => ">=",
+        Token::AndAnd => "&&",
+        Token::OrOr => "||",
+        Token::Question => "?",
+        Token::Colon => ":",
+        _ => return None,
+    })
+}
+
+fn parse_arg_list(tokens: &[Token], pos: &mut usize, cfg: &ParseConfig) -> Result<Vec<Ast>, ExathError> {
     let mut args = Vec::new();
     if *pos < tokens.len() && matches!(&tokens[*pos], Token::RParen) {
         return Ok(args);
     }
-    args.push(parse_expr(tokens, pos)?);
+    args.push(parse_expr(tokens, pos, cfg)?);
     while *pos < tokens.len() && matches!(&tokens[*pos], Token::Comma) {
         *pos += 1;
-        args.push(parse_expr(tokens, pos)?);
+        args.push(parse_expr(tokens, pos, cfg)?);
     }
     Ok(args)
 }
 
-/// Returns true if the identifier is a known function name.
-fn is_function(name: &str) -> bool {
+/// Check that parentheses and brackets pair up, naming the column of the
+/// first one that does not.
+fn check_balance(tokens: &[Token], spans: &[Span]) -> Result<(), ExathError> {
+    let mut open: Vec<(char, usize)> = Vec::new();
+    for (token, &(start, _)) in tokens.iter().zip(spans) {
+        let column = start + 1;
+        let (closing, opener) = match token {
+            Token::LParen => {
+                open.push(('(', column));
+                continue;
+            }
+            Token::LBracket => {
+                open.push(('[', column));
+                continue;
+            }
+            Token::RParen => (')', '('),
+            Token::RBracket => (']', '['),
+            _ => continue,
+        };
+        match open.pop() {
+            Some((kind, _)) if kind == opener => {}
+            Some((kind, opened_at)) => {
+                return Err(ExathError::parse(format!(
+                    "Unmatched '{}' opened at column {} (found '{}' at column {})",
+                    kind, opened_at, closing, column
+                )));
+            }
+            None => {
+                return Err(ExathError::parse(format!(
+                    "Unmatched '{}' at column {}",
+                    closing, column
+                )));
+            }
+        }
+    }
+    match open.pop() {
+        Some((kind, opened_at)) => Err(ExathError::parse(format!(
+            "Unmatched '{}' opened at column {}",
+            kind, opened_at
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Resolve built-in function and constant names case-insensitively to their
+/// canonical lowercase form (`SIN` → `sin`, `ArcTan` → `atan`, `PI` → `pi`).
+/// Any other identifier keeps its case, so `Temp` and `temp` stay distinct.
+fn builtin_name(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if is_function(&lower) || is_builtin_constant(&lower) || matches!(lower.as_str(), "nthroot" | "mod") {
+        canonical_name(&lower).to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns true if the identifier is a known function name, ignoring ASCII
+/// case.
+pub(crate) fn is_function(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    let name = canonical_name(&lower);
     matches!(
         name,
         "sin"  | "cos"  | "tan"  | "cot"  | "sec"  | "csc"  |
@@ -267,22 +494,380 @@ fn is_function(name: &str) -> bool {
         "asinh" | "acosh" | "atanh" | "acoth" | "asech" | "acsch" |
         "ln" | "lg" | "log" | "exp" |
         "sqrt" | "cbrt" | "abs" |
-        "gamma" | "lgamma" | "erf" | "erfc" | "digamma" |
-        "floor" | "ceil" | "round" | "trunc" | "frac" |
+        "gamma" | "lgamma" | "erf" | "erfc" | "digamma" | "sigmoid" | "logit" |
+        "floor" | "ceil" | "round" | "trunc" | "frac" | "fracpos" |
         "sign" | "sgn" | "arg" | "conj" | "real" | "imag" |
         "deg" | "rad" |
-        "if" | "min" | "max" | "clamp" | "gcd" | "lcm"
+        "if" | "min" | "max" | "clamp" | "gcd" | "lcm" | "idiv" | "rem"
     ) || name.starts_with("log:")
 }
 
+/// Returns true if the identifier names a built-in numeric constant,
+/// ignoring ASCII case.
+pub(crate) fn is_builtin_constant(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "e" | "pi" | "\u{03c0}" | "phi" | "\u{03d5}" | "\u{03b5}" | "epsilon" | "inf" | "nan"
+    )
+}
+
+/// Returns true if the identifier is a built-in that only has meaning when
+/// called with parentheses (`sum(..)`, `diff(..)`, `det(..)`), ignoring
+/// ASCII case. A bare `Sum` stays an ordinary variable.
+fn is_call_form(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "beta" | "isprime" | "nextprime" | "totient" | "powmod" | "factorint" |
+        "mean" | "median" | "variance" | "stddev" | "npdf" | "ncdf" | "binom" |
+        "piecewise" | "assume" | "sum" | "product" | "fold" | "deriv" | "convert" |
+        "rand" | "randint" |
+        "diff" | "simplify" | "integral" | "solve" | "factor" | "polygcd" | "nsolve" |
+        "expand" | "taylor" | "limit" | "grad" | "jacobian" | "hessian" | "odesolve" |
+        "minimize" | "maximize" | "sumc" | "laplace" | "dsolve" |
+        "det" | "inv" | "transpose" | "trace" | "rank" | "norm" | "svdvals" | "charpoly" |
+        "identity" | "linsolve" | "eigenvalues" | "eigenvectors"
+    )
+}
+
 /// Resolve a bare identifier to a constant literal or a Var node.
 fn resolve_const_or_var(name: String) -> Result<Ast, ExathError> {
     match name.as_str() {
-        "e" => Ok(Ast::Number(std::f64::consts::E)),
-        "pi" | "\u{03c0}" => Ok(Ast::Number(std::f64::consts::PI)),
+        "inf" => Ok(Ast::Number(f64::INFINITY)),
+        "nan" => Ok(Ast::Number(f64::NAN)),
+        "e" => Ok(Ast::Number(core::f64::consts::E)),
+        "pi" | "\u{03c0}" => Ok(Ast::Number(core::f64::consts::PI)),
         "phi" | "\u{03d5}" => Ok(Ast::Number(1.618_033_988_749_895)),
-        "\u{03b5}" | "epsilon" => Ok(Ast::Number(std::f64::consts::E)),
+        "\u{03b5}" | "epsilon" => Ok(Ast::Number(core::f64::consts::E)),
         "mod" => Err(ExathError::parse("'mod' must be used as a binary operator")),
         _ => Ok(Ast::Var(name)),
     }
 }
+
+#[cfg(test)]
+mod precedence_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::evaluator::evaluate;
+
+    fn eval(expr: &str) -> f64 {
+        evaluate(expr, AngleMode::Rad).unwrap()
+    }
+
+    fn close(expr: &str, expected: f64) {
+        let got = eval(expr);
+        assert!((got - expected).abs() < 1e-12, "{} = {}, expected {}", expr, got, expected);
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_power() {
+        close("-2^2", -4.0);
+        close("(-2)^2", 4.0);
+        close("-2^2 + 5", 1.0);
+        close("3 * -2^2", -12.0);
+        close("-3!", -6.0);
+        close("--2^2", 4.0);
+    }
+
+    #[test]
+    fn bang_is_not_before_and_factorial_after() {
+        close("!5", 0.0);
+        close("5!", 120.0);
+        close("!0", 1.0);
+        close("!(3!)", 0.0);
+        close("!3!", 0.0);
+        close("!!3", 1.0);
+        close("3!!", 720.0);
+        close("!0!", 0.0);
+        close("3!==6", 1.0);
+        close("3! == 6", 1.0);
+        close("3!=6", 1.0);
+        close("!0 + 1", 2.0);
+        close("2^3!", 64.0);
+    }
+
+    #[test]
+    fn comparisons_chain() {
+        close("0 < 5 < 10", 1.0);
+        close("0 < 20 < 10", 0.0);
+        close("0 < 5 <= 5", 1.0);
+        close("3 >= 3 > 1", 1.0);
+        close("2 == 2 == 2", 1.0);
+        close("1 < 2 < 3 < 2", 0.0);
+        close("(0 < 5 < 10) == 1", 1.0);
+        // `==` continues the chain: 0 < 5 && 5 < 10 && 10 == 1.
+        close("0 < 5 < 10 == 1", 0.0);
+        close("(0 < 20) < 10", 1.0);
+        for bad in ["1 < 2 > 0", "1 != 2 != 1", "1 < 2 != 3"] {
+            assert!(evaluate(bad, AngleMode::Rad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn signed_exponents() {
+        close("2^-3", 0.125);
+        close("2^+3", 8.0);
+        close("2^-2^2", 0.0625);
+        close("2^3^2", 512.0);
+        close("-2^-2", -0.25);
+    }
+
+    #[test]
+    fn prefix_function_takes_one_power_operand() {
+        close("sin 2pi", 2.0f64.sin() * std::f64::consts::PI);
+        close("sin 2 + 3", 2.0f64.sin() + 3.0);
+        close("sin 2^2", 4.0f64.sin());
+        close("sin 3!", 6.0f64.sin());
+        close("cos -2", (-2.0f64).cos());
+        close("-sin 2^2", -(4.0f64.sin()));
+    }
+
+    #[test]
+    fn prefix_function_shapes() {
+        use crate::ast::parse_str;
+        let same = |a: &str, b: &str| {
+            assert_eq!(
+                format!("{:?}", parse_str(a).unwrap()),
+                format!("{:?}", parse_str(b).unwrap()),
+                "{} vs {}",
+                a,
+                b
+            );
+        };
+        same("sin 2x", "sin(2) * x");
+        same("sin 2 + 3", "sin(2) + 3");
+        same("sin 2^2", "sin(2^2)");
+        same("cos -x", "cos(-x)");
+        same("sin x^2 / 2", "sin(x^2) / 2");
+    }
+
+    #[test]
+    fn rendering_round_trips() {
+        use crate::ast::parse_str;
+        use crate::symbolic::render;
+        for src in ["-x^2", "(-x)^2", "2^(-x)", "-(x^2)!", "(!x)^2"] {
+            let ast = parse_str(src).unwrap();
+            let back = parse_str(&render(&ast)).unwrap();
+            assert_eq!(format!("{:?}", back), format!("{:?}", ast), "{}", src);
+        }
+    }
+}
+
+#[cfg(test)]
+mod juxtaposition_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::ast::{parse_str, structurally_equal};
+    use crate::evaluator::evaluate;
+    use std::f64::consts::{E, PI};
+
+    fn same(a: &str, b: &str) {
+        let (left, right) = (parse_str(a).unwrap(), parse_str(b).unwrap());
+        assert!(structurally_equal(&left, &right), "{} parsed as {:?}", a, left);
+    }
+
+    #[test]
+    fn number_then_constant() {
+        same("2pi", "2 * pi");
+        assert_eq!(evaluate("2pi", AngleMode::Rad).unwrap(), 2.0 * PI);
+        assert_eq!(evaluate("2e", AngleMode::Rad).unwrap(), 2.0 * E);
+    }
+
+    #[test]
+    fn power_binds_before_juxtaposition() {
+        same("2pi^2", "2 * (pi^2)");
+        same("2e^x", "2 * (e^x)");
+        same("3x^2y", "3 * x^2 * y");
+        assert!((evaluate("2pi^2", AngleMode::Rad).unwrap() - 2.0 * PI * PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn letters_then_digits_form_one_name() {
+        // `pi2` and `e3` are identifiers, not `pi * 2` or `e * 3`.
+        assert!(matches!(parse_str("pi2").unwrap(), crate::ast::Ast::Var(ref v) if v == "pi2"));
+        assert!(evaluate("pi2", AngleMode::Rad).is_err());
+        same("x e3", "x * e3");
+    }
+
+    #[test]
+    fn scientific_notation_or_constant_e() {
+        let eval = |s: &str| evaluate(s, AngleMode::Rad).unwrap();
+        assert_eq!(eval("3e"), 3.0 * E);
+        assert_eq!(eval("3e2"), 300.0);
+        assert_eq!(eval("3e+2"), 300.0);
+        assert_eq!(eval("3E-2"), 0.03);
+        assert_eq!(eval("6.022e23"), 6.022e23);
+        assert_eq!(eval(".5e1"), 5.0);
+        // Only digits right after `e` (or its sign) make an exponent.
+        assert_eq!(eval("3e + 2"), 3.0 * E + 2.0);
+        assert_eq!(eval("2exp(1)"), 2.0 * E);
+        same("3e-x", "3 * e - x");
+        same("2e3x", "2000 * x");
+        same("2e^x", "2 * (e^x)");
+        same("2e3^2", "2000^2");
+    }
+}
+
+#[cfg(test)]
+mod implicit_mul_tests {
+    use super::parse_str_with;
+    use crate::config::ParseConfig;
+    use crate::error::ErrorKind;
+
+    fn off() -> ParseConfig {
+        ParseConfig { implicit_mul: false, ..ParseConfig::default() }
+    }
+
+    #[test]
+    fn juxtaposition_follows_the_setting() {
+        let on = ParseConfig::default();
+        for src in ["2x", "3(x + 1)", "2sqrt(x)", "x y", "2|x|"] {
+            assert!(parse_str_with(src, &on).is_ok(), "{}", src);
+            let err = parse_str_with(src, &off()).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::ParseError, "{}", src);
+        }
+    }
+
+    #[test]
+    fn calls_and_explicit_products_still_parse_when_off() {
+        for src in ["2*x", "f(x)", "sin(x) * 3", "sin x", "-x^2", "[1, 2]"] {
+            assert!(parse_str_with(src, &off()).is_ok(), "{}", src);
+        }
+    }
+}
+
+#[cfg(test)]
+mod pow_assoc_tests {
+    use super::parse_str_with;
+    use crate::config::ParseConfig;
+    use crate::evaluate;
+    use crate::symbolic::render;
+    use crate::AngleMode;
+
+    fn left() -> ParseConfig {
+        ParseConfig { pow_right_assoc: false, ..ParseConfig::default() }
+    }
+
+    fn close(src: &str, cfg: &ParseConfig, want: f64) {
+        let ast = parse_str_with(src, cfg).unwrap();
+        let got = crate::ast::eval_ast(&ast, &Default::default(), &Default::default(), AngleMode::Rad)
+            .unwrap()
+            .re;
+        assert!((got - want).abs() < 1e-9, "{} = {}, want {}", src, got, want);
+    }
+
+    #[test]
+    fn right_by_default_left_on_request() {
+        assert!((evaluate("2^3^2", AngleMode::Rad).unwrap() - 512.0).abs() < 1e-9);
+        close("2^3^2", &ParseConfig::default(), 512.0);
+        close("2^3^2", &left(), 64.0);
+        close("2**3**2", &left(), 64.0);
+        assert_eq!(render(&parse_str_with("2^3^2", &left()).unwrap()), "(2^3)^2");
+    }
+
+    #[test]
+    fn left_chain_keeps_signs_factorials_and_unary_minus() {
+        close("2^-2^2", &left(), 0.0625);
+        close("2^3!", &left(), 64.0);
+        close("-2^2^3", &left(), -64.0);
+        close("2^(1+1)^3 + 1", &left(), 65.0);
+        close("3 * 2^2^2", &left(), 48.0);
+    }
+}
+
+#[cfg(test)]
+mod ternary_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::ast::{parse_str, structurally_equal};
+    use crate::evaluator::evaluate;
+
+    fn eval(expr: &str) -> f64 {
+        evaluate(expr, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn chooses_a_branch() {
+        assert_eq!(eval("1 ? 2 : 3"), 2.0);
+        assert_eq!(eval("0 ? 2 : 3"), 3.0);
+        // Lowest precedence: the condition and branches are whole expressions.
+        assert_eq!(eval("1 ? 2 : 3 == 2"), 2.0);
+        assert_eq!(eval("2 > 3 || 1 ? 10 + 1 : 20"), 11.0);
+        assert_eq!(eval("(0 ? 1 : 2) * 3"), 6.0);
+        assert_eq!(eval("max(1 ? 5 : 6, 2)"), 5.0);
+        let sugar = parse_str("x > 0 ? x : -x").unwrap();
+        assert!(structurally_equal(&sugar, &parse_str("if(x > 0, x, -x)").unwrap()));
+    }
+
+    #[test]
+    fn nests_from_the_right() {
+        assert_eq!(eval("0 ? 1 : 0 ? 2 : 3"), 3.0);
+        assert_eq!(eval("0 ? 1 : 1 ? 2 : 3"), 2.0);
+        assert_eq!(eval("1 ? 0 ? 4 : 5 : 6"), 5.0);
+    }
+
+    #[test]
+    fn untaken_branch_is_not_evaluated() {
+        assert_eq!(eval("1 ? 7 : 1/0"), 7.0);
+        assert_eq!(eval("0 ? ln(0) : 8"), 8.0);
+        assert!(evaluate("1 ? 1/0 : 7", AngleMode::Rad).is_err());
+    }
+
+    #[test]
+    fn malformed_ternaries() {
+        assert_eq!(parse_str("1 ? 2").unwrap_err().message, "Expected ':' in 'condition ? a : b'");
+        assert_eq!(parse_str("1 ? : 3").unwrap_err().message, "Expected expression after '?'");
+        assert!(parse_str("1 : 2").is_err());
+        // The subscript base of `log` still works next to the new tokens.
+        assert!((eval("1 ? log\u{208D}2\u{208E}(8) : 0") - 3.0).abs() < 1e-12);
+    }
+}
+
+#[cfg(test)]
+mod balance_tests {
+    use super::parse_str;
+
+    fn message(input: &str) -> String {
+        parse_str(input).unwrap_err().message
+    }
+
+    #[test]
+    fn unmatched_open_reports_its_column() {
+        assert_eq!(message("2 * (3 + sin(4)"), "Unmatched '(' opened at column 5");
+        assert_eq!(message("[1, 2"), "Unmatched '[' opened at column 1");
+    }
+
+    #[test]
+    fn unmatched_close_reports_its_column() {
+        assert_eq!(message("(1 + 2)) * 3"), "Unmatched ')' at column 8");
+        assert_eq!(
+            message("max(1, [2)"),
+            "Unmatched '[' opened at column 8 (found ')' at column 10)"
+        );
+    }
+
+    #[test]
+    fn dangling_operators_are_named() {
+        assert_eq!(message("2 +"), "Expected expression after '+'");
+        assert_eq!(message("3 *"), "Expected expression after '*'");
+        assert_eq!(message("^2"), "Expected expression before '^'");
+        assert_eq!(message("2^"), "Expected expression after '^'");
+        assert_eq!(message("1 + * 2"), "Expected expression after '+'");
+        assert_eq!(message("max(1 <, 2)"), "Expected expression after '<'");
+        assert_eq!(message("x && -"), "Expected expression after '-'");
+        assert_eq!(message("sin("), "Unmatched '(' opened at column 4");
+    }
+
+    #[test]
+    fn empty_input_is_its_own_error() {
+        assert_eq!(message(""), "Empty expression");
+        assert_eq!(message("   "), "Empty expression");
+        assert_eq!(message("\t\u{2041} "), "Empty expression");
+        assert!(!crate::analysis::is_valid(""));
+        assert!(!crate::analysis::is_valid("  "));
+        // Not empty: the bar is reported as unclosed.
+        assert_eq!(message("|"), "Missing closing '|'");
+    }
+
+    #[test]
+    fn balanced_input_still_parses() {
+        assert!(parse_str("|(1 + 2)| * (3)").is_ok());
+        assert!(parse_str("sin(30°15') + ((1))").is_ok());
+    }
+}
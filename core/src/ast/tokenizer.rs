@@ -1,4 +1,6 @@
+use crate::config::ParseConfig;
 use crate::error::ExathError;
+use crate::prelude::*;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Token {
@@ -25,14 +27,28 @@ pub(crate) enum Token {
     Ge,
     AndAnd,
     OrOr,
+    Question,
+    Colon,
 }
 
-pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
+/// `(start, end)` character offsets of a token, `end` exclusive.
+pub(crate) type Span = (usize, usize);
+
+/// Tokenize and also return each token's `(start, end)` character span.
+///
+/// Tokens synthesized from a single source character (the `abs` and `(` that
+/// an opening `|` expands to) share that character's span.
+pub(crate) fn tokenize_spanned(
+    input: &str,
+    cfg: &ParseConfig,
+) -> Result<(Vec<Token>, Vec<Span>), ExathError> {
     let mut tokens = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
     let chars: Vec<char> = input.chars().collect();
     let mut pos = 0;
 
     while pos < chars.len() {
+        let start = pos;
         match chars[pos] {
             // Whitespace and calculator marker characters
             ' ' | '\t' | '\u{2041}' | '\u{203E}' | '\u{208D}' | '\u{208E}' => {
@@ -94,10 +110,22 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                 tokens.push(Token::Mod);
                 pos += 1;
             }
+            '?' => {
+                tokens.push(Token::Question);
+                pos += 1;
+            }
+            // Only the ternary's separator: a `log:b` name is synthesized
+            // from the subscript form `log₍b₎` and never read from a colon.
+            ':' => {
+                tokens.push(Token::Colon);
+                pos += 1;
+            }
 
             '!' => {
                 pos += 1;
-                if pos < chars.len() && chars[pos] == '=' {
+                // `!==` is a factorial followed by `==` (`3!==6`).
+                let ne = chars.get(pos) == Some(&'=') && chars.get(pos + 1) != Some(&'=');
+                if ne {
                     tokens.push(Token::Ne);
                     pos += 1;
                 } else {
@@ -146,48 +174,31 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                 }
             }
 
-            '|' if pos + 1 < chars.len() && chars[pos + 1] == '|' => {
+            // `||` is logical or only after an operand; elsewhere (e.g. the
+            // start of `||x|-1|`) it opens an absolute value.
+            '|' if pos + 1 < chars.len()
+                && chars[pos + 1] == '|'
+                && ends_operand(tokens.last()) =>
+            {
                 tokens.push(Token::OrOr);
                 pos += 2;
             }
 
-            // |expr| → abs(expr)
+            // |expr| → abs(expr), with the inner slice tokenized recursively.
             '|' => {
+                let close = find_closing_bar(&chars, pos)
+                    .ok_or_else(|| ExathError::parse("Missing closing '|'"))?;
                 tokens.push(Token::Ident("abs".to_string()));
                 tokens.push(Token::LParen);
-                pos += 1;
-                let mut depth = 1;
-                while pos < chars.len() && depth > 0 {
-                    if chars[pos] == '|' {
-                        depth -= 1;
-                    }
-                    if depth > 0 {
-                        tokens.push(match chars[pos] {
-                            '+' => Token::Plus,
-                            '-' | '\u{2212}' => Token::Minus,
-                            '*' | '\u{00d7}' => Token::Mul,
-                            '/' | '\u{00f7}' => Token::Div,
-                            '^' => Token::Pow,
-                            '(' => Token::LParen,
-                            ')' => Token::RParen,
-                            ch if ch.is_ascii_digit() => match ch.to_digit(10) {
-                                Some(digit) => Token::Number(digit as f64),
-                                None => {
-                                    return Err(ExathError::parse(format!(
-                                        "Invalid digit in absolute value: '{}'",
-                                        ch
-                                    )));
-                                }
-                            },
-                            _ => {
-                                pos += 1;
-                                continue;
-                            }
-                        });
-                    }
-                    pos += 1;
-                }
+                spans.push((pos, pos + 1));
+                spans.push((pos, pos + 1));
+                let inner: String = chars[pos + 1..close].iter().collect();
+                let (inner_tokens, inner_spans) = tokenize_spanned(&inner, cfg)?;
+                tokens.extend(inner_tokens);
+                spans.extend(inner_spans.into_iter().map(|(s, e)| (s + pos + 1, e + pos + 1)));
                 tokens.push(Token::RParen);
+                spans.push((close, close + 1));
+                pos = close + 1;
             }
 
             // Decimal point starting a fractional number (e.g. ".5")
@@ -199,6 +210,7 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                     num_str.push(chars[pos]);
                     pos += 1;
                 }
+                num_str.push_str(&read_exponent(&chars, &mut pos));
                 if num_str == "0." {
                     return Err(ExathError::parse(format!(
                         "Unexpected token at position {}",
@@ -208,32 +220,51 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                 let value: f64 = num_str
                     .parse()
                     .map_err(|_| ExathError::parse("Invalid number"))?;
+                if cfg.strict_literals {
+                    check_literal(&num_str, value)?;
+                }
                 tokens.push(Token::Number(value));
             }
 
             // Digits
             ch if ch.is_ascii_digit() => {
-                let mut num_str = String::new();
+                let mut num_str = match cfg.group_separator {
+                    Some(sep) => read_grouped_digits(&chars, &mut pos, sep)?,
+                    None => String::new(),
+                };
                 while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
                     num_str.push(chars[pos]);
                     pos += 1;
                 }
+                num_str.push_str(&read_exponent(&chars, &mut pos));
                 // Exath 2.0: the comma is purely a separator. Decimals use `.`
                 // only, so `,` is never folded into a number here.
                 let value: f64 = num_str
                     .parse()
                     .map_err(|_| ExathError::parse("Invalid number"))?;
-                tokens.push(Token::Number(value));
+                if cfg.strict_literals {
+                    check_literal(&num_str, value)?;
+                }
+                if chars.get(pos) == Some(&'\u{00b0}') {
+                    // 30°15'20" → °(30.2555…), converted to the angle mode at
+                    // evaluation time.
+                    pos += 1;
+                    let degrees = value + read_dms_part(&chars, &mut pos, &['\'', '\u{2032}'])? / 60.0
+                        + read_dms_part(&chars, &mut pos, &['"', '\u{2033}'])? / 3600.0;
+                    tokens.push(Token::Ident("\u{00b0}".to_string()));
+                    tokens.push(Token::LParen);
+                    tokens.push(Token::Number(degrees));
+                    tokens.push(Token::RParen);
+                } else {
+                    tokens.push(Token::Number(value));
+                }
             }
 
-            // Greek letters for constants + ASCII identifiers
-            ch if ch.is_ascii_alphabetic()
-                || ch == '\u{03c0}'
-                || ch == '\u{03d5}'
-                || ch == '\u{03b5}' =>
-            {
+            // ASCII identifiers, and Greek letters as one-letter identifiers
+            // (π, φ and ε are constants, the rest are ordinary names)
+            ch if ch.is_ascii_alphabetic() || is_greek_letter(ch) => {
                 let mut name = String::new();
-                if ch == '\u{03c0}' || ch == '\u{03d5}' || ch == '\u{03b5}' {
+                if is_greek_letter(ch) {
                     name.push(ch);
                     pos += 1;
                 } else {
@@ -244,10 +275,8 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                         pos += 1;
                     }
                 }
-                let lower = name.to_lowercase();
-
                 // Handle log with subscript base: log₍base₎
-                if lower == "log" && pos < chars.len() && chars[pos] == '\u{208D}' {
+                if name.eq_ignore_ascii_case("log") && pos < chars.len() && chars[pos] == '\u{208D}' {
                     pos += 1;
                     let mut base_str = String::new();
                     while pos < chars.len() && chars[pos] != '\u{208E}' {
@@ -258,18 +287,31 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                         pos += 1;
                     }
                     tokens.push(Token::Ident(format!("log:{}", base_str)));
-                } else if is_keyword(&lower) {
-                    tokens.push(Token::Ident(lower));
                 } else {
+                    // Case is kept; built-in names are matched
+                    // case-insensitively by the parser.
                     tokens.push(Token::Ident(name));
                 }
             }
 
-            // √ symbol → sqrt function
+            // Radical signs are prefix functions: like `sin`, the radicand
+            // is one power-level operand, so `√4+5` is `sqrt(4) + 5` and
+            // `√x^2` is `sqrt(x^2)`; write `√(4+5)` for a wider radicand.
             '\u{221a}' => {
                 tokens.push(Token::Ident("sqrt".to_string()));
                 pos += 1;
             }
+            '\u{221b}' => {
+                tokens.push(Token::Ident("cbrt".to_string()));
+                pos += 1;
+            }
+            // ∜ is `sqrt sqrt`, the principal fourth root (halving the
+            // argument twice), and exact for perfect squares of squares.
+            '\u{221c}' => {
+                tokens.push(Token::Ident("sqrt".to_string()));
+                tokens.push(Token::Ident("sqrt".to_string()));
+                pos += 1;
+            }
 
             ch => {
                 return Err(ExathError::parse(format!(
@@ -278,24 +320,405 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, ExathError> {
                 )));
             }
         }
+        while spans.len() < tokens.len() {
+            spans.push((start, pos));
+        }
+    }
+    Ok((tokens, spans))
+}
+
+/// Greek letters (α–ω, Α–Ω and the variant forms ϑ, ϕ, ϵ), each of which
+/// is a complete identifier on its own, so `2αβ` is `2·α·β`.
+pub(crate) fn is_greek_letter(c: char) -> bool {
+    matches!(c, '\u{0391}'..='\u{03a1}' | '\u{03a3}'..='\u{03a9}' | '\u{03b1}'..='\u{03c9}')
+        || matches!(c, '\u{03d1}' | '\u{03d5}' | '\u{03f5}')
+}
+
+/// Whether `name` is a valid variable or function name: an ASCII letter
+/// followed by letters, digits or `_`, or a single Greek letter.
+#[cfg(feature = "std")]
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if is_greek_letter(first) => chars.next().is_none(),
+        Some(first) if first.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Read the integer digits of a number whose groups may be separated by
+/// `sep` (`ParseConfig::group_separator`), as in `1'000'000`, dropping the
+/// separators. Once one appears, the first group must have 1 to 3 digits and
+/// every later group exactly 3.
+fn read_grouped_digits(chars: &[char], pos: &mut usize, sep: char) -> Result<String, ExathError> {
+    let start = *pos;
+    let mut digits = String::new();
+    let mut groups = vec![0usize];
+    loop {
+        match chars.get(*pos) {
+            Some(&c) if c.is_ascii_digit() => {
+                digits.push(c);
+                if let Some(last) = groups.last_mut() {
+                    *last += 1;
+                }
+            }
+            Some(&c) if c == sep && chars.get(*pos + 1).is_some_and(|d| d.is_ascii_digit()) => {
+                groups.push(0);
+            }
+            _ => break,
+        }
+        *pos += 1;
+    }
+    if groups.len() > 1 && (groups[0] > 3 || groups[1..].iter().any(|&n| n != 3)) {
+        let text: String = chars[start..*pos].iter().collect();
+        return Err(ExathError::parse(format!(
+            "Misplaced group separator '{}' in {}",
+            sep, text
+        )));
+    }
+    Ok(digits)
+}
+
+/// Read the exponent of a number in scientific notation, `e3`, `E-7` or
+/// `e+2`, right after its digits. An `e` without digits directly after it
+/// (or after its sign) is left alone: it is the constant, so `3e` is `3·e`,
+/// `3e+2` is `300` but `3e + 2` is `3·e + 2`, and `2exp(1)` is `2·exp(1)`.
+fn read_exponent(chars: &[char], pos: &mut usize) -> String {
+    let mut end = *pos;
+    if !matches!(chars.get(end), Some('e' | 'E')) {
+        return String::new();
+    }
+    end += 1;
+    if matches!(chars.get(end), Some('+' | '-')) {
+        end += 1;
     }
-    Ok(tokens)
+    if !chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+        return String::new();
+    }
+    while chars.get(end).is_some_and(|c| c.is_ascii_digit()) {
+        end += 1;
+    }
+    let exponent: String = chars[*pos..end].iter().collect();
+    *pos = end;
+    exponent
+}
+
+/// Reject a literal that `f64` cannot hold (`ParseConfig::strict_literals`):
+/// one that overflows to infinity, or whose integer part is not exactly
+/// representable, like `9007199254740993` (2^53 + 1).
+fn check_literal(num_str: &str, value: f64) -> Result<(), ExathError> {
+    if value.is_infinite() {
+        return Err(ExathError::overflow(format!(
+            "Number literal {} is too large",
+            num_str
+        )));
+    }
+    // `{:.0}` prints the exact decimal value of the float, not the shortest
+    // string that reads back to it.
+    let Some(int_part) = integer_digits(num_str) else {
+        return Ok(());
+    };
+    let exact = format!("{:.0}", value.trunc());
+    if int_part != exact.trim_start_matches('0') {
+        return Err(ExathError::overflow(format!(
+            "Number literal {} loses precision (stored as {})",
+            num_str, exact
+        )));
+    }
+    Ok(())
+}
+
+/// The integer part of a decimal literal as digits without leading zeros,
+/// with any exponent applied: `"1.5e3"` gives `"1500"`. `None` for an
+/// exponent too large to write out; such a literal is `∞` or `0` anyway.
+fn integer_digits(num_str: &str) -> Option<String> {
+    let (mantissa, exponent) = match num_str.find(['e', 'E']) {
+        Some(i) => (&num_str[..i], num_str[i + 1..].parse::<i32>().ok()?),
+        None => (num_str, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let point = int.len() as i64 + exponent as i64;
+    let mut out = if point <= 0 {
+        String::new()
+    } else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        digits[..point as usize].to_string()
+    };
+    out = out.trim_start_matches('0').to_string();
+    Some(out)
 }
 
-/// Check if a lowercase name is a builtin function, constant, or keyword.
-fn is_keyword(name: &str) -> bool {
+/// Read the minutes or seconds of a degrees-minutes-seconds angle: a number
+/// directly followed by one of `marks`. Returns 0 without consuming anything
+/// if the part is absent.
+fn read_dms_part(chars: &[char], pos: &mut usize, marks: &[char]) -> Result<f64, ExathError> {
+    let mut end = *pos;
+    while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+        end += 1;
+    }
+    if end == *pos || !chars.get(end).is_some_and(|c| marks.contains(c)) {
+        return Ok(0.0);
+    }
+    let num_str: String = chars[*pos..end].iter().collect();
+    let value: f64 = num_str
+        .parse()
+        .map_err(|_| ExathError::parse("Invalid number"))?;
+    if value >= 60.0 {
+        return Err(ExathError::parse(format!(
+            "Angle minutes and seconds must be below 60, got {}{}",
+            num_str, chars[end]
+        )));
+    }
+    *pos = end + 1;
+    Ok(value)
+}
+
+/// Whether a `|` directly after this token would close rather than open.
+fn ends_operand(last: Option<&Token>) -> bool {
     matches!(
-        name,
-        "sin"  | "cos"  | "tan"  | "cot"  | "sec"  | "csc"  |
-        "asin" | "acos" | "atan" | "acot" | "asec" | "acsc" |
-        "sinh"  | "cosh"  | "tanh"  | "coth"  | "sech"  | "csch" |
-        "asinh" | "acosh" | "atanh" | "acoth" | "asech" | "acsch" |
-        "ln" | "lg" | "log" | "exp" |
-        "sqrt" | "cbrt" | "abs" | "nthroot" |
-        "floor" | "ceil" | "round" | "trunc" | "frac" |
-        "sign" | "sgn" | "arg" | "conj" | "real" | "imag" |
-        "deg" | "rad" |
-        "if" | "min" | "max" | "clamp" | "gcd" | "lcm" | "mod" |
-        "e" | "pi" | "phi" | "i" | "x"
+        last,
+        Some(
+            Token::Number(_) | Token::Ident(_) | Token::RParen | Token::RBracket | Token::Factorial
+        )
     )
 }
+
+/// Find the `|` closing the absolute value opened at `open`.
+///
+/// A bar following an operand closes, any other bar opens a nested absolute
+/// value. A `||` after an operand at the outermost level is read as logical
+/// or, so `|a||b|` is `abs(a || b)`; write `|a|*|b|` for the product.
+fn find_closing_bar(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut after_operand = false;
+    let mut i = open + 1;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '|' if after_operand && depth == 1 && chars.get(i + 1) == Some(&'|') => {
+                after_operand = false;
+                i += 2;
+                continue;
+            }
+            '|' if after_operand => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            '|' => {
+                depth += 1;
+                after_operand = false;
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                after_operand = c.is_alphanumeric()
+                    || matches!(c, '_' | '.' | ')' | ']' | '!');
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod abs_bar_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::evaluator::{evaluate_with_vars, CalcResult, Cx};
+    use std::collections::HashMap;
+
+    fn eval(expr: &str) -> f64 {
+        let mut vars = HashMap::new();
+        vars.insert("x".to_string(), Cx::real(-2.0));
+        vars.insert("a".to_string(), Cx::real(1.0));
+        vars.insert("b".to_string(), Cx::real(4.0));
+        match evaluate_with_vars(expr, AngleMode::Rad, &vars) {
+            Ok(CalcResult::Real(v)) => v,
+            other => panic!("{} → {:?}", expr, other),
+        }
+    }
+
+    #[test]
+    fn bars_wrap_full_expressions() {
+        assert_eq!(eval("|x|"), 2.0);
+        assert_eq!(eval("|a-b|"), 3.0);
+        assert!((eval("|sin(x)|") - 2f64.sin()).abs() < 1e-12);
+        assert_eq!(eval("|x + 3*a| * 2"), 2.0);
+        assert_eq!(eval("|a-b| + |x|"), 5.0);
+    }
+
+    #[test]
+    fn nested_bars_and_logical_or() {
+        assert_eq!(eval("||x|-5|"), 3.0);
+        assert_eq!(eval("|a - |x||"), 1.0);
+        assert_eq!(eval("a || 0"), 1.0);
+        assert!(evaluate_with_vars("|x", AngleMode::Rad, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn unicode_operators_inside_bars() {
+        assert_eq!(eval("|3\u{2212}4|"), 1.0);
+        assert_eq!(eval("|2\u{00d7}x|"), 4.0);
+        assert_eq!(eval("|\u{2212}8\u{00f7}b|"), 2.0);
+        assert_eq!(eval("||a\u{2212}b|\u{2212}5|"), 2.0);
+    }
+}
+
+#[cfg(test)]
+mod dms_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::evaluator::evaluate;
+
+    #[test]
+    fn degrees_minutes_seconds() {
+        let deg = |e: &str| evaluate(e, AngleMode::Deg).unwrap();
+        assert!((deg("30°15'20\"") - (30.0 + 15.0 / 60.0 + 20.0 / 3600.0)).abs() < 1e-12);
+        assert_eq!(deg("30°"), 30.0);
+        assert_eq!(deg("30°30'"), 30.5);
+        assert_eq!(deg("10°30′36″"), 10.51);
+        assert_eq!(deg("-45° + 1"), -44.0);
+    }
+
+    #[test]
+    fn dms_feeds_trig_in_any_angle_mode() {
+        let expected = (30.5f64).to_radians().sin();
+        for mode in [AngleMode::Deg, AngleMode::Rad, AngleMode::Grad] {
+            let v = evaluate("sin(30°30')", mode).unwrap();
+            assert!((v - expected).abs() < 1e-12, "{:?}: {}", mode, v);
+        }
+        let rad = evaluate("180°", AngleMode::Rad).unwrap();
+        assert!((rad - std::f64::consts::PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stray_marks_are_errors() {
+        for bad in ["30'", "30\"", "'5", "x°", "30°75'"] {
+            assert!(evaluate(bad, AngleMode::Deg).is_err(), "{}", bad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod strict_literal_tests {
+    use crate::ast::{parse_str, parse_str_with, Ast};
+    use crate::config::ParseConfig;
+    use crate::error::ErrorKind;
+
+    fn strict() -> ParseConfig {
+        ParseConfig { strict_literals: true, ..ParseConfig::default() }
+    }
+
+    #[test]
+    fn overflow_and_rounding_are_errors() {
+        // 10^400 as a plain literal reads as ∞ unless strict.
+        let huge = format!("1{}", "0".repeat(400));
+        assert!(matches!(parse_str(&huge), Ok(Ast::Number(x)) if x.is_infinite()));
+        assert_eq!(parse_str_with(&huge, &strict()).unwrap_err().kind, ErrorKind::Overflow);
+        let twenty = "12345678901234567891";
+        assert!(parse_str(twenty).is_ok());
+        assert_eq!(parse_str_with(twenty, &strict()).unwrap_err().kind, ErrorKind::Overflow);
+        assert_eq!(parse_str_with("1e400", &strict()).unwrap_err().kind, ErrorKind::Overflow);
+        assert!(matches!(parse_str(".5e400"), Ok(Ast::Number(x)) if x.is_infinite()));
+        assert_eq!(parse_str_with(".5e400", &strict()).unwrap_err().kind, ErrorKind::Overflow);
+        // 10^23 is not a double; the nearest is 99999999999999991611392.
+        assert!(parse_str_with("1e23", &strict()).is_err());
+        assert!(parse_str_with("9007199254740993.5 + 1", &strict()).is_err());
+        assert!(parse_str_with("|9007199254740993|", &strict()).is_err());
+    }
+
+    #[test]
+    fn representable_literals_pass() {
+        for ok in [
+            "9007199254740992", "1152921504606846976", "100000000000000000000",
+            "0.1", "007", "3.", ".5", "1e22", "1.5e3", "2.5e-3", "1e-400", ".1e-400",
+        ] {
+            assert!(parse_str_with(ok, &strict()).is_ok(), "{}", ok);
+        }
+    }
+}
+
+#[cfg(test)]
+mod group_separator_tests {
+    use crate::ast::{parse_str, parse_str_with, Ast};
+    use crate::config::ParseConfig;
+    use crate::error::ErrorKind;
+
+    fn grouped(sep: char) -> ParseConfig {
+        ParseConfig { group_separator: Some(sep), ..ParseConfig::default() }
+    }
+
+    fn number(expr: &str, cfg: &ParseConfig) -> f64 {
+        match parse_str_with(expr, cfg).unwrap() {
+            Ast::Number(x) => x,
+            other => panic!("{} parsed as {:?}", expr, other),
+        }
+    }
+
+    #[test]
+    fn apostrophe_groups() {
+        let swiss = grouped('\'');
+        assert_eq!(number("1'000'000", &swiss), 1e6);
+        assert_eq!(number("12'345.25", &swiss), 12345.25);
+        assert_eq!(number("999", &swiss), 999.0);
+        assert_eq!(number("1234", &swiss), 1234.0);
+        assert!(matches!(parse_str_with("30\u{00b0}15'", &swiss), Ok(Ast::Call(..))));
+    }
+
+    #[test]
+    fn space_groups() {
+        assert_eq!(number("1 000 000", &grouped(' ')), 1e6);
+    }
+
+    #[test]
+    fn separators_only_between_groups_of_three() {
+        let swiss = grouped('\'');
+        for bad in ["1'00", "1'0000", "1234'567", "1'000'00", "1'000'"] {
+            let err = parse_str_with(bad, &swiss).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::ParseError, "{}", bad);
+        }
+        // Off by default.
+        assert!(parse_str("1'000").is_err());
+    }
+}
+
+#[cfg(test)]
+mod radical_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::ast::{parse_str, structurally_equal};
+    use crate::evaluator::{evaluate, evaluate_complex, CalcResult};
+
+    fn eval(expr: &str) -> f64 {
+        evaluate(expr, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn radicand_is_one_operand() {
+        assert_eq!(eval("\u{221a}4+5"), 7.0);
+        assert_eq!(eval("\u{221a}(4+5)"), 3.0);
+        assert_eq!(eval("2\u{221a}9"), 6.0);
+        assert_eq!(eval("-\u{221a}4"), -2.0);
+        assert_eq!(eval("\u{221a}\u{221a}16"), 2.0);
+        let same = |a: &str, b: &str| {
+            assert!(structurally_equal(&parse_str(a).unwrap(), &parse_str(b).unwrap()), "{}", a);
+        };
+        same("\u{221a}x^2", "sqrt(x^2)");
+        same("\u{221a}x*y", "sqrt(x) * y");
+        same("\u{221a}2x", "sqrt(2) * x");
+        assert_eq!(evaluate_complex("\u{221a}-4", AngleMode::Rad).unwrap(), CalcResult::Complex(0.0, 2.0));
+    }
+
+    #[test]
+    fn cube_and_fourth_roots() {
+        assert!((eval("\u{221b}27") - 3.0).abs() < 1e-12);
+        assert!((eval("\u{221b}(20+7)") - 3.0).abs() < 1e-12);
+        assert_eq!(eval("\u{221c}16"), 2.0);
+        assert_eq!(eval("\u{221c}(8+8) + 1"), 3.0);
+        assert!((eval("\u{221c}16^2") - 4.0).abs() < 1e-12);
+        let tokens = crate::analysis::tokens("\u{221c}16").unwrap();
+        assert_eq!(tokens.len(), 2);
+    }
+}
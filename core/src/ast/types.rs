@@ -1,3 +1,5 @@
+use crate::prelude::*;
+
 /// Binary operators supported by the expression language.
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinOp {
@@ -34,6 +36,11 @@ pub enum Ast {
     UnaryNot(Box<Ast>),
     /// Postfix factorial  n!
     Factorial(Box<Ast>),
+    /// Chained comparison `a < b <= c`: `ops[i]` compares `operands[i]` with
+    /// `operands[i + 1]`, and the chain holds when every comparison does.
+    /// Each operand is evaluated at most once, left to right, stopping at the
+    /// first false comparison.
+    Chain(Vec<BinOp>, Vec<Ast>),
     /// Function call with zero or more arguments: name(a, b, ...)
     Call(String, Vec<Ast>),
     /// Matrix literal: rows of element expressions, e.g. `[[1,2],[3,4]]`.
@@ -1,4 +1,5 @@
 use super::types::Ast;
+use crate::prelude::*;
 
 /// Collect all variable names referenced in an AST.
 pub fn collect_vars(ast: &Ast) -> Vec<String> {
@@ -21,7 +22,7 @@ fn collect_vars_rec(ast: &Ast, out: &mut Vec<String>) {
         Ast::UnaryNeg(inner) | Ast::UnaryNot(inner) | Ast::Factorial(inner) => {
             collect_vars_rec(inner, out);
         }
-        Ast::Call(_, args) => {
+        Ast::Call(_, args) | Ast::Chain(_, args) => {
             for arg in args {
                 collect_vars_rec(arg, out);
             }
@@ -36,3 +37,102 @@ fn collect_vars_rec(ast: &Ast, out: &mut Vec<String>) {
         Ast::Number(_) => {}
     }
 }
+
+/// True if `a` and `b` have the same shape, operators, names and calls, with
+/// number literals compared within a relative tolerance of `1e-12` (two NaN
+/// literals count as equal).
+///
+/// No simplification happens here: fold or simplify both sides first if
+/// `2 + 3` should match `5`.
+///
+/// ```
+/// use exath_engine::ast::{parse_str, structurally_equal};
+/// let a = parse_str("sin(x) * 0.3").unwrap();
+/// assert!(!structurally_equal(&a, &parse_str("sin(x) * (0.1 + 0.2)").unwrap()));
+/// assert!(structurally_equal(&a, &parse_str("sin(x) * 0.30000000000000004").unwrap()));
+/// ```
+pub fn structurally_equal(a: &Ast, b: &Ast) -> bool {
+    match (a, b) {
+        (Ast::Number(x), Ast::Number(y)) => {
+            (x.is_nan() && y.is_nan())
+                || x == y
+                || (x - y).abs() <= 1e-12 * x.abs().max(y.abs()).max(1.0)
+        }
+        (Ast::Var(x), Ast::Var(y)) => x == y,
+        (Ast::BinOp(op_a, la, ra), Ast::BinOp(op_b, lb, rb)) => {
+            op_a == op_b && structurally_equal(la, lb) && structurally_equal(ra, rb)
+        }
+        (Ast::UnaryNeg(x), Ast::UnaryNeg(y))
+        | (Ast::UnaryNot(x), Ast::UnaryNot(y))
+        | (Ast::Factorial(x), Ast::Factorial(y)) => structurally_equal(x, y),
+        (Ast::Call(name_a, args_a), Ast::Call(name_b, args_b)) => {
+            name_a == name_b && all_equal(args_a, args_b)
+        }
+        (Ast::Chain(ops_a, args_a), Ast::Chain(ops_b, args_b)) => {
+            ops_a == ops_b && all_equal(args_a, args_b)
+        }
+        (Ast::Matrix(rows_a), Ast::Matrix(rows_b)) => {
+            rows_a.len() == rows_b.len()
+                && rows_a.iter().zip(rows_b).all(|(ra, rb)| all_equal(ra, rb))
+        }
+        _ => false,
+    }
+}
+
+fn all_equal(a: &[Ast], b: &[Ast]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| structurally_equal(x, y))
+}
+
+#[cfg(test)]
+mod structurally_equal_tests {
+    use super::structurally_equal;
+    use crate::ast::parse_str;
+
+    fn eq(a: &str, b: &str) -> bool {
+        structurally_equal(&parse_str(a).unwrap(), &parse_str(b).unwrap())
+    }
+
+    #[test]
+    fn equal_trees() {
+        assert!(eq("x^2 + 2*x + 1", "x ^ 2 + 2 * x + 1"));
+        assert!(eq("arcsin(x)", "asin(x)"));
+        assert!(eq("[[1, 2], [3, a]]", "[[1,2],[3,a]]"));
+        assert!(eq("0.3 * y!", "0.30000000000000004 * y!"));
+        assert!(eq("nan + -x", "nan + -x"));
+    }
+
+    #[test]
+    fn unequal_trees() {
+        assert!(!eq("x + 1", "1 + x"));
+        assert!(!eq("x - 1", "x + 1"));
+        assert!(!eq("sin(x)", "cos(x)"));
+        assert!(!eq("max(x, 1)", "max(x, 1, 2)"));
+        assert!(!eq("-x", "!x"));
+        assert!(!eq("0.3", "0.3001"));
+        assert!(!eq("x", "y"));
+    }
+
+    #[test]
+    fn simplified_forms_compare_equal() {
+        use crate::symbolic::simplify_ast;
+        let a = simplify_ast(parse_str("x + x").unwrap());
+        assert!(structurally_equal(&a, &parse_str("2 * x").unwrap()));
+    }
+}
+
+#[cfg(test)]
+mod collect_vars_tests {
+    use super::collect_vars;
+    use crate::ast::parse_str;
+
+    #[test]
+    fn lists_each_variable_once_sorted() {
+        let ast = parse_str("b*x^2 + a*x + sin(b)").unwrap();
+        assert_eq!(collect_vars(&ast), vec!["a", "b", "x"]);
+    }
+
+    #[test]
+    fn constants_are_not_variables() {
+        assert!(collect_vars(&parse_str("2*pi + e^2 - sqrt(3)").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+mod repl;
+
+use exath_engine::analysis::to_fraction;
+use exath_engine::ast::{parse_str, Ast};
+use exath_engine::{AngleMode, CalcResult, Session};
+use repl::Command;
+use std::io::{self, BufRead, Write};
+
+/// Largest denominator `--frac` will print.
+const FRACTION_MAX_DENOM: u64 = 10_000;
+
+/// Output options set from command-line flags.
+struct Options {
+    /// `--frac`: print real results as `p/q` when a close fraction exists.
+    fractions: bool,
+}
+
+fn main() {
+    // Usage: exath [--frac] [script]
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let opts = Options {
+        fractions: take_flag(&mut args, "--frac"),
+    };
+
+    let mut session = Session::new(AngleMode::Rad);
+
+    if let Some(path) = args.first() {
+        // File mode: run a script
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        run_lines(&mut session, &opts, content.lines(), true);
+    } else {
+        // REPL mode
+        println!("exath 1.0, interactive DSL session (type ':help' for commands, 'exit' to quit)");
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        let mut line_num = 0u32;
+
+        loop {
+            print!(">> ");
+            stdout.flush().ok();
+
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) => break, // EOF
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Read error: {}", e);
+                    break;
+                }
+            }
+
+            let command = repl::parse(&line);
+            if command == Command::Skip {
+                continue;
+            }
+            line_num += 1;
+            if !run_command(&mut session, &opts, command, line_num, true) {
+                break;
+            }
+        }
+    }
+}
+
+/// Remove `flag` from `args`, returning whether it was present.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+fn run_lines<'a>(
+    session: &mut Session,
+    opts: &Options,
+    lines: impl Iterator<Item = &'a str>,
+    verbose: bool,
+) {
+    for (i, line) in lines.enumerate() {
+        if !run_command(session, opts, repl::parse(line), (i + 1) as u32, verbose) {
+            break;
+        }
+    }
+}
+
+/// Carry out one command; false when it asks to stop.
+fn run_command(
+    session: &mut Session,
+    opts: &Options,
+    command: Command,
+    line_num: u32,
+    show_input: bool,
+) -> bool {
+    match command {
+        Command::Skip => {}
+        Command::Exit => return false,
+        Command::Help => print!("{}", repl::HELP),
+        Command::List => print_names(session),
+        Command::Delete(name) => match session.eval(&format!("del {}", name)) {
+            Ok(_) => println!("  deleted: {}", name),
+            Err(e) => eprintln!("  [line {}] Error: {}", line_num, e),
+        },
+        Command::Unknown(name) => {
+            eprintln!("  [line {}] Error: Unknown command ':{}' (try :help)", line_num, name);
+        }
+        Command::Eval(line) => eval_and_print(session, opts, line, line_num, show_input),
+    }
+    true
+}
+
+/// `:list`: every variable with its value, then every user function.
+fn print_names(session: &Session) {
+    for name in session.var_names() {
+        if let Some(value) = session.get_var(&name) {
+            println!("  {} = {}", name, value.format());
+        }
+    }
+    for name in session.fn_names() {
+        let params = session.fn_params(&name).unwrap_or_default();
+        println!("  {}({})", name, params.join(", "));
+    }
+}
+
+fn eval_and_print(
+    session: &mut Session,
+    opts: &Options,
+    line: &str,
+    line_num: u32,
+    show_input: bool,
+) {
+    // Detect if this is a function definition (contains `(` before `=`)
+    let is_fn_def = is_function_def(line);
+    let is_assignment = !is_fn_def && is_var_assignment(line);
+
+    match session.eval(line) {
+        Ok(result) => {
+            if is_fn_def {
+                // Function definitions: print confirmation
+                if show_input {
+                    println!("  defined: {}", line);
+                }
+            } else {
+                let formatted = format_result(&result, opts);
+                if is_assignment {
+                    // Show the assignment with result
+                    println!("  {} = {}", line.split('=').next().unwrap_or(line).trim(), formatted);
+                } else {
+                    println!("  {}", formatted);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("  [line {}] Error: {}", line_num, e);
+        }
+    }
+}
+
+fn format_result(result: &CalcResult, opts: &Options) -> String {
+    if let (true, CalcResult::Real(value)) = (opts.fractions, result) {
+        if let Some((p, q)) = to_fraction(*value, FRACTION_MAX_DENOM) {
+            if q != 1 {
+                return format!("{}/{}", p, q);
+            }
+        }
+    }
+    result.format()
+}
+
+/// Quick check if line looks like `name(params) = body`.
+fn is_function_def(line: &str) -> bool {
+    if let Some(lp) = line.find('(') {
+        if let Some(rp) = line[lp..].find(')') {
+            let after = line[lp + rp + 1..].trim_start();
+            if after.starts_with('=') && !after.starts_with("==") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Quick check if line looks like `ident = expr` (not ==, <=, >=, !=).
+fn is_var_assignment(line: &str) -> bool {
+    for (i, b) in line.bytes().enumerate() {
+        if b == b'=' {
+            let prev = if i > 0 { line.as_bytes()[i - 1] } else { 0 };
+            let next = if i + 1 < line.len() { line.as_bytes()[i + 1] } else { 0 };
+            if prev != b'!' && prev != b'<' && prev != b'>' && next != b'=' {
+                // Let the parser decide what a name is, e.g. `θ = 30`.
+                let lhs = line[..i].trim();
+                return matches!(parse_str(lhs), Ok(Ast::Var(name)) if name == lhs);
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,87 @@
+//! REPL meta-commands, parsed apart from evaluation so they can be tested
+//! without stdin.
+
+/// What one input line asks the REPL (or a script) to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// A blank line or a `#` comment.
+    Skip,
+    /// `exit` or `quit`.
+    Exit,
+    /// `:help`.
+    Help,
+    /// `:list`, show the variables and user functions.
+    List,
+    /// `del name` or `unset name`.
+    Delete(&'a str),
+    /// A `:command` that does not exist, without the colon.
+    Unknown(&'a str),
+    /// Anything else, passed to `Session::eval`.
+    Eval(&'a str),
+}
+
+/// Text printed by `:help`.
+pub const HELP: &str = "  expr              evaluate, e.g. 2 sin(pi/4)
+  name = expr       assign a variable
+  const name = expr assign a variable that cannot be reassigned
+  f(x, y) = expr    define a function
+  del name          remove a variable or function (also: unset)
+  :list             show variables and functions
+  :help             show this help
+  exit              quit (also: quit)
+";
+
+/// Classify one line of input.
+pub fn parse(line: &str) -> Command<'_> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Command::Skip;
+    }
+    if let Some(name) = line.strip_prefix(':') {
+        return match name.trim() {
+            "help" => Command::Help,
+            "list" => Command::List,
+            other => Command::Unknown(other),
+        };
+    }
+    if line == "exit" || line == "quit" {
+        return Command::Exit;
+    }
+    if let Some((keyword, name)) = line.split_once(char::is_whitespace) {
+        if matches!(keyword, "del" | "unset") {
+            return Command::Delete(name.trim());
+        }
+    }
+    Command::Eval(line)
+}
+
+#[cfg(test)]
+mod parse_tests {
+    use super::*;
+
+    #[test]
+    fn meta_commands() {
+        assert_eq!(parse(":help"), Command::Help);
+        assert_eq!(parse("  :list "), Command::List);
+        assert_eq!(parse(":history"), Command::Unknown("history"));
+        assert_eq!(parse("exit"), Command::Exit);
+        assert_eq!(parse("quit"), Command::Exit);
+        assert_eq!(parse(""), Command::Skip);
+        assert_eq!(parse("# note"), Command::Skip);
+    }
+
+    #[test]
+    fn delete_takes_the_name() {
+        assert_eq!(parse("del x"), Command::Delete("x"));
+        assert_eq!(parse("unset  f "), Command::Delete("f"));
+    }
+
+    #[test]
+    fn everything_else_is_evaluated() {
+        assert_eq!(parse("1 + 2"), Command::Eval("1 + 2"));
+        assert_eq!(parse(" x = 5 "), Command::Eval("x = 5"));
+        assert_eq!(parse("f(x) = x^2"), Command::Eval("f(x) = x^2"));
+        assert_eq!(parse("delta + 1"), Command::Eval("delta + 1"));
+        assert_eq!(parse("exit_code"), Command::Eval("exit_code"));
+    }
+}
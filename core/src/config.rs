@@ -0,0 +1,108 @@
+//! Evaluation policies that change how numeric edge cases are handled.
+//!
+//! The defaults reproduce the engine's historical behaviour, so passing
+//! `EvalConfig::default()` is always equivalent to not passing a config.
+
+/// What `x / 0` (and `x % 0`) evaluates to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DivByZero {
+    /// Return a `DomainError` ("Division by zero" / "Modulo by zero").
+    #[default]
+    Error,
+    /// Follow IEEE 754: `1/0 == inf`, `-1/0 == -inf`, `0/0 == nan`.
+    /// `x % 0` is `nan`, matching `f64`'s remainder.
+    Ieee,
+}
+
+/// Which branch multivalued functions (`ln`, `sqrt`, `^` with a non-integer
+/// exponent) take, by fixing the range of the argument `arg z` they use.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BranchPolicy {
+    /// The principal branch: `arg z` in `(-π, π]`, so `ln(-1) = iπ` and
+    /// `sqrt(4) = 2`.
+    #[default]
+    Principal,
+    /// `arg z` in `[θ, θ + 2π)` for the given `θ` in radians. `ArgFrom(0.0)`
+    /// puts the cut along the positive reals (`ln(-i) = 3iπ/2`), and
+    /// `ArgFrom(π)` selects the other square root (`sqrt(4) = -2`).
+    ArgFrom(f64),
+}
+
+/// Sign convention of `a % b` (and `a mod b`) when an operand is negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModConvention {
+    /// Truncate the quotient toward zero, like Rust's and C's `%`: the
+    /// result has the sign of `a`, so `-7 % 3 == -1`.
+    #[default]
+    Truncated,
+    /// The result is never negative: `-7 % 3 == 2` and `7 % -3 == 1`.
+    Euclidean,
+    /// Round the quotient down, like Python's `%`: the result has the sign
+    /// of `b`, so `-7 % 3 == 2` and `7 % -3 == -2`.
+    Floored,
+}
+
+/// Policies applied during evaluation. See [`crate::ast::eval_ast_with`]
+/// and [`crate::Session::config`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalConfig {
+    /// Division and modulo by zero; defaults to [`DivByZero::Error`].
+    pub div_by_zero: DivByZero,
+    /// Strict finite mode: when set, any intermediate value with an infinite
+    /// or NaN part (from IEEE division, overflow, an `inf` literal, …) is a
+    /// `DomainError` instead of propagating. Defaults to off.
+    pub strict_finite: bool,
+    /// Report a line whose outermost operator is a comparison or logical
+    /// operator (`3 > 2`, `a && b`, `!x`) as [`crate::CalcResult::Bool`]
+    /// instead of `Real(1.0)` / `Real(0.0)`. Defaults to off.
+    pub bool_results: bool,
+    /// Let `round`, `floor`, `ceil` and `trunc` take complex arguments and
+    /// round the real and imaginary parts independently: `round(3.4 + 2.6i)`
+    /// is `3 + 3i`. Defaults to off, where a complex argument is an
+    /// `ArgumentType` error.
+    pub complex_rounding: bool,
+    /// Branch of `ln`, `sqrt` and `^`; defaults to
+    /// [`BranchPolicy::Principal`].
+    pub branch: BranchPolicy,
+    /// Sign convention of `%` with negative operands; defaults to
+    /// [`ModConvention::Truncated`].
+    pub modulo: ModConvention,
+}
+
+/// Options that change how expressions are parsed. See
+/// [`crate::ast::parse_str_with`] and [`crate::Session::parse_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseConfig {
+    /// Read juxtaposition as multiplication: `2x`, `3(x+1)`, `2sqrt(x)`. When
+    /// off, such input is a `ParseError`; `f(x)` is always a call. Defaults
+    /// to on.
+    pub implicit_mul: bool,
+    /// Group `^` from the right, as in mathematics and most calculators and
+    /// CAS: `2^3^2` is `2^(3^2) = 512`. When off, `^` groups from the left
+    /// like in spreadsheets (Excel, LibreOffice): `2^3^2` is `(2^3)^2 = 64`.
+    /// Defaults to on.
+    pub pow_right_assoc: bool,
+    /// Reject number literals that `f64` cannot represent: one too large
+    /// (which would read as `∞`) or an integer part that would be rounded
+    /// (`12345678901234567891`). Such input is an `Overflow` error. Defaults
+    /// to off, where the literal silently becomes the nearest `f64`.
+    pub strict_literals: bool,
+    /// A character allowed between digit groups of a number's integer part,
+    /// e.g. `Some('\'')` for `1'000'000` or `Some(' ')` for `1 000 000`. It is
+    /// dropped before the number is read; once one appears, the first group
+    /// has 1 to 3 digits and every later group exactly 3, otherwise the input
+    /// is a `ParseError`. Should not be `.`, `,` or a digit. Defaults to
+    /// `None`.
+    pub group_separator: Option<char>,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        ParseConfig {
+            implicit_mul: true,
+            pow_right_assoc: true,
+            strict_literals: false,
+            group_separator: None,
+        }
+    }
+}
@@ -4,7 +4,8 @@
 /// The `Display` impl produces a human-readable message suitable for UIs and logs.
 /// The `kind` field allows callers to branch on the error category without parsing strings.
 
-use std::fmt;
+use core::fmt;
+use crate::prelude::*;
 
 /// Category of error, for programmatic handling.
 #[derive(Debug, Clone, PartialEq)]
@@ -19,7 +20,8 @@ pub enum ErrorKind {
     ArgumentType,
     /// A mathematical domain was violated (ln(0), division by zero, etc.).
     DomainError,
-    /// Overflow in integer arithmetic (gcd/lcm).
+    /// Overflow in integer arithmetic (gcd/lcm), or a number literal `f64`
+    /// cannot hold under `ParseConfig::strict_literals`.
     Overflow,
     /// Numerical method produced a complex intermediate result.
     ComplexResult,
@@ -27,6 +29,42 @@ pub enum ErrorKind {
     RangeTooLarge,
 }
 
+impl ErrorKind {
+    /// Every kind, in code order.
+    pub const ALL: [ErrorKind; 8] = [
+        ErrorKind::ParseError,
+        ErrorKind::UndefinedName,
+        ErrorKind::ArgumentCount,
+        ErrorKind::ArgumentType,
+        ErrorKind::DomainError,
+        ErrorKind::Overflow,
+        ErrorKind::ComplexResult,
+        ErrorKind::RangeTooLarge,
+    ];
+
+    /// Stable numeric code for this kind, as used by the C FFI's `error_kind`.
+    ///
+    /// Codes are never reused or renumbered; new kinds get new codes.
+    pub fn as_code(&self) -> i32 {
+        match self {
+            ErrorKind::ParseError => 0,
+            ErrorKind::UndefinedName => 1,
+            ErrorKind::ArgumentCount => 2,
+            ErrorKind::ArgumentType => 3,
+            ErrorKind::DomainError => 4,
+            ErrorKind::Overflow => 5,
+            ErrorKind::ComplexResult => 6,
+            ErrorKind::RangeTooLarge => 7,
+        }
+    }
+
+    /// The kind for a code produced by [`as_code`](Self::as_code), or `None`
+    /// if the code is unknown.
+    pub fn from_code(code: i32) -> Option<ErrorKind> {
+        ErrorKind::ALL.iter().find(|kind| kind.as_code() == code).cloned()
+    }
+}
+
 /// An error returned by any exath-engine function.
 #[derive(Debug, Clone)]
 pub struct ExathError {
@@ -35,6 +73,12 @@ pub struct ExathError {
 }
 
 impl ExathError {
+    /// Build an error from a kind and message, e.g. when reconstructing one
+    /// that was marshalled across an FFI boundary.
+    pub fn from_parts(kind: ErrorKind, message: String) -> Self {
+        ExathError { kind, message }
+    }
+
     pub fn parse(msg: impl Into<String>) -> Self {
         ExathError {
             kind: ErrorKind::ParseError,
@@ -98,4 +142,35 @@ impl fmt::Display for ExathError {
     }
 }
 
-impl std::error::Error for ExathError {}
+impl core::error::Error for ExathError {}
+
+#[cfg(test)]
+mod code_tests {
+    use super::*;
+
+    #[test]
+    fn every_kind_round_trips_through_its_code() {
+        for kind in ErrorKind::ALL {
+            assert_eq!(ErrorKind::from_code(kind.as_code()), Some(kind.clone()));
+        }
+        let codes: Vec<i32> = ErrorKind::ALL.iter().map(ErrorKind::as_code).collect();
+        assert_eq!(codes, (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unknown_codes_are_rejected() {
+        assert_eq!(ErrorKind::from_code(-1), None);
+        assert_eq!(ErrorKind::from_code(8), None);
+    }
+
+    #[test]
+    fn from_parts_rebuilds_an_error() {
+        let original = ExathError::domain("ln(0) is undefined");
+        let rebuilt = ExathError::from_parts(
+            ErrorKind::from_code(original.kind.as_code()).unwrap(),
+            original.message.clone(),
+        );
+        assert_eq!(rebuilt.kind, ErrorKind::DomainError);
+        assert_eq!(rebuilt.to_string(), original.to_string());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::angle_mode::AngleMode;
+use crate::error::ExathError;
+use super::calc_result::CalcResult;
+use super::cx::Cx;
+use crate::prelude::*;
+
+/// Fluent one-shot evaluation with a handful of known variables, without
+/// the overhead of a full [`Session`](super::Session).
+///
+/// ```
+/// use exath_engine::{AngleMode, CalcResult, EvalBuilder};
+/// let r = EvalBuilder::new(AngleMode::Rad).var("a", 5.0).eval("a*2").unwrap();
+/// assert_eq!(r, CalcResult::Real(10.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EvalBuilder {
+    angle_mode: AngleMode,
+    vars: HashMap<String, Cx>,
+}
+
+impl EvalBuilder {
+    pub fn new(angle_mode: AngleMode) -> Self {
+        EvalBuilder {
+            angle_mode,
+            vars: HashMap::new(),
+        }
+    }
+
+    /// Bind a real variable (replacing an earlier binding of the same name).
+    pub fn var(self, name: &str, value: f64) -> Self {
+        self.complex_var(name, value, 0.0)
+    }
+
+    /// Bind a complex variable `re + im·i`.
+    pub fn complex_var(mut self, name: &str, re: f64, im: f64) -> Self {
+        self.vars.insert(name.to_string(), Cx { re, im });
+        self
+    }
+
+    /// Evaluate `expr` against the bound variables. The builder is not
+    /// consumed, so several expressions can share one set of bindings.
+    pub fn eval(&self, expr: &str) -> Result<CalcResult, ExathError> {
+        super::evaluate_with_vars(expr, self.angle_mode, &self.vars)
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+
+    #[test]
+    fn chained_vars() {
+        let b = EvalBuilder::new(AngleMode::Rad)
+            .var("a", 5.0)
+            .var("b", 2.0)
+            .var("a", 3.0)
+            .complex_var("z", 0.0, 1.0);
+        assert_eq!(b.eval("a*b + 1").unwrap(), CalcResult::Real(7.0));
+        assert_eq!(b.eval("z*z").unwrap(), CalcResult::Real(-1.0));
+        assert_eq!(b.eval("a - b").unwrap(), CalcResult::Real(1.0));
+        assert!(b.eval("c").is_err());
+    }
+
+    #[test]
+    fn respects_angle_mode() {
+        let r = EvalBuilder::new(AngleMode::Deg).var("x", 90.0).eval("sin(x)").unwrap();
+        assert!((r.to_f64_lossy() - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,3 +1,8 @@
+use core::fmt;
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use crate::prelude::F64Ext;
+
 use super::cx::Cx;
 
 /// The result of a numeric evaluation.
@@ -11,6 +16,7 @@ use super::cx::Cx;
 /// match evaluate_complex("sqrt(-4)", AngleMode::Rad)? {
 ///     CalcResult::Real(x) => println!("real: {x}"),
 ///     CalcResult::Complex(re, im) => println!("{re} + {im}i"),
+///     CalcResult::Bool(b) => println!("{b}"),
 /// }
 /// # Ok::<(), exath_engine::ExathError>(())
 /// ```
@@ -20,12 +26,75 @@ pub enum CalcResult {
     Real(f64),
     /// A complex value, as `(real, imaginary)`.
     Complex(f64, f64),
+    /// The truth value of a comparison or logical expression. Only produced
+    /// when [`EvalConfig::bool_results`](crate::EvalConfig::bool_results) is
+    /// set; otherwise such results are `Real(1.0)` / `Real(0.0)`.
+    Bool(bool),
 }
 
 impl CalcResult {
+    /// Render the result for display: integers without a fraction,
+    /// `∞` / `-∞` / `NaN` for non-finite parts, complex as `a + bi`,
+    /// booleans as `true` / `false`. Magnitudes outside `[1e-4, 1e15]` use
+    /// scientific notation (`1.5e-7`, `1.23456789e20`); digits are the
+    /// shortest that read back to the same `f64`.
+    ///
+    /// Complex values are written the way a mathematician would: a unit
+    /// coefficient is dropped (`2 - i`), and a part that rounds to zero is
+    /// omitted (`3i`, `2`).
+    ///
+    /// ```
+    /// use exath_engine::CalcResult;
+    /// assert_eq!(CalcResult::Real(4.0).format(), "4");
+    /// assert_eq!(CalcResult::Real(f64::NEG_INFINITY).format(), "-∞");
+    /// assert_eq!(CalcResult::Complex(1.0, -2.5).format(), "1 - 2.5i");
+    /// assert_eq!(CalcResult::Complex(0.0, 1.0).format(), "i");
+    /// assert_eq!(CalcResult::Real(2.5e20).format(), "2.5e20");
+    /// ```
+    pub fn format(&self) -> String {
+        self.format_digits(None)
+    }
+
+    /// Like [`CalcResult::format`], rounded to `digits` significant digits
+    /// (at least 1), with trailing zeros dropped.
+    ///
+    /// ```
+    /// use exath_engine::CalcResult;
+    /// assert_eq!(CalcResult::Real(1.0 / 3.0).format_sig(4), "0.3333");
+    /// assert_eq!(CalcResult::Real(123456789e12).format_sig(3), "1.23e20");
+    /// ```
+    pub fn format_sig(&self, digits: usize) -> String {
+        self.format_digits(Some(digits.max(1)))
+    }
+
+    fn format_digits(&self, digits: Option<usize>) -> String {
+        match self {
+            CalcResult::Real(value) => format_f64(*value, digits),
+            CalcResult::Bool(b) => b.to_string(),
+            CalcResult::Complex(re, im) => {
+                let re_str = format_f64(*re, digits);
+                let im_abs = format_f64(im.abs(), digits);
+                if is_zero(&im_abs) {
+                    return re_str;
+                }
+                let im_term = if im_abs == "1" { "i".to_string() } else { format!("{}i", im_abs) };
+                let negative = *im < 0.0;
+                match (is_zero(&re_str), negative) {
+                    (true, false) => im_term,
+                    (true, true) => format!("-{}", im_term),
+                    (false, false) => format!("{} + {}", re_str, im_term),
+                    (false, true) => format!("{} - {}", re_str, im_term),
+                }
+            }
+        }
+    }
+
+    /// The value as an `f64`: `NaN` for a complex value, `1.0` / `0.0` for a
+    /// boolean.
     pub fn to_f64_lossy(&self) -> f64 {
         match self {
             CalcResult::Real(value) => *value,
+            CalcResult::Bool(b) => f64::from(u8::from(*b)),
             CalcResult::Complex(_, _) => f64::NAN,
         }
     }
@@ -40,3 +109,149 @@ impl Cx {
         }
     }
 }
+
+/// Renders like [`CalcResult::format`]: `3 + 4i`, `-i`, `2.5`.
+///
+/// ```
+/// use exath_engine::evaluator::Cx;
+/// assert_eq!(Cx::new(3.0, 4.0).to_string(), "3 + 4i");
+/// assert_eq!(format!("{}", Cx::imag(-1.0)), "-i");
+/// ```
+impl fmt::Display for Cx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_calc_result().format())
+    }
+}
+
+/// Format a single `f64`: whole numbers without a fraction, non-finite
+/// values as `∞`, `-∞` and `NaN`, scientific notation outside `[1e-4, 1e15]`.
+/// `digits` is the number of significant digits, `None` for the shortest
+/// round-trip rendering. Values within rounding noise of a whole number
+/// (including `0`) print as that number.
+fn format_f64(f: f64, digits: Option<usize>) -> String {
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "\u{221e}" } else { "-\u{221e}" }.to_string();
+    }
+    // Round to `digits` significant figures first, so whole numbers and the
+    // integer part of large values are rounded too (123456 → 120000).
+    let f = match digits {
+        Some(digits) => format!("{:.*e}", digits - 1, f).parse().unwrap_or(f),
+        None => f,
+    };
+    let rounded = f.round();
+    let tol = f.abs().max(1.0) * 1e-12;
+    if (f - rounded).abs() < tol && f.abs() < 1e15 {
+        return format!("{:.0}", rounded);
+    }
+    let magnitude = f.abs();
+    if !(1e-4..=1e15).contains(&magnitude) {
+        return match digits {
+            Some(digits) => {
+                let sci = format!("{:.*e}", digits - 1, f);
+                let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+                format!("{}e{}", trim_fraction(mantissa), exponent)
+            }
+            None => format!("{:e}", f),
+        };
+    }
+    match digits {
+        Some(digits) => {
+            let exponent = magnitude.log10().floor() as i32;
+            let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+            trim_fraction(&format!("{:.*}", decimals, f)).to_string()
+        }
+        None => format!("{}", f),
+    }
+}
+
+/// Drop trailing zeros after the decimal point, and the point itself.
+fn trim_fraction(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+/// Whether a `format_f64` rendering is zero (within its rounding tolerance).
+fn is_zero(rendered: &str) -> bool {
+    rendered == "0" || rendered == "-0"
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::{CalcResult, Cx};
+
+    fn fmt(re: f64, im: f64) -> String {
+        CalcResult::Complex(re, im).format()
+    }
+
+    #[test]
+    fn unit_and_zero_parts_are_elided() {
+        assert_eq!(fmt(0.0, 1.0), "i");
+        assert_eq!(fmt(0.0, -1.0), "-i");
+        assert_eq!(fmt(0.0, 3.0), "3i");
+        assert_eq!(fmt(2.0, -1.0), "2 - i");
+        assert_eq!(fmt(2.0, 1.0 + 1e-15), "2 + i");
+        assert_eq!(fmt(0.0, 0.0), "0");
+        assert_eq!(fmt(-0.0, -2.5), "-2.5i");
+        assert_eq!(fmt(1.5, 1e-20), "1.5");
+    }
+
+    #[test]
+    fn general_and_non_finite_values() {
+        assert_eq!(fmt(1.0, -2.5), "1 - 2.5i");
+        assert_eq!(fmt(1.0, f64::INFINITY), "1 + \u{221e}i");
+        assert_eq!(fmt(1.0, f64::NAN), "1 + NaNi");
+        assert_eq!(CalcResult::Real(-1.0).format(), "-1");
+    }
+
+    #[test]
+    fn scientific_outside_fixed_range() {
+        let real = |x: f64| CalcResult::Real(x).format();
+        assert_eq!(real(1.5e-7), "1.5e-7");
+        assert_eq!(real(-2.25e-5), "-2.25e-5");
+        assert_eq!(real(1.23456789e20), "1.23456789e20");
+        assert_eq!(real(-4e300), "-4e300");
+        assert_eq!(real(1e-13), "0");
+        assert_eq!(fmt(1e20, 3e-6), "1e20 + 3e-6i");
+    }
+
+    #[test]
+    fn fixed_in_mid_range() {
+        let real = |x: f64| CalcResult::Real(x).format();
+        assert_eq!(real(0.0001), "0.0001");
+        assert_eq!(real(123.456), "123.456");
+        assert_eq!(real(1e15), "1000000000000000");
+        assert_eq!(real(999999999999999.0), "999999999999999");
+    }
+
+    #[test]
+    fn significant_digits() {
+        let sig = |x: f64, d: usize| CalcResult::Real(x).format_sig(d);
+        assert_eq!(sig(std::f64::consts::PI, 3), "3.14");
+        assert_eq!(sig(123.456, 2), "120");
+        assert_eq!(sig(1234567.89, 3), "1230000");
+        assert_eq!(sig(123456.0, 2), "120000");
+        assert_eq!(sig(-96.0, 1), "-100");
+        assert_eq!(sig(9.96, 2), "10");
+        assert_eq!(sig(0.000123456, 3), "0.000123");
+        assert_eq!(sig(1.23456789e20, 3), "1.23e20");
+        assert_eq!(sig(1.5e-7, 6), "1.5e-7");
+        assert_eq!(sig(2.0 / 3.0, 0), "0.7");
+        assert_eq!(CalcResult::Complex(1.0 / 3.0, -2.0 / 3.0).format_sig(2), "0.33 - 0.67i");
+    }
+
+    #[test]
+    fn cx_display_matches_format() {
+        assert_eq!(Cx::real(3.0).to_string(), "3");
+        assert_eq!(Cx::real(0.1 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(Cx::imag(1.0).to_string(), "i");
+        assert_eq!(Cx::imag(-2.0).to_string(), "-2i");
+        assert_eq!(Cx::new(3.0, 4.0).to_string(), "3 + 4i");
+        assert_eq!(Cx::new(1.5, -1.0).to_string(), "1.5 - i");
+    }
+}
@@ -1,20 +1,74 @@
+use crate::config::BranchPolicy;
 use crate::error::ExathError;
+use core::hash::{Hash, Hasher};
+#[cfg(not(feature = "std"))]
+use crate::prelude::F64Ext;
 
 /// Complex number type used throughout exath-engine.
 /// All math is done over ℂ; real numbers are the special case im == 0.
+///
+/// Equality is exact (bitwise per component, no tolerance), with `0.0` and
+/// `-0.0` treated as equal and a NaN equal to an identical NaN, so `Cx` can be
+/// used as a hash key. Compare computed values with a tolerance instead.
+///
+/// Build values with [`Cx::new`], [`Cx::real`], [`Cx::imag`] or
+/// [`Cx::from_polar`] rather than a struct literal: the public fields may
+/// become private in a future major version.
 #[derive(Debug, Clone, Copy)]
 pub struct Cx {
     pub re: f64,
     pub im: f64,
 }
 
+/// Bit pattern used for equality and hashing; folds `-0.0` into `0.0`.
+fn key_bits(x: f64) -> u64 {
+    if x == 0.0 { 0 } else { x.to_bits() }
+}
+
+impl PartialEq for Cx {
+    fn eq(&self, other: &Self) -> bool {
+        key_bits(self.re) == key_bits(other.re) && key_bits(self.im) == key_bits(other.im)
+    }
+}
+
+impl Eq for Cx {}
+
+impl Hash for Cx {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        key_bits(self.re).hash(state);
+        key_bits(self.im).hash(state);
+    }
+}
+
 impl Cx {
+    /// `re + im·i`. Prefer this to a struct literal in downstream code.
+    pub fn new(re: f64, im: f64) -> Self {
+        Cx { re, im }
+    }
+
     pub fn real(re: f64) -> Self {
         Cx { re, im: 0.0 }
     }
 
+    /// The pure imaginary number `im·i`.
+    pub fn imag(im: f64) -> Self {
+        Cx { re: 0.0, im }
+    }
+
+    /// `r·(cos θ + i·sin θ)`, with `theta` in radians.
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Cx { re: r * theta.cos(), im: r * theta.sin() }
+    }
+
+    /// Modulus and argument `(r, θ)`, with `θ` in radians in `(-π, π]`.
+    /// Inverse of [`Cx::from_polar`].
+    pub fn to_polar(&self) -> (f64, f64) {
+        (self.abs_val(), self.arg())
+    }
+
     pub fn is_real(&self) -> bool {
-        self.im.abs() < 1e-12
+        // A NaN imaginary part is not "small": keep such values complex.
+        !self.im.is_nan() && self.im.abs() < 1e-12
     }
 
     pub fn add(self, rhs: Cx) -> Cx {
@@ -32,6 +86,17 @@ impl Cx {
     }
 
     pub fn mul(self, rhs: Cx) -> Cx {
+        // Scale componentwise when one side is real, so `inf * 2` stays
+        // `inf` instead of picking up `inf * 0 = NaN` in the cross terms.
+        if self.im == 0.0 && rhs.im == 0.0 {
+            return Cx::real(self.re * rhs.re);
+        }
+        if rhs.im == 0.0 {
+            return Cx { re: self.re * rhs.re, im: self.im * rhs.re };
+        }
+        if self.im == 0.0 {
+            return Cx { re: self.re * rhs.re, im: self.re * rhs.im };
+        }
         Cx {
             re: self.re * rhs.re - self.im * rhs.im,
             im: self.re * rhs.im + self.im * rhs.re,
@@ -43,6 +108,13 @@ impl Cx {
         if denominator == 0.0 {
             return Err(ExathError::domain("Division by zero"));
         }
+        if self.im == 0.0 && rhs.im == 0.0 {
+            return Ok(Cx::real(self.re / rhs.re));
+        }
+        if rhs.im == 0.0 {
+            // Real divisor: divide componentwise (keeps `1/inf == 0`).
+            return Ok(Cx { re: self.re / rhs.re, im: self.im / rhs.re });
+        }
         Ok(Cx {
             re: (self.re * rhs.re + self.im * rhs.im) / denominator,
             im: (self.im * rhs.re - self.re * rhs.im) / denominator,
@@ -67,18 +139,44 @@ impl Cx {
     }
 
     pub fn ln(self) -> Result<Cx, ExathError> {
+        self.ln_in(BranchPolicy::Principal)
+    }
+
+    /// The argument of `self` in the range `branch` selects; for
+    /// [`BranchPolicy::Principal`] this is [`Cx::arg`].
+    pub fn arg_in(self, branch: BranchPolicy) -> f64 {
+        let principal = self.arg();
+        match branch {
+            BranchPolicy::Principal => principal,
+            BranchPolicy::ArgFrom(start) => {
+                let turn = 2.0 * core::f64::consts::PI;
+                principal + turn * ((start - principal) / turn).ceil()
+            }
+        }
+    }
+
+    /// Natural logarithm on the branch `branch` selects: its imaginary part
+    /// is [`Cx::arg_in`].
+    pub fn ln_in(self, branch: BranchPolicy) -> Result<Cx, ExathError> {
+        // A positive real skips the modulus, whose sqrt(re²) can be an ulp off.
+        if self.im == 0.0 && self.re > 0.0 {
+            return Ok(Cx { re: self.re.ln(), im: self.arg_in(branch) });
+        }
         let modulus = self.abs_val();
         if modulus == 0.0 {
             return Err(ExathError::domain("ln undefined for 0"));
         }
         Ok(Cx {
             re: modulus.ln(),
-            im: self.arg(),
+            im: self.arg_in(branch),
         })
     }
 
     pub fn exp(self) -> Cx {
         let exp_re = self.re.exp();
+        if self.im == 0.0 {
+            return Cx::real(exp_re);
+        }
         Cx {
             re: exp_re * self.im.cos(),
             im: exp_re * self.im.sin(),
@@ -86,16 +184,64 @@ impl Cx {
     }
 
     pub fn pow(self, exponent: Cx) -> Result<Cx, ExathError> {
+        self.pow_in(exponent, BranchPolicy::Principal)
+    }
+
+    /// `self^exponent` as `exp(exponent · ln self)` with the logarithm on
+    /// the branch `branch` selects. Whole exponents give the same value on
+    /// every branch.
+    pub fn pow_in(self, exponent: Cx, branch: BranchPolicy) -> Result<Cx, ExathError> {
         if self.re == 0.0 && self.im == 0.0 {
             if exponent.re > 0.0 {
                 return Ok(Cx::real(0.0));
             }
             return Err(ExathError::domain("0^x undefined for x≤0"));
         }
-        Ok(self.ln()?.mul(exponent).exp())
+        // Small whole exponents by repeated squaring: exact where the
+        // products are, so `i^2` is `-1` with no dust from `ln`/`exp`.
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && (0.0..=64.0).contains(&exponent.re) {
+            if let Some(z) = self.powu(exponent.re as u32) {
+                return Ok(z);
+            }
+        }
+        Ok(self.ln_in(branch)?.mul(exponent).exp())
     }
 
+    /// `None` once a complex intermediate overflows: the cross terms would
+    /// then multiply `inf * 0` and leave a `NaN` part that `exp(n·ln z)`
+    /// does not. Real bases stay real in `mul`, so they never bail out.
+    fn powu(self, mut n: u32) -> Option<Cx> {
+        let finite = |z: Cx| self.im == 0.0 || (z.re.is_finite() && z.im.is_finite());
+        let mut base = self;
+        let mut acc = Cx::real(1.0);
+        while n > 0 {
+            if n & 1 == 1 {
+                acc = acc.mul(base);
+                if !finite(acc) {
+                    return None;
+                }
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.mul(base);
+                if !finite(base) {
+                    return None;
+                }
+            }
+        }
+        Some(acc)
+    }
+
+    /// Principal square root. Real inputs are exact: a non-negative real
+    /// gives a real root and a negative real a pure imaginary one, with no
+    /// rounding dust from the polar form.
     pub fn sqrt(self) -> Cx {
+        if self.im == 0.0 && self.re >= 0.0 {
+            return Cx::real(self.re.sqrt());
+        }
+        if self.im == 0.0 && self.re < 0.0 {
+            return Cx { re: 0.0, im: (-self.re).sqrt() };
+        }
         let modulus = self.abs_val().sqrt();
         let half_angle = self.arg() / 2.0;
         Cx {
@@ -103,4 +249,190 @@ impl Cx {
             im: modulus * half_angle.sin(),
         }
     }
+
+    /// Square root on the branch `branch` selects, `|z|^½ · e^(i·arg/2)`
+    /// with the argument from [`Cx::arg_in`]; [`Cx::sqrt`] for the
+    /// principal branch.
+    pub fn sqrt_in(self, branch: BranchPolicy) -> Cx {
+        match branch {
+            BranchPolicy::Principal => self.sqrt(),
+            BranchPolicy::ArgFrom(_) => {
+                Cx::from_polar(self.abs_val().sqrt(), self.arg_in(branch) / 2.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod polar_tests {
+    use super::Cx;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn close(a: Cx, b: Cx) {
+        assert!((a.re - b.re).abs() < 1e-12 && (a.im - b.im).abs() < 1e-12, "{:?} vs {:?}", a, b);
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(Cx::new(1.0, -2.0), Cx { re: 1.0, im: -2.0 });
+        assert_eq!(Cx::imag(3.0), Cx::new(0.0, 3.0));
+        close(Cx::from_polar(2.0, FRAC_PI_2), Cx::imag(2.0));
+        close(Cx::from_polar(1.0, PI), Cx::real(-1.0));
+    }
+
+    #[test]
+    fn polar_round_trip() {
+        for z in [Cx::new(3.0, 4.0), Cx::new(-1.0, 0.5), Cx::new(0.0, -2.0), Cx::real(-7.0)] {
+            let (r, theta) = z.to_polar();
+            close(Cx::from_polar(r, theta), z);
+        }
+        assert_eq!(Cx::new(3.0, 4.0).to_polar().0, 5.0);
+        assert_eq!(Cx::real(-1.0).to_polar(), (1.0, PI));
+        let (r, theta) = Cx::from_polar(2.0, 0.75).to_polar();
+        assert!((r - 2.0).abs() < 1e-12 && (theta - 0.75).abs() < 1e-12);
+    }
+}
+
+#[cfg(test)]
+mod ln_tests {
+    use super::Cx;
+    use crate::config::BranchPolicy;
+    use std::f64::consts::{E, PI};
+
+    #[test]
+    fn positive_reals_match_f64_ln() {
+        assert_eq!(Cx::real(E).ln().unwrap().re, E.ln());
+        assert_eq!(Cx::real(10.0).ln().unwrap(), Cx::new(10f64.ln(), 0.0));
+        assert_eq!(Cx::real(10.0).ln().unwrap().im, 0.0);
+        assert_eq!(Cx::real(1e300).ln().unwrap().re, 1e300f64.ln());
+    }
+
+    #[test]
+    fn other_values_keep_their_argument() {
+        let z = Cx::real(-1.0).ln().unwrap();
+        assert_eq!(z, Cx::new(0.0, PI));
+        let w = Cx::real(2.0).ln_in(BranchPolicy::ArgFrom(1.0)).unwrap();
+        assert_eq!(w, Cx::new(2f64.ln(), 2.0 * PI));
+        assert!(Cx::real(0.0).ln().is_err());
+    }
+}
+
+#[cfg(test)]
+mod pow_tests {
+    use super::Cx;
+
+    #[test]
+    fn whole_exponents_are_exact() {
+        let i = Cx::imag(1.0);
+        assert_eq!(i.pow(Cx::real(2.0)).unwrap(), Cx::new(-1.0, 0.0));
+        assert_eq!(i.pow(Cx::real(3.0)).unwrap(), Cx::new(0.0, -1.0));
+        assert_eq!(Cx::new(1.0, 1.0).pow(Cx::real(4.0)).unwrap(), Cx::real(-4.0));
+        assert_eq!(Cx::real(16.0).pow(Cx::real(2.0)).unwrap(), Cx::real(256.0));
+        assert_eq!(Cx::real(-2.0).pow(Cx::real(5.0)).unwrap(), Cx::real(-32.0));
+        assert_eq!(Cx::real(7.0).pow(Cx::real(0.0)).unwrap(), Cx::real(1.0));
+        assert!(Cx::real(0.0).pow(Cx::real(0.0)).is_err());
+    }
+
+    #[test]
+    fn overflowing_complex_powers_avoid_nan() {
+        let z = Cx::new(1e200, 1.0).pow(Cx::real(4.0)).unwrap();
+        assert_eq!((z.re, z.im), (f64::INFINITY, f64::INFINITY));
+        assert_eq!(Cx::real(1e200).pow(Cx::real(4.0)).unwrap(), Cx::real(f64::INFINITY));
+        assert_eq!(Cx::real(-1e200).pow(Cx::real(3.0)).unwrap(), Cx::real(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn other_exponents_use_the_principal_branch() {
+        let root = Cx::real(-4.0).pow(Cx::real(0.5)).unwrap();
+        assert!(root.re.abs() < 1e-12 && (root.im - 2.0).abs() < 1e-12);
+        let big = Cx::real(2.0).pow(Cx::real(65.0)).unwrap();
+        assert!((big.re / 2f64.powi(65) - 1.0).abs() < 1e-12);
+    }
+}
+
+#[cfg(test)]
+mod sqrt_tests {
+    use super::Cx;
+
+    #[test]
+    fn real_roots_are_exact() {
+        let root = Cx::real(4.0).sqrt();
+        assert_eq!(root.re, 2.0);
+        assert_eq!(root.im.to_bits(), 0.0f64.to_bits());
+        assert_eq!(Cx::real(2.0).sqrt().re, 2.0f64.sqrt());
+        assert_eq!(Cx::real(0.0).sqrt(), Cx::real(0.0));
+    }
+
+    #[test]
+    fn negative_reals_give_pure_imaginary_roots() {
+        let root = Cx::real(-4.0).sqrt();
+        assert_eq!(root.re.to_bits(), 0.0f64.to_bits());
+        assert_eq!(root.im, 2.0);
+        // The sign of a zero imaginary part does not flip the branch.
+        assert_eq!(Cx { re: -9.0, im: -0.0 }.sqrt(), Cx { re: 0.0, im: 3.0 });
+    }
+
+    #[test]
+    fn complex_roots_square_back() {
+        let z = Cx { re: 3.0, im: 4.0 };
+        let root = z.sqrt();
+        assert!((root.re - 2.0).abs() < 1e-15 && (root.im - 1.0).abs() < 1e-15);
+    }
+}
+
+#[cfg(test)]
+mod eq_tests {
+    use super::Cx;
+    use std::collections::HashSet;
+
+    #[test]
+    fn exact_equality_and_hashing() {
+        assert_eq!(Cx::real(1.5), Cx { re: 1.5, im: 0.0 });
+        assert_eq!(Cx::real(0.0), Cx::real(-0.0));
+        assert_ne!(Cx::real(0.1 + 0.2), Cx::real(0.3));
+        assert_eq!(Cx::real(f64::NAN), Cx::real(f64::NAN));
+        let set: HashSet<Cx> = [Cx::real(0.0), Cx::real(-0.0), Cx { re: 0.0, im: 1.0 }].into();
+        assert_eq!(set.len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod non_finite_tests {
+    use crate::angle_mode::AngleMode;
+    use crate::evaluator::{evaluate, evaluate_complex, CalcResult};
+
+    fn eval(expr: &str) -> f64 {
+        evaluate(expr, AngleMode::Rad).unwrap()
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval("inf"), f64::INFINITY);
+        assert_eq!(eval("-inf"), f64::NEG_INFINITY);
+        assert!(eval("nan").is_nan());
+        assert_eq!(eval("Inf"), f64::INFINITY);
+    }
+
+    #[test]
+    fn arithmetic_stays_real() {
+        assert_eq!(eval("1/inf"), 0.0);
+        assert_eq!(eval("inf + 1"), f64::INFINITY);
+        assert_eq!(eval("2*inf"), f64::INFINITY);
+        assert_eq!(eval("-inf*3"), f64::NEG_INFINITY);
+        assert_eq!(eval("inf^2"), f64::INFINITY);
+        assert_eq!(eval("exp(-inf)"), 0.0);
+        assert_eq!(eval("sqrt(inf)"), f64::INFINITY);
+        assert!(eval("inf - inf").is_nan());
+        assert!(eval("nan + 1").is_nan());
+    }
+
+    #[test]
+    fn formatting() {
+        let fmt = |e: &str| evaluate_complex(e, AngleMode::Rad).unwrap().format();
+        assert_eq!(fmt("inf"), "\u{221e}");
+        assert_eq!(fmt("-inf"), "-\u{221e}");
+        assert_eq!(fmt("nan"), "NaN");
+        assert_eq!(fmt("sqrt(-4) + 1"), "1 + 2i");
+        assert_eq!(CalcResult::Complex(1.0, f64::NAN).format(), "1 + NaNi");
+    }
 }
@@ -1,4 +1,6 @@
 use crate::error::ExathError;
+#[cfg(not(feature = "std"))]
+use crate::prelude::F64Ext;
 
 pub fn factorial(n: f64) -> Result<f64, ExathError> {
     if n < 0.0 || n.fract() != 0.0 {
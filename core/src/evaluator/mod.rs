@@ -1,19 +1,35 @@
+mod builder;
 mod cx;
 mod calc_result;
 mod factorial;
 mod functions;
+mod rng;
+#[cfg(feature = "std")]
 mod session;
+#[cfg(feature = "profiling")]
+mod timing;
 
+pub use builder::EvalBuilder;
 pub use cx::Cx;
 pub use calc_result::CalcResult;
 pub use factorial::factorial;
 pub use functions::apply_function;
-pub use session::{Session, LineResult};
+pub(crate) use functions::canonical_name;
+pub(crate) use rng::Rng;
+#[cfg(feature = "std")]
+pub(crate) use functions::FUNCTION_ALIASES;
+#[cfg(feature = "std")]
+pub use session::{Changes, Session, LineResult, VarChangeCallback};
+#[cfg(feature = "std")]
+pub(crate) use session::value_ast;
+#[cfg(feature = "profiling")]
+pub use timing::{evaluate_timed, Timings};
 
 use crate::angle_mode::AngleMode;
-use crate::ast::{eval_ast, UserFns};
+use crate::ast::{eval_ast_with, Ast, BinOp, UserFns};
+use crate::config::EvalConfig;
 use crate::error::ExathError;
-use std::collections::HashMap;
+use crate::prelude::*;
 
 /// Evaluate an expression to a real `f64` (stateless, numeric only).
 ///
@@ -21,10 +37,30 @@ use std::collections::HashMap;
 /// complex, use this when you specifically want a real number. Does not
 /// understand symbolic forms like `diff(x^2, x)` or `factor(...)`; for those
 /// use [`Session::eval_line`].
+///
+/// A complex result is reported as an error of kind
+/// [`ErrorKind::ComplexResult`](crate::ErrorKind::ComplexResult), distinct
+/// from syntax errors ([`ErrorKind::ParseError`](crate::ErrorKind::ParseError)),
+/// so a caller can fall back without matching on the message:
+///
+/// ```
+/// use exath_engine::{evaluate, evaluate_complex, AngleMode, CalcResult, ErrorKind};
+///
+/// let value = match evaluate("sqrt(-4)", AngleMode::Rad) {
+///     Ok(x) => CalcResult::Real(x),
+///     Err(e) if e.kind == ErrorKind::ComplexResult => evaluate_complex("sqrt(-4)", AngleMode::Rad)?,
+///     Err(e) => return Err(e),
+/// };
+/// assert!(matches!(value, CalcResult::Complex(_, _)));
+/// # Ok::<(), exath_engine::ExathError>(())
+/// ```
+///
+/// If both outcomes are acceptable, call [`evaluate_complex`] directly.
 pub fn evaluate(expr: &str, angle_mode: AngleMode) -> Result<f64, ExathError> {
     match evaluate_complex(expr, angle_mode)? {
         CalcResult::Real(value) => Ok(value),
         CalcResult::Complex(_, _) => Err(ExathError::complex_result("Result is complex")),
+        result @ CalcResult::Bool(_) => Ok(result.to_f64_lossy()),
     }
 }
 
@@ -38,6 +74,46 @@ pub fn evaluate_complex(expr: &str, angle_mode: AngleMode) -> Result<CalcResult,
     evaluate_with_vars(expr, angle_mode, &HashMap::new())
 }
 
+/// The kind of result a caller of [`evaluate_typed`] wants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultMode {
+    /// Real numbers only: a complex result becomes `NaN`, as in plain `f64`
+    /// arithmetic (`sqrt(-1)` is `NaN`).
+    RealOnly,
+    /// Keep complex results, like [`evaluate_complex`].
+    #[default]
+    Complex,
+    /// A complex result is an [`ErrorKind::ComplexResult`](crate::ErrorKind::ComplexResult)
+    /// error, like [`evaluate`].
+    RealOrError,
+}
+
+/// Evaluate an expression, shaping the result as `want` asks.
+///
+/// ```
+/// use exath_engine::{evaluate_typed, AngleMode, CalcResult, ResultMode};
+///
+/// let complex = evaluate_typed("sqrt(-1)", AngleMode::Rad, ResultMode::Complex).unwrap();
+/// assert_eq!(complex, CalcResult::Complex(0.0, 1.0));
+/// let real = evaluate_typed("sqrt(-1)", AngleMode::Rad, ResultMode::RealOnly).unwrap();
+/// assert!(matches!(real, CalcResult::Real(x) if x.is_nan()));
+/// assert!(evaluate_typed("sqrt(-1)", AngleMode::Rad, ResultMode::RealOrError).is_err());
+/// ```
+pub fn evaluate_typed(
+    expr: &str,
+    angle_mode: AngleMode,
+    want: ResultMode,
+) -> Result<CalcResult, ExathError> {
+    let result = evaluate_complex(expr, angle_mode)?;
+    match (want, result) {
+        (ResultMode::RealOnly, CalcResult::Complex(_, _)) => Ok(CalcResult::Real(f64::NAN)),
+        (ResultMode::RealOrError, CalcResult::Complex(_, _)) => {
+            Err(ExathError::complex_result("Result is complex"))
+        }
+        (_, result) => Ok(result),
+    }
+}
+
 /// Evaluate an expression with a variable map.
 pub fn evaluate_with_vars(
     expr: &str,
@@ -47,14 +123,177 @@ pub fn evaluate_with_vars(
     evaluate_with_vars_and_fns(expr, angle_mode, vars, &UserFns::new())
 }
 
+/// Evaluate an expression with real-valued variables, the common case for
+/// plotting and forms. Use [`evaluate_with_vars`] for complex inputs.
+///
+/// ```
+/// use exath_engine::{evaluate_with_reals, AngleMode, CalcResult};
+/// use std::collections::HashMap;
+///
+/// let vars = HashMap::from([("x".to_string(), 3.0), ("y".to_string(), 4.0)]);
+/// let result = evaluate_with_reals("x^2+y^2", AngleMode::Rad, &vars).unwrap();
+/// assert!(matches!(result, CalcResult::Real(v) if (v - 25.0).abs() < 1e-9));
+/// ```
+pub fn evaluate_with_reals(
+    expr: &str,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, f64>,
+) -> Result<CalcResult, ExathError> {
+    let vars: HashMap<String, Cx> = vars
+        .iter()
+        .map(|(name, value)| (name.clone(), Cx::real(*value)))
+        .collect();
+    evaluate_with_vars(expr, angle_mode, &vars)
+}
+
 /// Evaluate an expression with a variable map and user-defined functions.
 pub fn evaluate_with_vars_and_fns(
     expr: &str,
     angle_mode: AngleMode,
     vars: &HashMap<String, Cx>,
     fns: &UserFns,
+) -> Result<CalcResult, ExathError> {
+    evaluate_with_config(expr, angle_mode, vars, fns, &EvalConfig::default())
+}
+
+/// Evaluate several expressions against one shared variable map.
+///
+/// Results come back in input order, and each expression succeeds or fails
+/// on its own, so one bad cell does not fail the whole batch.
+///
+/// ```
+/// use exath_engine::{evaluate_batch, AngleMode, CalcResult};
+/// use exath_engine::evaluator::Cx;
+/// use std::collections::HashMap;
+///
+/// let vars = HashMap::from([("x".to_string(), Cx::real(2.0))]);
+/// let results = evaluate_batch(&["x + 1", "x +* 1"], AngleMode::Rad, &vars);
+/// assert_eq!(results[0].as_ref().unwrap(), &CalcResult::Real(3.0));
+/// assert!(results[1].is_err());
+/// ```
+pub fn evaluate_batch(
+    exprs: &[&str],
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+) -> Vec<Result<CalcResult, ExathError>> {
+    let fns = UserFns::new();
+    let config = EvalConfig::default();
+    exprs
+        .iter()
+        .map(|expr| evaluate_with_config(expr, angle_mode, vars, &fns, &config))
+        .collect()
+}
+
+/// Like [`evaluate_with_vars_and_fns`], with explicit evaluation policies.
+pub(crate) fn evaluate_with_config(
+    expr: &str,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+    config: &EvalConfig,
 ) -> Result<CalcResult, ExathError> {
     let ast = crate::ast::parse_str(expr)?;
-    let result = eval_ast(&ast, vars, fns, angle_mode)?;
-    Ok(result.to_calc_result())
+    let value = eval_ast_with(&ast, vars, fns, angle_mode, config)?;
+    Ok(to_result(&ast, value, config))
+}
+
+/// The [`CalcResult`] for `value`, the value of `ast`: a
+/// [`CalcResult::Bool`] when `config.bool_results` is set and the outermost
+/// operator of `ast` is a comparison or logical one.
+pub(crate) fn to_result(ast: &Ast, value: Cx, config: &EvalConfig) -> CalcResult {
+    let boolean = match ast {
+        Ast::BinOp(op, _, _) => matches!(
+            op,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+                | BinOp::And | BinOp::Or
+        ),
+        Ast::UnaryNot(_) => true,
+        _ => false,
+    };
+    if config.bool_results && boolean {
+        CalcResult::Bool(value.re != 0.0)
+    } else {
+        value.to_calc_result()
+    }
+}
+
+#[cfg(test)]
+mod evaluate_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn error_kind_separates_complex_from_invalid() {
+        let kind = |e: &str| evaluate(e, AngleMode::Rad).unwrap_err().kind;
+        assert_eq!(kind("sqrt(-1)"), ErrorKind::ComplexResult);
+        assert_eq!(kind("ln(-2)"), ErrorKind::ComplexResult);
+        assert_eq!(kind("2 +* 3"), ErrorKind::ParseError);
+        assert_eq!(kind("y + 1"), ErrorKind::UndefinedName);
+        assert_eq!(kind("1/0"), ErrorKind::DomainError);
+    }
+
+    #[test]
+    fn complex_fallback_by_kind() {
+        let eval_any = |e: &str| match evaluate(e, AngleMode::Rad) {
+            Ok(x) => Ok(CalcResult::Real(x)),
+            Err(err) if err.kind == ErrorKind::ComplexResult => evaluate_complex(e, AngleMode::Rad),
+            Err(err) => Err(err),
+        };
+        assert_eq!(eval_any("2 + 2").unwrap(), CalcResult::Real(4.0));
+        assert!(matches!(eval_any("sqrt(-9)").unwrap(), CalcResult::Complex(_, im) if (im - 3.0).abs() < 1e-12));
+        assert!(eval_any("2 +* 3").is_err());
+    }
+
+    #[test]
+    fn typed_results_for_each_mode() {
+        let typed = |want| evaluate_typed("sqrt(-1)", AngleMode::Rad, want);
+        assert_eq!(typed(ResultMode::Complex).unwrap(), CalcResult::Complex(0.0, 1.0));
+        assert!(matches!(typed(ResultMode::RealOnly).unwrap(), CalcResult::Real(x) if x.is_nan()));
+        assert_eq!(typed(ResultMode::RealOrError).unwrap_err().kind, ErrorKind::ComplexResult);
+        for want in [ResultMode::RealOnly, ResultMode::Complex, ResultMode::RealOrError] {
+            assert_eq!(evaluate_typed("sqrt(4)", AngleMode::Rad, want).unwrap(), CalcResult::Real(2.0));
+            assert_eq!(evaluate_typed("2 +* 1", AngleMode::Rad, want).unwrap_err().kind, ErrorKind::ParseError);
+        }
+    }
+
+    #[test]
+    fn deg_and_rad_ignore_the_angle_mode() {
+        let eval = |e: &str, mode| evaluate(e, mode).unwrap();
+        let close = |a: f64, b: f64| assert!((a - b).abs() < 1e-12, "{} vs {}", a, b);
+        close(eval("sin(rad(90))", AngleMode::Rad), eval("sin(90)", AngleMode::Deg));
+        close(eval("deg(asin(1))", AngleMode::Rad), eval("asin(1)", AngleMode::Deg));
+        close(eval("cos(rad(60))", AngleMode::Rad), 0.5);
+        for mode in [AngleMode::Rad, AngleMode::Deg, AngleMode::Grad] {
+            close(eval("rad(90)", mode), std::f64::consts::FRAC_PI_2);
+            close(eval("deg(pi)", mode), 180.0);
+            close(eval("deg(rad(37))", mode), 37.0);
+        }
+        // In Deg mode the converted value is read as degrees again.
+        close(eval("sin(rad(90))", AngleMode::Deg), std::f64::consts::FRAC_PI_2.to_radians().sin());
+    }
+
+    #[test]
+    fn batch_isolates_errors() {
+        let mut vars = HashMap::new();
+        vars.insert("x".to_string(), Cx::real(3.0));
+        let results = evaluate_batch(&["x * 3", "x +* 1", "y", "sqrt(-x^2)", ""], AngleMode::Rad, &vars);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().unwrap(), &CalcResult::Real(9.0));
+        assert_eq!(results[1].as_ref().unwrap_err().kind, ErrorKind::ParseError);
+        assert_eq!(results[2].as_ref().unwrap_err().kind, ErrorKind::UndefinedName);
+        assert!(matches!(results[3], Ok(CalcResult::Complex(_, im)) if (im - 3.0).abs() < 1e-12));
+        assert!(results[4].is_err());
+        assert!(evaluate_batch(&[], AngleMode::Rad, &vars).is_empty());
+    }
+
+    #[test]
+    fn function_aliases() {
+        let eval = |e: &str| evaluate(e, AngleMode::Rad).unwrap();
+        assert_eq!(eval("arcsin(1)"), eval("asin(1)"));
+        assert_eq!(eval("ArcTan 1"), eval("atan(1)"));
+        assert_eq!(eval("loge(e^2)"), 2.0);
+        assert_eq!(eval("modulus(-3)"), 3.0);
+        let ast = crate::ast::parse_str("arccos(x)").unwrap();
+        assert_eq!(format!("{:?}", ast), format!("{:?}", crate::ast::parse_str("acos(x)").unwrap()));
+    }
 }
@@ -0,0 +1,47 @@
+//! The pseudo-random generator behind `rand()` and `randint(a, b)`.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// SplitMix64: small, fast, statistically sound for Monte-Carlo use, and
+/// fully determined by its seed, so a seeded run is reproducible. Not for
+/// cryptography. The state is atomic so evaluation can draw through `&`
+/// and a `Session` stays `Sync`.
+#[derive(Debug)]
+pub(crate) struct Rng {
+    state: AtomicU64,
+}
+
+impl Clone for Rng {
+    fn clone(&self) -> Self {
+        Rng { state: AtomicU64::new(self.state.load(Ordering::Relaxed)) }
+    }
+}
+
+impl Rng {
+    #[cfg(feature = "std")]
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng { state: AtomicU64::new(seed) }
+    }
+
+    pub(crate) fn next_u64(&self) -> u64 {
+        const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut z = self.state.fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`, from the top 53 bits.
+    pub(crate) fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in `lo..=hi`, for `lo <= hi`.
+    pub(crate) fn next_in(&self, lo: i64, hi: i64) -> i64 {
+        let span = (hi as i128 - lo as i128 + 1) as u128;
+        // Multiply-shift maps 64 random bits onto the span without the
+        // skew of `%` for large spans.
+        let offset = (self.next_u64() as u128 * span) >> 64;
+        (lo as i128 + offset as i128) as i64
+    }
+}
@@ -1,10 +1,12 @@
 use crate::angle_mode::AngleMode;
-use crate::ast::{eval_ast, parse_str, Ast, UserFns};
+use crate::ast::{eval_ast_native, parse_str_with, Ast, BinOp, FnDefaults, NativeFns, UserFns};
+use crate::config::{EvalConfig, ParseConfig};
 use crate::error::ExathError;
 use crate::symbolic;
 use super::calc_result::CalcResult;
 use super::cx::Cx;
-use std::collections::HashMap;
+use super::rng::Rng;
+use std::collections::{HashMap, HashSet};
 
 /// Result of [`Session::eval_line`]: either a computed number or, for symbolic
 /// forms like `diff(...)` / `simplify(...)`, an expression rendered as a string.
@@ -29,34 +31,218 @@ pub enum LineResult {
 /// s.eval("f(x) = x^2 + 1").unwrap();
 /// let r2 = s.eval("f(4)").unwrap();  // CalcResult::Real(17.0)
 /// ```
+///
+/// # Scoping of user functions
+///
+/// Free variables in a function body are resolved **dynamically**, at call
+/// time, against the session's variables as they are then:
+///
+/// ```
+/// use exath_engine::{Session, AngleMode, CalcResult};
+/// let mut s = Session::new(AngleMode::Rad);
+/// s.eval("a = 1").unwrap();
+/// s.eval("f(x) = x + a").unwrap();
+/// s.eval("a = 10").unwrap();
+/// assert_eq!(s.eval("f(1)").unwrap(), CalcResult::Real(11.0));
+/// ```
+///
+/// Parameters shadow session variables of the same name for the duration of
+/// the call. Use [`Session::define_fn_lexical`] to capture the current values
+/// of free variables at definition time instead.
 pub struct Session {
     pub angle_mode: AngleMode,
+    /// Evaluation policies (division by zero, …) used by every eval call.
+    pub config: EvalConfig,
+    /// Parse options (implicit multiplication, …) for every line and
+    /// function body given to the session.
+    pub parse_config: ParseConfig,
     vars: HashMap<String, Cx>,
+    /// Variables declared with `const name = expr`, which later assignments
+    /// and `del` reject.
+    consts: HashSet<String>,
     fns: UserFns,
+    /// Defaults of the trailing parameters of `fns`, from `f(x, n = 2) = …`.
+    fn_defaults: FnDefaults,
+    /// Host functions registered with [`Session::register_native`].
+    natives: NativeFns,
     /// Symbolic variables, names bound to an expression (e.g. via
     /// `g = diff(x^2, x)`). Used only by [`Session::eval_line`].
     sym_vars: HashMap<String, Ast>,
     /// Sign assumptions on variables (+1 = nonnegative, −1 = nonpositive),
     /// set via `assume(x > 0)`; consulted by `simplify`.
     assumptions: HashMap<String, i8>,
+    /// Observer registered with [`Session::on_var_change`].
+    on_var_change: Option<VarChangeCallback>,
+    /// Source of `rand()` / `randint(a, b)`; see [`Session::seed`].
+    rng: Rng,
+}
+
+/// Names of the variables and functions changed by one
+/// [`Session::eval_with_changes`] call, each list sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Variables that did not exist before.
+    pub vars_added: Vec<String>,
+    /// Existing variables whose value changed.
+    pub vars_updated: Vec<String>,
+    /// Variables that no longer exist.
+    pub vars_removed: Vec<String>,
+    /// User functions that did not exist before.
+    pub fns_added: Vec<String>,
+    /// User functions that no longer exist.
+    pub fns_removed: Vec<String>,
+}
+
+impl Changes {
+    /// True if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.vars_added.is_empty()
+            && self.vars_updated.is_empty()
+            && self.vars_removed.is_empty()
+            && self.fns_added.is_empty()
+            && self.fns_removed.is_empty()
+    }
+}
+
+/// Seed for a fresh session's generator: the current time in nanoseconds.
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// `SystemTime::now` panics on wasm32, so sessions there start from a fixed
+/// seed; the WASM bindings reseed from `Math.random`.
+#[cfg(target_arch = "wasm32")]
+fn clock_seed() -> u64 {
+    0x853C_49E6_748F_EA9B
+}
+
+/// Callback invoked with a variable's name and its new value (`None` when
+/// the variable was removed). `Send + Sync` like [`crate::ast::NativeFn`],
+/// so a `Session` can still be shared across threads.
+pub type VarChangeCallback = Box<dyn FnMut(&str, Option<CalcResult>) + Send + Sync>;
+
+/// Clones every variable, function and setting. The [`Session::on_var_change`]
+/// callback is not cloned, so changes made through the copy are not reported.
+impl Clone for Session {
+    fn clone(&self) -> Self {
+        Session {
+            angle_mode: self.angle_mode,
+            config: self.config.clone(),
+            parse_config: self.parse_config.clone(),
+            vars: self.vars.clone(),
+            consts: self.consts.clone(),
+            fns: self.fns.clone(),
+            fn_defaults: self.fn_defaults.clone(),
+            natives: self.natives.clone(),
+            sym_vars: self.sym_vars.clone(),
+            assumptions: self.assumptions.clone(),
+            on_var_change: None,
+            rng: self.rng.clone(),
+        }
+    }
 }
 
 impl Session {
     pub fn new(angle_mode: AngleMode) -> Self {
         Session {
             angle_mode,
+            config: EvalConfig::default(),
+            parse_config: ParseConfig::default(),
             vars: HashMap::new(),
+            consts: HashSet::new(),
             fns: UserFns::new(),
+            fn_defaults: FnDefaults::new(),
+            natives: NativeFns::new(),
             sym_vars: HashMap::new(),
             assumptions: HashMap::new(),
+            on_var_change: None,
+            rng: Rng::new(clock_seed()),
         }
     }
 
-    /// Evaluate one line to a NUMERIC result. Handles three forms:
+    /// Reseed the generator behind `rand()` and `randint(a, b)`. The same
+    /// seed gives the same sequence of draws, so Monte-Carlo runs can be
+    /// reproduced. A new session is seeded from the system clock (from a
+    /// fixed seed on wasm32, where there is none).
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.seed(42);
+    /// let first = s.eval("rand()").unwrap();
+    /// s.seed(42);
+    /// assert_eq!(s.eval("rand()").unwrap(), first);
+    /// ```
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Register a callback that runs whenever a numeric variable is set or
+    /// removed: by [`Session::set_var`], [`Session::remove_var`],
+    /// [`Session::clear_vars`] and by assignments in [`Session::eval`] /
+    /// [`Session::eval_line`]. It receives the name and the new value, or
+    /// `None` on removal. Replaces any previously registered callback.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode, CalcResult};
+    /// use std::sync::{Arc, Mutex};
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&seen);
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.on_var_change(Box::new(move |name, value| {
+    ///     log.lock().unwrap().push((name.to_string(), value));
+    /// }));
+    /// s.eval("a = 2").unwrap();
+    /// assert_eq!(seen.lock().unwrap()[0], ("a".to_string(), Some(CalcResult::Real(2.0))));
+    /// ```
+    pub fn on_var_change(&mut self, callback: VarChangeCallback) {
+        self.on_var_change = Some(callback);
+    }
+
+    /// Turn strict finite mode on or off: while on, any infinite or NaN
+    /// intermediate value is a `DomainError`. Shorthand for setting
+    /// `config.strict_finite`.
+    pub fn set_strict_finite(&mut self, strict: bool) {
+        self.config.strict_finite = strict;
+    }
+
+    /// Report comparisons and logical expressions as [`CalcResult::Bool`].
+    /// Shorthand for setting `config.bool_results`.
+    pub fn set_bool_results(&mut self, on: bool) {
+        self.config.bool_results = on;
+    }
+
+    fn store_var(&mut self, name: &str, value: Cx) {
+        self.vars.insert(name.to_string(), value);
+        if let Some(callback) = self.on_var_change.as_mut() {
+            callback(name, Some(value.to_calc_result()));
+        }
+    }
+
+    fn drop_var(&mut self, name: &str) {
+        if self.vars.remove(name).is_some() {
+            if let Some(callback) = self.on_var_change.as_mut() {
+                callback(name, None);
+            }
+        }
+    }
+
+    /// Evaluate one line to a NUMERIC result. Handles five forms:
     /// - `f(x, y) = expr`, defines a user function (stored, returns 0)
     /// - `ident = expr`  , assigns a variable, returns its value
+    /// - `const ident = expr`, assigns a variable that later assignments and
+    ///   `del` reject, returns its value
+    /// - `del name`      , removes a variable or function (also `unset name`,
+    ///   returns 0; an unknown name is an `UndefinedName` error)
     /// - `expr`          , evaluates the expression, returns its value
     ///
+    /// Assigning to a built-in constant (`pi = 3`) or function name
+    /// (`sin = 3`), or defining a function under one (`sin(x) = 2`), is
+    /// rejected; use [`Session::set_var`] to shadow a constant deliberately.
+    ///
     /// This is the numeric-only path: symbolic forms such as `diff(x^2, x)` or
     /// `factor(...)` are NOT understood here and return an error. Use
     /// [`Session::eval_line`] for those, it is a superset that runs the same
@@ -64,25 +250,141 @@ impl Session {
     pub fn eval(&mut self, line: &str) -> Result<CalcResult, ExathError> {
         let line = line.trim();
 
-        if let Some((name, params, body_str)) = split_fn_def(line) {
-            let body_ast = crate::ast::parse_str(body_str)?;
-            self.fns.insert(name.to_string(), (params, body_ast));
+        if let Some(name) = split_delete(line) {
+            self.delete(name)?;
+            return Ok(CalcResult::Real(0.0));
+        }
+
+        if let Some((name, params, defaults, body_str)) = split_fn_def(line) {
+            check_assignable(name)?;
+            let body_ast = self.parse(body_str)?;
+            self.define_fn(name, params, &defaults, body_ast)?;
             return Ok(CalcResult::Real(0.0));
         }
 
+        if let Some((lhs, rhs)) = split_const(line) {
+            check_assignable(lhs)?;
+            self.check_not_const(lhs)?;
+            let ast = self.parse(rhs)?;
+            let result = self.result_of(&ast, self.eval_with_vars(&ast, &self.vars)?);
+            self.sym_vars.remove(lhs);
+            self.store_var(lhs, cx_of(&result));
+            self.consts.insert(lhs.to_string());
+            return Ok(result);
+        }
+
         if let Some((lhs, rhs)) = split_assignment(line) {
-            let result = super::evaluate_with_vars_and_fns(
-                rhs, self.angle_mode, &self.vars, &self.fns,
-            )?;
-            let cx = match &result {
-                CalcResult::Real(value) => Cx::real(*value),
-                CalcResult::Complex(re, im) => Cx { re: *re, im: *im },
-            };
-            self.vars.insert(lhs.to_string(), cx);
+            check_assignable(lhs)?;
+            self.check_not_const(lhs)?;
+            let ast = self.parse(rhs)?;
+            let result = self.result_of(&ast, self.eval_with_vars(&ast, &self.vars)?);
+            self.store_var(lhs, cx_of(&result));
             return Ok(result);
         }
 
-        super::evaluate_with_vars_and_fns(line, self.angle_mode, &self.vars, &self.fns)
+        let ast = self.parse(line)?;
+        Ok(self.result_of(&ast, self.eval_with_vars(&ast, &self.vars)?))
+    }
+
+    /// Evaluate `line` like [`Session::eval`] and report which variables and
+    /// functions it added, changed or removed, so a UI can update just those.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// let (_, changes) = s.eval_with_changes("a = 5").unwrap();
+    /// assert_eq!(changes.vars_added, vec!["a"]);
+    /// ```
+    pub fn eval_with_changes(&mut self, line: &str) -> Result<(CalcResult, Changes), ExathError> {
+        let vars_before = self.vars.clone();
+        let fns_before = self.fn_names();
+        let result = self.eval(line)?;
+
+        let mut changes = Changes::default();
+        for (name, value) in &self.vars {
+            match vars_before.get(name) {
+                None => changes.vars_added.push(name.clone()),
+                Some(old) if old != value => changes.vars_updated.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.vars_removed = vars_before
+            .keys()
+            .filter(|name| !self.vars.contains_key(*name))
+            .cloned()
+            .collect();
+        changes.fns_added = self
+            .fns
+            .keys()
+            .filter(|name| !fns_before.contains(name))
+            .cloned()
+            .collect();
+        changes.fns_removed = fns_before
+            .into_iter()
+            .filter(|name| !self.fns.contains_key(name))
+            .collect();
+        for list in [
+            &mut changes.vars_added,
+            &mut changes.vars_updated,
+            &mut changes.vars_removed,
+            &mut changes.fns_added,
+        ] {
+            list.sort();
+        }
+        Ok((result, changes))
+    }
+
+    /// Evaluate `line` like [`Session::eval`] but with `mode` as the angle
+    /// mode. The session's own `angle_mode` is left as it was; assignments
+    /// and definitions made by the line are kept.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode, CalcResult};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// assert_eq!(s.eval_in_mode("sin(90)", AngleMode::Deg).unwrap(), CalcResult::Real(1.0));
+    /// assert_eq!(s.angle_mode, AngleMode::Rad);
+    /// ```
+    pub fn eval_in_mode(&mut self, line: &str, mode: AngleMode) -> Result<CalcResult, ExathError> {
+        let saved = std::mem::replace(&mut self.angle_mode, mode);
+        let result = self.eval(line);
+        self.angle_mode = saved;
+        result
+    }
+
+    /// Evaluate `line` like [`Session::eval`] on a scratch copy of the
+    /// session: assignments and definitions made by the line are discarded.
+    /// Useful for previews and autocomplete.
+    pub fn try_eval(&self, line: &str) -> Result<CalcResult, ExathError> {
+        self.clone().eval(line)
+    }
+
+    /// Evaluate a multi-statement program with [`Session::eval`] and return
+    /// the last statement's result. Statements are separated by newlines or
+    /// `;`; `#` starts a comment that runs to the end of the line. Definitions
+    /// and assignments take effect for the statements after them.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode, CalcResult};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// let r = s.eval_program("a = 5; b = a*a  # square\nb + 1").unwrap();
+    /// assert_eq!(r, CalcResult::Real(26.0));
+    /// ```
+    ///
+    /// Evaluation stops at the first failing statement; its error message is
+    /// prefixed with the 1-based source line number.
+    pub fn eval_program(&mut self, src: &str) -> Result<CalcResult, ExathError> {
+        let mut last = None;
+        for (line_no, line) in src.lines().enumerate() {
+            let code = line.split('#').next().unwrap_or("");
+            for stmt in code.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                let result = self.eval(stmt).map_err(|e| ExathError {
+                    kind: e.kind,
+                    message: format!("line {}: {}", line_no + 1, e.message),
+                })?;
+                last = Some(result);
+            }
+        }
+        last.ok_or_else(|| ExathError::parse("Program contains no statements"))
     }
 
     /// Like [`Session::eval`], but additionally understands every DSL form:
@@ -97,29 +399,43 @@ impl Session {
     pub fn eval_line(&mut self, line: &str) -> Result<LineResult, ExathError> {
         let line = line.trim();
 
+        // del name , remove a variable or function.
+        if let Some(name) = split_delete(line) {
+            self.delete(name)?;
+            return Ok(LineResult::Value(CalcResult::Real(0.0)));
+        }
+
         // f(x) = body , define a user function.
-        if let Some((name, params, body_str)) = split_fn_def(line) {
-            let body_ast = parse_str(body_str)?;
-            self.fns.insert(name.to_string(), (params, body_ast));
+        if let Some((name, params, defaults, body_str)) = split_fn_def(line) {
+            check_assignable(name)?;
+            let body_ast = self.parse(body_str)?;
+            self.define_fn(name, params, &defaults, body_ast)?;
             return Ok(LineResult::Value(CalcResult::Real(0.0)));
         }
 
+        // const ident = rhs , numeric only.
+        if split_const(line).is_some() {
+            return self.eval(line).map(LineResult::Value);
+        }
+
         // ident = rhs , assignment (numeric or symbolic).
         if let Some((lhs, rhs)) = split_assignment(line) {
-            let ast = parse_str(rhs)?;
+            check_assignable(lhs)?;
+            self.check_not_const(lhs)?;
+            let ast = self.parse(rhs)?;
             if let Some(expr) = self.try_symbolic(&ast)? {
-                self.vars.remove(lhs);
+                self.drop_var(lhs);
                 self.sym_vars.insert(lhs.to_string(), expr.clone());
                 return Ok(LineResult::Expression(symbolic::render(&expr)));
             }
             let value = self.eval_numeric(&ast)?;
             self.sym_vars.remove(lhs);
-            self.vars.insert(lhs.to_string(), cx_of(&value));
+            self.store_var(lhs, cx_of(&value));
             return Ok(LineResult::Value(value));
         }
 
         // Bare expression.
-        let ast = parse_str(line)?;
+        let ast = self.parse(line)?;
         if let Some(expr) = self.try_symbolic(&ast)? {
             return Ok(LineResult::Expression(symbolic::render(&expr)));
         }
@@ -129,12 +445,7 @@ impl Session {
         // Eigenvalues: roots of the characteristic polynomial (may be complex).
         if let Ast::Call(name, cargs) = &ast {
             if name == "eigenvalues" && cargs.len() == 1 {
-                let m = match crate::matrix::eval_matrix_ast(
-                    &cargs[0],
-                    &self.vars,
-                    &self.fns,
-                    self.angle_mode,
-                )? {
+                let m = match self.eval_matrix(&cargs[0])? {
                     crate::matrix::MValue::Mat(m) => m,
                     crate::matrix::MValue::Scalar(_) => {
                         return Err(ExathError::arg_type("eigenvalues expects a matrix"))
@@ -230,9 +541,7 @@ impl Session {
         // Characteristic polynomial of a matrix as a symbolic expression in var.
         if let Ast::Call(name, cargs) = &ast {
             if name == "charpoly" && cargs.len() == 2 {
-                let m = match crate::matrix::eval_matrix_ast(
-                    &cargs[0], &self.vars, &self.fns, self.angle_mode,
-                )? {
+                let m = match self.eval_matrix(&cargs[0])? {
                     crate::matrix::MValue::Mat(m) => m,
                     crate::matrix::MValue::Scalar(_) => {
                         return Err(ExathError::arg_type("charpoly expects a matrix"))
@@ -298,9 +607,7 @@ impl Session {
         // Eigenvectors: null space of (A − λI) for each distinct real eigenvalue.
         if let Ast::Call(name, cargs) = &ast {
             if name == "eigenvectors" && cargs.len() == 1 {
-                let m = match crate::matrix::eval_matrix_ast(
-                    &cargs[0], &self.vars, &self.fns, self.angle_mode,
-                )? {
+                let m = match self.eval_matrix(&cargs[0])? {
                     crate::matrix::MValue::Mat(m) => m,
                     crate::matrix::MValue::Scalar(_) => {
                         return Err(ExathError::arg_type("eigenvectors expects a matrix"))
@@ -351,7 +658,7 @@ impl Session {
         }
         // Matrix expressions (literals like [[1,2],[3,4]], det/inv/transpose/…).
         if crate::matrix::is_matrix_expr(&ast) {
-            let v = crate::matrix::eval_matrix_ast(&ast, &self.vars, &self.fns, self.angle_mode)?;
+            let v = self.eval_matrix(&ast)?;
             return match v {
                 crate::matrix::MValue::Scalar(s) => {
                     Ok(LineResult::Value(CalcResult::Real(s)))
@@ -584,7 +891,7 @@ impl Session {
                         let mut m = self.vars.clone();
                         m.insert(xv.clone(), Cx::real(x));
                         m.insert(yv.clone(), Cx::real(y));
-                        Ok(eval_ast(&f, &m, &self.fns, self.angle_mode)?.re)
+                        Ok(self.eval_with_vars(&f, &m)?.re)
                     };
                     let n = 2000;
                     let h = (x1 - x0) / n as f64;
@@ -614,7 +921,7 @@ impl Session {
                     let fx = |x: f64| -> Result<f64, ExathError> {
                         let mut m = self.vars.clone();
                         m.insert(v.clone(), Cx::real(x));
-                        Ok(sign * eval_ast(&f, &m, &self.fns, self.angle_mode)?.re)
+                        Ok(sign * self.eval_with_vars(&f, &m)?.re)
                     };
                     let gr = (5.0_f64.sqrt() - 1.0) / 2.0;
                     let mut c = b - gr * (b - a);
@@ -718,22 +1025,51 @@ impl Session {
         Ok(None)
     }
 
+    /// Parse user input with the session's [`ParseConfig`].
+    fn parse(&self, src: &str) -> Result<Ast, ExathError> {
+        parse_str_with(src, &self.parse_config)
+    }
+
+    /// Evaluate `ast` against `vars` with this session's functions, natives,
+    /// angle mode and config.
+    fn eval_with_vars(&self, ast: &Ast, vars: &HashMap<String, Cx>) -> Result<Cx, ExathError> {
+        eval_ast_native(
+            ast,
+            vars,
+            &self.fns,
+            &self.natives,
+            &self.fn_defaults,
+            &self.rng,
+            self.angle_mode,
+            &self.config,
+        )
+    }
+
     /// Evaluate `ast` to a real scalar using the current variables.
     fn eval_scalar(&self, ast: &Ast) -> Result<f64, ExathError> {
         let prepared = self.substitute_sym_vars(ast.clone());
-        Ok(eval_ast(&prepared, &self.vars, &self.fns, self.angle_mode)?.to_calc_result().to_f64_lossy())
+        Ok(self.eval_with_vars(&prepared, &self.vars)?.to_calc_result().to_f64_lossy())
+    }
+
+    /// Evaluate a matrix expression, its scalar parts like [`Session::eval`].
+    fn eval_matrix(&self, ast: &Ast) -> Result<crate::matrix::MValue, ExathError> {
+        crate::matrix::eval_matrix_with(ast, &|e: &Ast| self.eval_with_vars(e, &self.vars))
     }
 
     /// Expand user-defined functions and symbolic variables (for symbolic use).
     fn expand(&self, ast: &Ast) -> Result<Ast, ExathError> {
-        let inlined = symbolic::inline_user_fns(ast, &self.fns)?;
+        let inlined = symbolic::inline_user_fns_with(ast, &self.fns, &self.fn_defaults)?;
         Ok(self.substitute_sym_vars(inlined))
     }
 
     /// Evaluate numerically, first substituting any symbolic variables in.
     fn eval_numeric(&self, ast: &Ast) -> Result<CalcResult, ExathError> {
         let prepared = self.substitute_sym_vars(ast.clone());
-        Ok(eval_ast(&prepared, &self.vars, &self.fns, self.angle_mode)?.to_calc_result())
+        Ok(self.result_of(&prepared, self.eval_with_vars(&prepared, &self.vars)?))
+    }
+
+    fn result_of(&self, ast: &Ast, value: Cx) -> CalcResult {
+        super::to_result(ast, value, &self.config)
     }
 
     /// Substitute symbolic variables into `ast`. Repeated passes resolve chains
@@ -764,18 +1100,36 @@ impl Session {
 
     /// Set a variable manually (e.g. from C/WASM host).
     pub fn set_var(&mut self, name: &str, re: f64, im: f64) {
-        self.vars.insert(name.to_string(), Cx { re, im });
+        self.store_var(name, Cx { re, im });
     }
 
-    /// Remove a variable.
+    /// Remove a variable, also one declared with `const`.
     pub fn remove_var(&mut self, name: &str) {
-        self.vars.remove(name);
+        self.drop_var(name);
+        self.consts.remove(name);
     }
 
-    /// Clear all variables (numeric and symbolic).
+    /// Clear all variables (numeric and symbolic), including constants.
     pub fn clear_vars(&mut self) {
-        self.vars.clear();
+        for name in self.var_names() {
+            self.drop_var(&name);
+        }
         self.sym_vars.clear();
+        self.consts.clear();
+    }
+
+    /// Whether `name` was declared with `const name = expr`. The host can
+    /// still change it with [`Session::set_var`] or remove it with
+    /// [`Session::remove_var`].
+    pub fn is_const(&self, name: &str) -> bool {
+        self.consts.contains(name)
+    }
+
+    fn check_not_const(&self, name: &str) -> Result<(), ExathError> {
+        if self.is_const(name) {
+            return Err(ExathError::parse(format!("Cannot assign to constant '{}'", name)));
+        }
+        Ok(())
     }
 
     /// List all variable names.
@@ -785,6 +1139,28 @@ impl Session {
         names
     }
 
+    /// Like [`crate::analysis::complete`], also offering this session's
+    /// variables, symbolic variables, user functions and native functions
+    /// that start with `prefix` (ignoring ASCII case). Sorted, without
+    /// duplicates.
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> =
+            crate::analysis::complete(prefix).into_iter().map(String::from).collect();
+        let own = self
+            .vars
+            .keys()
+            .chain(self.sym_vars.keys())
+            .chain(self.fns.keys())
+            .chain(self.natives.keys());
+        names.extend(
+            own.filter(|name| crate::analysis::starts_with_ignore_case(name, prefix))
+                .cloned(),
+        );
+        names.sort();
+        names.dedup();
+        names
+    }
+
     /// List all user-defined function names.
     pub fn fn_names(&self) -> Vec<String> {
         let mut names: Vec<String> = self.fns.keys().cloned().collect();
@@ -792,10 +1168,289 @@ impl Session {
         names
     }
 
+    /// Parameter names of the user function `name`, in order, or `None` if
+    /// there is no such function. Lets a UI insert a call template such as
+    /// `g(a, b)`.
+    pub fn fn_params(&self, name: &str) -> Option<Vec<String>> {
+        self.fns.get(name).map(|(params, _)| params.clone())
+    }
+
     /// Remove a user-defined function.
     pub fn remove_fn(&mut self, name: &str) {
         self.fns.remove(name);
+        self.fn_defaults.remove(name);
+    }
+
+    /// Write the user functions and numeric variables as a script that
+    /// [`Session::eval_program`] (or the CLI) replays into an equal session:
+    /// one `f(x) = body` line per function, then one `name = value` line per
+    /// variable (`const name = value` for constants), each group sorted by
+    /// name. Values are written in full
+    /// precision, complex ones as `re + im*sqrt(-1)` (numeric evaluation has
+    /// no `i` constant).
+    ///
+    /// Functions come first since variables are stored as plain values and
+    /// never refer to them; function bodies look up names at call time, so
+    /// their order does not matter. Symbolic variables, assumptions, native
+    /// functions and settings are not included.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.eval_program("f(x) = x^2 + a; a = 0.5; z = sqrt(-4)").unwrap();
+    /// assert_eq!(s.to_script(), "f(x) = x^2 + a\na = 0.5\nz = 0 + 2*sqrt(-1)\n");
+    /// ```
+    pub fn to_script(&self) -> String {
+        let mut out = String::new();
+        for name in self.fn_names() {
+            let (params, body) = &self.fns[&name];
+            let mut params = params.clone();
+            if let Some(defaults) = self.fn_defaults.get(&name) {
+                let first = params.len() - defaults.len();
+                for (param, default) in params[first..].iter_mut().zip(defaults) {
+                    *param = format!("{} = {}", param, symbolic::render(default));
+                }
+            }
+            out.push_str(&format!("{}({}) = {}\n", name, params.join(", "), symbolic::render(body)));
+        }
+        for name in self.var_names() {
+            let Cx { re, im } = self.vars[&name];
+            // `{}` prints the shortest decimal that reads back to the same
+            // f64, and `inf` / `NaN` for the non-finite values.
+            let value = if im == 0.0 {
+                format!("{}", re)
+            } else if im < 0.0 {
+                format!("{} - {}*sqrt(-1)", re, -im)
+            } else {
+                format!("{} + {}*sqrt(-1)", re, im)
+            };
+            let keyword = if self.is_const(&name) { "const " } else { "" };
+            out.push_str(&format!("{}{} = {}\n", keyword, name, value));
+        }
+        out
+    }
+
+    /// `del name`: remove the variable (numeric or symbolic) and the user
+    /// function called `name`.
+    fn delete(&mut self, name: &str) -> Result<(), ExathError> {
+        let known = self.vars.contains_key(name)
+            || self.sym_vars.contains_key(name)
+            || self.fns.contains_key(name);
+        if !known {
+            return Err(ExathError::undefined(format!(
+                "Cannot delete '{}': no such variable or function",
+                name
+            )));
+        }
+        if self.is_const(name) {
+            return Err(ExathError::parse(format!("Cannot delete constant '{}'", name)));
+        }
+        self.remove_var(name);
+        self.sym_vars.remove(name);
+        self.remove_fn(name);
+        Ok(())
+    }
+
+    /// Register a host function (a closure or a boxed `dyn Fn`) callable from
+    /// numeric expressions as `name(arg1, …, argN)` with exactly `arity`
+    /// arguments.
+    ///
+    /// Lookup order is user-defined functions, then natives, then built-ins,
+    /// so a session function `f(x) = …` shadows a native `f`. Names of
+    /// built-in functions and constants cannot be registered. Natives are
+    /// treated as impure: calls are never memoized. Symbolic forms
+    /// (`diff`, `simplify`, …) do not see them.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode, CalcResult};
+    /// use exath_engine::evaluator::Cx;
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.register_native("price", 1, |args: &[Cx]| {
+    ///     Ok(Cx::real(if args[0].re == 1.0 { 101.5 } else { 0.0 }))
+    /// }).unwrap();
+    /// assert_eq!(s.eval("2 * price(1)").unwrap(), CalcResult::Real(203.0));
+    /// ```
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Cx]) -> Result<Cx, ExathError> + Send + Sync + 'static,
+    ) -> Result<(), ExathError> {
+        if !crate::ast::is_identifier(name) || check_assignable(name).is_err() {
+            return Err(ExathError::parse(format!(
+                "Cannot register native function '{}': not an identifier or a built-in name",
+                name
+            )));
+        }
+        self.natives.insert(name.to_string(), (arity, std::sync::Arc::new(function)));
+        Ok(())
+    }
+
+    /// Remove a native function registered with [`Session::register_native`].
+    pub fn remove_native(&mut self, name: &str) {
+        self.natives.remove(name);
+    }
+
+    /// Numerically integrate the one-argument user function `name` from `a`
+    /// to `b`, like [`numerics::integrate`](crate::numerics::integrate) on
+    /// its body. Further parameters are allowed if they have defaults. The
+    /// body sees the session's variables, functions and natives; its
+    /// parameter shadows a variable of the same name.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.eval("f(x) = 3x^2").unwrap();
+    /// assert!((s.integrate("f", 0.0, 1.0).unwrap() - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn integrate(&self, name: &str, a: f64, b: f64) -> Result<f64, ExathError> {
+        let f = self.unary_fn(name)?;
+        crate::numerics::integrate_fn(f, a, b, crate::numerics::IntegrationMethod::Simpson, 1000)
+    }
+
+    /// Numerically differentiate the one-argument user function `name` at
+    /// `x`, like [`numerics::deriv`](crate::numerics::deriv) on its body.
+    pub fn deriv(&self, name: &str, x: f64) -> Result<f64, ExathError> {
+        crate::numerics::deriv_fn(self.unary_fn(name)?, x)
+    }
+
+    /// Σ `expr` for `var` = `from` to `to`, like
+    /// [`numerics::sum`](crate::numerics::sum), evaluated like
+    /// [`Session::eval`]: with the session's variables, functions, natives
+    /// and config. `var` shadows a variable of the same name.
+    ///
+    /// ```
+    /// use exath_engine::{Session, AngleMode};
+    /// let mut s = Session::new(AngleMode::Rad);
+    /// s.eval("c = 1").unwrap();
+    /// s.eval("f(x) = 2x").unwrap();
+    /// assert_eq!(s.sum("f(k) + c", "k", 1, 10).unwrap(), 120.0);
+    /// ```
+    pub fn sum(&self, expr: &str, var: &str, from: i64, to: i64) -> Result<f64, ExathError> {
+        crate::numerics::check_terms("Sum", from, to, crate::numerics::MAX_TERMS)?;
+        let body = self.parse(expr)?;
+        let f = self.real_fn(&body, var);
+        let mut accumulator = 0.0f64;
+        for k in from..=to {
+            accumulator += f(k as f64)?;
+        }
+        Ok(accumulator)
+    }
+
+    /// Π `expr` for `var` = `from` to `to`, like
+    /// [`numerics::prod`](crate::numerics::prod), evaluated like
+    /// [`Session::sum`]. Stops at the first factor that makes the product 0.
+    pub fn prod(&self, expr: &str, var: &str, from: i64, to: i64) -> Result<f64, ExathError> {
+        crate::numerics::check_terms("Product", from, to, crate::numerics::MAX_TERMS)?;
+        let body = self.parse(expr)?;
+        let f = self.real_fn(&body, var);
+        let mut accumulator = 1.0f64;
+        for k in from..=to {
+            accumulator *= f(k as f64)?;
+            if accumulator == 0.0 {
+                break;
+            }
+        }
+        Ok(accumulator)
+    }
+
+    /// `body` as a real function of `param`, evaluated against the session's
+    /// variables with `param` bound to the argument.
+    fn real_fn<'a>(&'a self, body: &'a Ast, param: &str) -> impl Fn(f64) -> Result<f64, ExathError> + 'a {
+        let param = param.to_string();
+        let vars = std::cell::RefCell::new(self.vars.clone());
+        move |x: f64| {
+            vars.borrow_mut().insert(param.clone(), Cx::real(x));
+            crate::numerics::require_real(self.eval_with_vars(body, &vars.borrow())?, x)
+        }
+    }
+
+    /// The user function `name` as a real function of its first parameter;
+    /// any others must have defaults.
+    fn unary_fn(&self, name: &str) -> Result<impl Fn(f64) -> Result<f64, ExathError> + '_, ExathError> {
+        let (params, _) = self
+            .fns
+            .get(name)
+            .ok_or_else(|| ExathError::undefined(format!("Unknown function '{}'", name)))?;
+        let defaults = self.fn_defaults.get(name).map_or(0, Vec::len);
+        if params.is_empty() || params.len() - defaults > 1 {
+            return Err(ExathError::arg_count(format!(
+                "'{}' takes {} arguments, expected a one-argument function",
+                name,
+                params.len()
+            )));
+        }
+        // Evaluated as the call `name(x)`, so left-out parameters take their
+        // defaults as in `eval`.
+        let name = name.to_string();
+        Ok(move |x: f64| {
+            let call = Ast::Call(name.clone(), vec![Ast::Number(x)]);
+            crate::numerics::require_real(self.eval_with_vars(&call, &self.vars)?, x)
+        })
     }
+
+    /// Define a user function like `f(x) = x + a`, but with lexical capture:
+    /// free variables that currently have a numeric value are replaced by
+    /// that value, so later changes to `a` do not affect `f`. Free variables
+    /// that are not defined yet stay dynamic.
+    pub fn define_fn_lexical(&mut self, definition: &str) -> Result<(), ExathError> {
+        let (name, params, defaults, body_str) = split_fn_def(definition.trim()).ok_or_else(|| {
+            ExathError::parse("expected a function definition like f(x) = expr")
+        })?;
+        check_assignable(name)?;
+        let mut body = self.parse(body_str)?;
+        for var in crate::ast::collect_vars(&body) {
+            if params.contains(&var) {
+                continue;
+            }
+            if let Some(value) = self.vars.get(&var) {
+                body = symbolic::substitute(&body, &var, &value_ast(*value));
+            }
+        }
+        self.define_fn(name, params, &defaults, body)
+    }
+
+    /// Store the user function `name`, parsing the defaults of its trailing
+    /// parameters (see [`split_fn_def`]).
+    fn define_fn(
+        &mut self,
+        name: &str,
+        params: Vec<String>,
+        defaults: &[Option<&str>],
+        body: Ast,
+    ) -> Result<(), ExathError> {
+        let first = defaults.iter().position(Option::is_some).unwrap_or(defaults.len());
+        if let Some(i) = defaults[first..].iter().position(Option::is_none) {
+            return Err(ExathError::parse(format!(
+                "Parameter '{}' of {}() needs a default: it follows a parameter that has one",
+                params[first + i],
+                name
+            )));
+        }
+        let defaults = defaults
+            .iter()
+            .flatten()
+            .map(|src| self.parse(src))
+            .collect::<Result<Vec<_>, _>>()?;
+        if defaults.is_empty() {
+            self.fn_defaults.remove(name);
+        } else {
+            self.fn_defaults.insert(name.to_string(), defaults);
+        }
+        self.fns.insert(name.to_string(), (params, body));
+        Ok(())
+    }
+}
+
+/// An AST that evaluates to `value` (`re + im·√-1` for complex values).
+pub(crate) fn value_ast(value: Cx) -> Ast {
+    let re = Ast::Number(value.re);
+    if value.is_real() {
+        return re;
+    }
+    let i = Ast::Call("sqrt".to_string(), vec![Ast::Number(-1.0)]);
+    let im = Ast::BinOp(BinOp::Mul, Box::new(Ast::Number(value.im)), Box::new(i));
+    Ast::BinOp(BinOp::Add, Box::new(re), Box::new(im))
 }
 
 /// Apply sign assumptions to canonical forms: `sqrt(v^2) → v` / `-v`,
@@ -882,22 +1537,41 @@ fn cx_of(result: &CalcResult) -> Cx {
     match result {
         CalcResult::Real(v) => Cx::real(*v),
         CalcResult::Complex(re, im) => Cx { re: *re, im: *im },
+        CalcResult::Bool(_) => Cx::real(result.to_f64_lossy()),
     }
 }
 
-/// Detect `ident(params) = body` and split into (name, [param, ...], body_str).
-fn split_fn_def(line: &str) -> Option<(&str, Vec<String>, &str)> {
+/// Detect `ident(params) = body` and split into (name, [param, ...],
+/// [default, ...], body_str). A parameter written `n = expr` has the default
+/// source `Some("expr")`, others `None`.
+#[allow(clippy::type_complexity)]
+fn split_fn_def(line: &str) -> Option<(&str, Vec<String>, Vec<Option<&str>>, &str)> {
     let lparen = line.find('(')?;
     let name = line[..lparen].trim();
 
-    if name.is_empty()
-        || !name.chars().next()?.is_ascii_alphabetic()
-        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
-    {
+    if !crate::ast::is_identifier(name) {
         return None;
     }
 
-    let rparen = line[lparen..].find(')')? + lparen;
+    // The matching ')', so a default may itself contain calls.
+    let mut depth = 0usize;
+    let mut rparen = None;
+    let mut commas = Vec::new();
+    for (i, ch) in line[lparen..].char_indices() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    rparen = (ch == ')').then_some(lparen + i);
+                    break;
+                }
+            }
+            ',' if depth == 1 => commas.push(lparen + i),
+            _ => {}
+        }
+    }
+    let rparen = rparen?;
 
     let after_paren = line[rparen + 1..].trim_start();
     if !after_paren.starts_with('=') {
@@ -908,26 +1582,78 @@ fn split_fn_def(line: &str) -> Option<(&str, Vec<String>, &str)> {
         return None;
     }
 
-    let params_str = line[lparen + 1..rparen].trim();
-    let params: Vec<String> = if params_str.is_empty() {
-        vec![]
-    } else {
-        params_str
-            .split(',')
-            .map(|p| p.trim().to_string())
-            .collect()
-    };
-
-    for param in &params {
-        if param.is_empty()
-            || !param.chars().next()?.is_ascii_alphabetic()
-            || !param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
-        {
-            return None;
+    let mut params = Vec::new();
+    let mut defaults = Vec::new();
+    if !line[lparen + 1..rparen].trim().is_empty() {
+        let mut from = lparen + 1;
+        for to in commas.into_iter().chain([rparen]) {
+            let param = line[from..to].trim();
+            let (param, default) = match split_assignment(param) {
+                Some((param, default)) => (param, Some(default)),
+                None => (param, None),
+            };
+            if !crate::ast::is_identifier(param) {
+                return None;
+            }
+            params.push(param.to_string());
+            defaults.push(default);
+            from = to + 1;
         }
     }
 
-    Some((name, params, after_eq))
+    Some((name, params, defaults, after_eq))
+}
+
+/// Statement keywords of [`Session::eval`], which cannot name a variable or
+/// function.
+const RESERVED: &[&str] = &["del", "unset"];
+
+fn check_not_reserved(name: &str) -> Result<(), ExathError> {
+    if RESERVED.contains(&name) {
+        return Err(ExathError::parse(format!(
+            "'{}' is reserved: use `{} name` to delete a variable",
+            name, name
+        )));
+    }
+    Ok(())
+}
+
+/// Detect `del name` or `unset name` and return the name.
+fn split_delete(line: &str) -> Option<&str> {
+    let (keyword, rest) = line.split_once(char::is_whitespace)?;
+    let name = rest.trim();
+    (RESERVED.contains(&keyword) && crate::ast::is_identifier(name)).then_some(name)
+}
+
+/// Reject assignment targets and function-definition names that name a
+/// built-in constant or function, or a reserved keyword.
+fn check_assignable(name: &str) -> Result<(), ExathError> {
+    check_not_reserved(name)?;
+    let lower = name.to_ascii_lowercase();
+    if crate::ast::is_builtin_constant(&lower) {
+        return Err(ExathError::parse(format!(
+            "Cannot assign to built-in constant '{}'",
+            name
+        )));
+    }
+    if crate::ast::is_function(&lower)
+        || crate::analysis::supported_functions().contains(&lower.as_str())
+    {
+        return Err(ExathError::parse(format!(
+            "Cannot assign to built-in function '{}'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Detect `const identifier = expression` and split into (lhs, rhs).
+fn split_const(line: &str) -> Option<(&str, &str)> {
+    let (keyword, rest) = line.split_once(char::is_whitespace)?;
+    if keyword != "const" {
+        return None;
+    }
+    split_assignment(rest.trim_start())
 }
 
 /// Detect `identifier = expression` and split into (lhs, rhs).
@@ -940,10 +1666,7 @@ fn split_assignment(line: &str) -> Option<(&str, &str)> {
             if prev != b'!' && prev != b'<' && prev != b'>' && next != b'=' {
                 let lhs = line[..i].trim();
                 let rhs = line[i + 1..].trim();
-                if !lhs.is_empty()
-                    && lhs.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
-                    && lhs.chars().next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false)
-                {
+                if crate::ast::is_identifier(lhs) {
                     return Some((lhs, rhs));
                 }
             }
@@ -955,6 +1678,384 @@ fn split_assignment(line: &str) -> Option<(&str, &str)> {
 #[cfg(test)]
 mod eval_line_tests {
     use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn default_arguments_fill_trailing_params() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("f(x, n = 2) = x^n").unwrap();
+        assert_eq!(s.eval("f(3)").unwrap(), CalcResult::Real(9.0));
+        assert_eq!(s.eval("f(3, 4)").unwrap(), CalcResult::Real(81.0));
+        assert_eq!(s.eval_line("f(2)").unwrap(), LineResult::Value(CalcResult::Real(4.0)));
+        assert_eq!(s.fn_params("f"), Some(vec!["x".to_string(), "n".to_string()]));
+        assert_eq!(s.to_script(), "f(x, n = 2) = x^n\n");
+        // Defaults are evaluated where the call is made.
+        s.eval("g(x, k = max(1, a)) = x * k").unwrap();
+        s.eval("a = 5").unwrap();
+        assert_eq!(s.eval("g(2)").unwrap(), CalcResult::Real(10.0));
+    }
+
+    #[test]
+    fn default_arguments_errors() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("f(x, n = 2) = x^n").unwrap();
+        assert_eq!(s.eval("f()").unwrap_err().kind, ErrorKind::ArgumentCount);
+        assert_eq!(s.eval("f(1, 2, 3)").unwrap_err().kind, ErrorKind::ArgumentCount);
+        let err = s.eval("h(x = 1, y) = x + y").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ParseError);
+        assert!(!s.fn_names().contains(&"h".to_string()));
+        // Redefining without defaults drops the old ones.
+        s.eval("f(x, n) = x^n").unwrap();
+        assert_eq!(s.eval("f(3)").unwrap_err().kind, ErrorKind::ArgumentCount);
+    }
+
+    #[test]
+    fn default_arguments_in_symbolic_matrix_and_numeric_forms() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("f(x, n = 2) = x^n").unwrap();
+        s.eval("g(x) = f(x) + 1").unwrap();
+        let expr = |s: &mut Session, line: &str| match s.eval_line(line).unwrap() {
+            LineResult::Expression(e) => e,
+            other => panic!("expected expression, got {:?}", other),
+        };
+        assert_eq!(expr(&mut s, "diff(f(x), x)"), "2 * x");
+        assert_eq!(expr(&mut s, "diff(g(x), x)"), "2 * x");
+        assert_eq!(expr(&mut s, "simplify(f(x))"), "x^2");
+        assert_eq!(expr(&mut s, "simplify(f(x, 3))"), "x^3");
+        assert_eq!(expr(&mut s, "expand(f(x+1))"), "x^2 + 2 * x + 1");
+        assert_eq!(expr(&mut s, "[f(1), f(2)]"), "[[1, 4]]");
+        assert_eq!(s.sum("f(k)", "k", 1, 3).unwrap(), 14.0);
+        assert!((s.integrate("f", 0.0, 1.0).unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((s.deriv("f", 3.0).unwrap() - 6.0).abs() < 1e-6);
+        let err = s.eval_line("simplify(f())").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ArgumentCount);
+    }
+
+    #[test]
+    fn complete_adds_session_names() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("sigma = 2").unwrap();
+        s.eval("Sinc(x) = sin(x) / x").unwrap();
+        s.eval("other = 1").unwrap();
+        assert_eq!(
+            s.complete("si"),
+            ["Sinc", "sigma", "sigmoid", "sign", "simplify", "sin", "sinh"]
+        );
+        assert!(s.complete("").contains(&"other".to_string()));
+    }
+
+    #[test]
+    fn seeded_rand_is_repeatable() {
+        let draws = |s: &mut Session| -> Vec<f64> {
+            (0..5)
+                .flat_map(|_| [s.eval("rand()").unwrap(), s.eval("randint(1, 6)").unwrap()])
+                .map(|r| r.to_f64_lossy())
+                .collect()
+        };
+        let mut s = Session::new(AngleMode::Rad);
+        s.seed(7);
+        let first = draws(&mut s);
+        s.seed(7);
+        assert_eq!(draws(&mut s), first);
+        s.seed(8);
+        assert_ne!(draws(&mut s), first);
+        for pair in first.chunks(2) {
+            assert!((0.0..1.0).contains(&pair[0]));
+            assert!([1.0, 2.0, 3.0, 4.0, 5.0, 6.0].contains(&pair[1]));
+        }
+        // Each call draws anew, even inside a user function.
+        s.eval("r() = rand()").unwrap();
+        assert_ne!(s.eval("r() - r()").unwrap(), CalcResult::Real(0.0));
+        assert_eq!(s.eval("randint(3, 3)").unwrap(), CalcResult::Real(3.0));
+    }
+
+    #[test]
+    fn chained_comparison_draws_middle_once() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.seed(3);
+        for _ in 0..200 {
+            // True only if the two comparisons saw different draws.
+            assert_eq!(s.eval("2 <= randint(1, 2) <= 1").unwrap(), CalcResult::Real(0.0));
+            assert_eq!(s.eval("0 <= rand() < 1").unwrap(), CalcResult::Real(1.0));
+        }
+    }
+
+    #[test]
+    fn rand_errors() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert_eq!(s.eval("rand(1)").unwrap_err().kind, ErrorKind::ArgumentCount);
+        assert_eq!(s.eval("randint(1)").unwrap_err().kind, ErrorKind::ArgumentCount);
+        assert_eq!(s.eval("randint(6, 1)").unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(s.eval("randint(1.5, 2)").unwrap_err().kind, ErrorKind::ArgumentType);
+        // The stateless entry points have no generator.
+        let err = crate::evaluate("rand()", AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DomainError);
+    }
+
+    #[test]
+    fn fn_params_lists_parameters_in_order() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("g(a, b) = a+b").unwrap();
+        s.eval("h() = 1").unwrap();
+        assert_eq!(s.fn_params("g"), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(s.fn_params("h"), Some(vec![]));
+        assert_eq!(s.fn_params("sin"), None);
+        s.eval("del g").unwrap();
+        assert_eq!(s.fn_params("g"), None);
+    }
+
+    #[test]
+    fn to_script_round_trips() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval_program("f(x, y) = x*y - a; g(t) = f(t, 2)!; a = 0.1 + 0.2").unwrap();
+        s.eval_program("z = sqrt(-2) - 1; w = -1/3; big = 2^70; h = inf").unwrap();
+        s.define_fn_lexical("k(x) = x + w").unwrap();
+        s.set_var("c", 1.5, -0.25);
+        let script = s.to_script();
+
+        let mut t = Session::new(AngleMode::Rad);
+        t.eval_program(&script).unwrap();
+        assert_eq!(t.fn_names(), s.fn_names());
+        assert_eq!(t.vars, s.vars);
+        assert_eq!(t.to_script(), script);
+        assert_eq!(t.eval("g(3) + k(0)").unwrap(), s.eval("g(3) + k(0)").unwrap());
+        assert_eq!(Session::new(AngleMode::Rad).to_script(), "");
+    }
+
+    #[test]
+    fn const_rejects_reassignment() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert_eq!(s.eval("const g = 9.81").unwrap(), CalcResult::Real(9.81));
+        assert!(s.is_const("g"));
+        let err = s.eval("g = 10").unwrap_err();
+        assert_eq!(err.message, "Cannot assign to constant 'g'");
+        assert!(s.eval_line("g = diff(x^2, x)").is_err());
+        assert!(s.eval("const g = 1").is_err());
+        assert!(s.eval("del g").is_err());
+        assert_eq!(s.eval("2g").unwrap(), CalcResult::Real(19.62));
+        assert_eq!(s.to_script(), "const g = 9.81\n");
+
+        // The right-hand side is evaluated once.
+        s.eval("a = 2").unwrap();
+        s.eval_line("const b = a^2").unwrap();
+        s.eval("a = 3").unwrap();
+        assert_eq!(s.get_var("b"), Some(CalcResult::Real(4.0)));
+
+        // A plain variable can be made constant; the host can still remove it.
+        s.eval("const a = a").unwrap();
+        assert!(s.eval("a = 4").is_err());
+        s.remove_var("a");
+        assert!(!s.is_const("a"));
+        s.eval("a = 4").unwrap();
+        assert!(s.eval("const pi = 3").is_err());
+    }
+
+    #[test]
+    fn bool_results_are_opt_in() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert_eq!(s.eval("3 > 2").unwrap(), CalcResult::Real(1.0));
+        s.set_bool_results(true);
+        assert_eq!(s.eval("3 > 2").unwrap(), CalcResult::Bool(true));
+        assert_eq!(s.eval("!1").unwrap(), CalcResult::Bool(false));
+        assert_eq!(s.eval("1 < 2 && 2 < 3").unwrap(), CalcResult::Bool(true));
+        // Only the outermost operator decides.
+        assert_eq!(s.eval("(3 > 2) + 1").unwrap(), CalcResult::Real(2.0));
+        assert_eq!(s.eval("b = 3 > 2").unwrap(), CalcResult::Bool(true));
+        assert_eq!(s.eval("b + 1").unwrap(), CalcResult::Real(2.0));
+        assert_eq!(CalcResult::Bool(true).format(), "true");
+        assert_eq!(CalcResult::Bool(false).to_f64_lossy(), 0.0);
+    }
+
+    #[test]
+    fn del_removes_variables_and_functions() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("a = 5").unwrap();
+        assert_eq!(s.eval("del a").unwrap(), CalcResult::Real(0.0));
+        assert_eq!(s.eval("a + 1").unwrap_err().kind, crate::ErrorKind::UndefinedName);
+        assert_eq!(s.eval("del a").unwrap_err().kind, crate::ErrorKind::UndefinedName);
+
+        s.eval("f(x) = 2x").unwrap();
+        s.eval("unset f").unwrap();
+        assert!(s.fn_names().is_empty());
+        s.eval_line("p = diff(x^3, x)").unwrap();
+        assert_eq!(s.sym_var_names(), vec!["p"]);
+        s.eval_line("del p").unwrap();
+        assert!(s.sym_var_names().is_empty());
+    }
+
+    #[test]
+    fn del_is_reserved() {
+        let mut s = Session::new(AngleMode::Rad);
+        for line in ["del = 5", "unset = 1", "del(x) = x"] {
+            assert_eq!(s.eval(line).unwrap_err().kind, crate::ErrorKind::ParseError, "{}", line);
+        }
+        assert!(s.eval_line("del(x) = x").is_err());
+        assert!(s.define_fn_lexical("unset(x) = x").is_err());
+        // Only a lone identifier is a delete statement.
+        s.eval("d = 2").unwrap();
+        assert_eq!(s.eval("del d + 1").unwrap_err().kind, crate::ErrorKind::UndefinedName);
+        assert_eq!(s.eval("d").unwrap(), CalcResult::Real(2.0));
+    }
+
+    #[test]
+    fn integrate_and_deriv_session_function() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("k = 3").unwrap();
+        s.eval("p(t) = k*t^2 - 2t + 1").unwrap();
+        assert!((s.integrate("p", 0.0, 2.0).unwrap() - 6.0).abs() < 1e-9);
+        assert!((s.integrate("p", 2.0, 0.0).unwrap() + 6.0).abs() < 1e-9);
+        assert!((s.deriv("p", 1.0).unwrap() - 4.0).abs() < 1e-5);
+        s.eval("q(x) = p(x) + x").unwrap();
+        assert!((s.deriv("q", 0.0).unwrap() + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn eval_in_mode_leaves_session_mode_alone() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert_eq!(s.eval_in_mode("sin(90)", AngleMode::Deg).unwrap(), CalcResult::Real(1.0));
+        assert_eq!(s.angle_mode, AngleMode::Rad);
+        assert!(s.eval_in_mode("sin(", AngleMode::Deg).is_err());
+        assert_eq!(s.angle_mode, AngleMode::Rad);
+        s.eval_in_mode("a = cos(180)", AngleMode::Deg).unwrap();
+        assert_eq!(s.get_var("a"), Some(CalcResult::Real(-1.0)));
+    }
+
+    #[test]
+    fn variable_names_keep_their_case() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("Temp = 30").unwrap();
+        s.eval("temp = 10").unwrap();
+        assert_eq!(s.eval("Temp - temp").unwrap(), CalcResult::Real(20.0));
+        assert_eq!(s.var_names(), vec!["Temp", "temp"]);
+        s.eval("X = 2").unwrap();
+        assert!(s.eval("x").is_err());
+        s.eval("F(t) = 2t").unwrap();
+        assert_eq!(s.eval("F(Temp)").unwrap(), CalcResult::Real(60.0));
+        assert!(s.eval("f(1)").is_err());
+    }
+
+    #[test]
+    fn eval_with_changes_reports_names() {
+        let mut s = Session::new(AngleMode::Rad);
+        let (value, changes) = s.eval_with_changes("a = 5").unwrap();
+        assert_eq!(value, CalcResult::Real(5.0));
+        assert_eq!(changes.vars_added, vec!["a"]);
+        assert!(changes.vars_updated.is_empty());
+
+        let (_, changes) = s.eval_with_changes("a = 6").unwrap();
+        assert_eq!(changes.vars_updated, vec!["a"]);
+        assert!(changes.vars_added.is_empty());
+
+        let (_, changes) = s.eval_with_changes("a = 6").unwrap();
+        assert!(changes.is_empty());
+
+        let (_, changes) = s.eval_with_changes("f(x) = x + a").unwrap();
+        assert_eq!(changes.fns_added, vec!["f"]);
+        let (_, changes) = s.eval_with_changes("f(2) * a").unwrap();
+        assert!(changes.is_empty());
+        assert!(s.eval_with_changes("b = nope").is_err());
+        assert_eq!(s.var_names(), vec!["a"]);
+    }
+
+    #[test]
+    fn implicit_mul_can_be_turned_off() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("x = 3").unwrap();
+        assert_eq!(s.eval("2x").unwrap(), CalcResult::Real(6.0));
+        s.parse_config.implicit_mul = false;
+        assert_eq!(s.eval("2x").unwrap_err().kind, crate::ErrorKind::ParseError);
+        assert!(s.eval("y = 2x").is_err());
+        assert!(s.eval("g(t) = 2t").is_err());
+        s.eval("g(t) = 2*t").unwrap();
+        assert_eq!(s.eval("g(x)").unwrap(), CalcResult::Real(6.0));
+        assert!(s.eval_line("2x").is_err());
+    }
+
+    #[test]
+    fn greek_letter_variables() {
+        let mut s = Session::new(AngleMode::Deg);
+        s.eval("θ = 30").unwrap();
+        assert!((value(&mut s, "sin(θ)") - 0.5).abs() < 1e-12);
+        assert!((value(&mut s, "2θ") - 60.0).abs() < 1e-12);
+        s.eval("α = 2").unwrap();
+        s.eval("β = 3").unwrap();
+        s.eval("Ω(ω) = ω^2").unwrap();
+        assert_eq!(value(&mut s, "αβ + Ω(α)"), 10.0);
+        assert_eq!(s.var_names(), vec!["α", "β", "θ"]);
+        // π, φ and ε stay constants and cannot be reassigned.
+        assert!((value(&mut s, "π") - std::f64::consts::PI).abs() < 1e-15);
+        assert!(s.eval("π = 3").is_err());
+        // Capital Π is an ordinary name, not π.
+        s.eval("Π = 7").unwrap();
+        assert_eq!(value(&mut s, "Π"), 7.0);
+    }
+
+    #[test]
+    fn builtin_names_ignore_case() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert_eq!(s.eval("SIN(0)").unwrap(), CalcResult::Real(0.0));
+        assert_eq!(s.eval("Sqrt 16 + ABS(-1)").unwrap(), CalcResult::Real(5.0));
+        assert_eq!(s.eval("ArcTan(0) + COS(0)").unwrap(), CalcResult::Real(1.0));
+        assert_eq!(s.eval("PI").unwrap(), CalcResult::Real(std::f64::consts::PI));
+        assert!(s.eval("SIN = 2").is_err());
+        assert_eq!(s.eval("SUM(k, k, 1, 3)").unwrap(), CalcResult::Real(6.0));
+        for (mixed, lower) in [
+            ("SIMPLIFY(a+a)", "simplify(a+a)"),
+            ("Diff(x^2, x)", "diff(x^2, x)"),
+            ("DET([[1,2],[3,4]])", "det([[1,2],[3,4]])"),
+        ] {
+            assert_eq!(s.eval_line(mixed).unwrap(), s.eval_line(lower).unwrap());
+        }
+        for name in crate::analysis::supported_functions() {
+            let call = crate::ast::parse_str(&format!("{}(1)", name.to_uppercase())).unwrap();
+            assert!(matches!(call, Ast::Call(n, _) if n == *name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn session_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Session>();
+    }
+
+    #[test]
+    fn definitions_under_builtin_names_are_rejected_in_any_case() {
+        // Calls fold the case of built-ins, so such a definition could never
+        // be called.
+        let mut s = Session::new(AngleMode::Rad);
+        assert!(s.eval("Mean(x) = x + 1").is_err());
+        assert_eq!(s.eval("Mean(2)").unwrap(), CalcResult::Real(2.0));
+        assert!(s.eval("Pi(x) = x").is_err());
+        assert!(s.eval_line("SIN(x) = x").is_err());
+        assert!(s.fn_names().is_empty());
+    }
+
+    #[test]
+    fn sum_and_prod_use_session_context() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("c = 3").unwrap();
+        s.eval("f(x) = x^2").unwrap();
+        assert!((s.sum("f(k) + c", "k", 1, 10).unwrap() - 415.0).abs() < 1e-9);
+        assert!((s.prod("c", "k", 1, 4).unwrap() - 81.0).abs() < 1e-9);
+        assert!(s.sum("g(k)", "k", 1, 3).is_err());
+        // Natives, the config and the generator apply as in `eval`.
+        s.register_native("double", 1, Box::new(|args: &[Cx]| Ok(args[0].mul(Cx::real(2.0)))))
+            .unwrap();
+        assert_eq!(s.sum("double(k)", "k", 1, 3).unwrap(), 12.0);
+        assert_eq!(s.prod("double(k)", "k", 1, 3).unwrap(), 48.0);
+        s.config.div_by_zero = crate::config::DivByZero::Ieee;
+        assert_eq!(s.sum("1/k", "k", 0, 1).unwrap(), f64::INFINITY);
+        let draws = s.sum("rand()", "k", 1, 100).unwrap();
+        assert!((0.0..100.0).contains(&draws));
+    }
+
+    #[test]
+    fn integrate_rejects_unknown_and_non_unary_functions() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("g(x, y) = x + y").unwrap();
+        assert_eq!(s.integrate("g", 0.0, 1.0).unwrap_err().kind, crate::ErrorKind::ArgumentCount);
+        assert_eq!(s.deriv("h", 0.0).unwrap_err().kind, crate::ErrorKind::UndefinedName);
+    }
 
     fn expr(s: &mut Session, line: &str) -> String {
         match s.eval_line(line) {
@@ -1143,6 +2244,238 @@ mod eval_line_tests {
         assert_eq!(expr(&mut s, "[1,2,3]"), "[[1, 2, 3]]");
     }
 
+    #[test]
+    fn assignment_to_builtin_names_is_rejected() {
+        let mut s = Session::new(AngleMode::Rad);
+        assert!(s.eval("pi = 3").is_err());
+        assert!(s.eval("sin = 3").is_err());
+        assert!(s.eval_line("E = 2").is_err());
+        assert!(s.eval("sin(x) = 2").is_err());
+        assert!(s.eval_line("sin(x) = 2").is_err());
+        assert!(s.define_fn_lexical("sin(x) = 2").is_err());
+        assert_eq!(s.eval("sin(0)").unwrap(), CalcResult::Real(0.0));
+        assert!(s.eval("pie = 3").is_ok());
+        s.set_var("pi", 3.0, 0.0);
+        assert_eq!(s.get_var("pi"), Some(CalcResult::Real(3.0)));
+    }
+
+    #[test]
+    fn ieee_division_by_zero() {
+        use crate::config::DivByZero;
+        let mut s = Session::new(AngleMode::Rad);
+        assert!(s.eval("1/0").is_err());
+        assert!(s.eval("5 % 0").is_err());
+        s.config.div_by_zero = DivByZero::Ieee;
+        assert_eq!(s.eval("1/0").unwrap(), CalcResult::Real(f64::INFINITY));
+        assert_eq!(s.eval("-1/0").unwrap().format(), "-\u{221e}");
+        assert_eq!(s.eval("1/0").unwrap().format(), "\u{221e}");
+        assert_eq!(s.eval("0/0").unwrap().format(), "NaN");
+        assert_eq!(s.eval_line("x = 2/0").unwrap(), LineResult::Value(CalcResult::Real(f64::INFINITY)));
+        assert_eq!(s.eval("1/x").unwrap(), CalcResult::Real(0.0));
+        assert!(s.eval("5 % 0").unwrap().to_f64_lossy().is_nan());
+        // Only the `/` operator is relaxed; functions keep their domain checks.
+        assert!(s.eval("ln(0)").is_err());
+    }
+
+    #[test]
+    fn user_functions_scope_dynamically() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("f(x) = x + a").unwrap();
+        assert!(s.eval("f(1)").is_err()); // `a` not defined yet
+        s.eval("a = 2").unwrap();
+        assert_eq!(s.eval("f(1)").unwrap(), CalcResult::Real(3.0));
+        s.eval("a = 5").unwrap();
+        assert_eq!(s.eval("f(1)").unwrap(), CalcResult::Real(6.0));
+        // A parameter shadows the session variable of the same name.
+        s.eval("x = 100").unwrap();
+        assert_eq!(s.eval("f(1)").unwrap(), CalcResult::Real(6.0));
+    }
+
+    #[test]
+    fn lexical_definition_captures_current_values() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("a = 2").unwrap();
+        s.eval("z = sqrt(-4)").unwrap();
+        s.define_fn_lexical("g(x) = x + a + b").unwrap();
+        s.define_fn_lexical("h(x) = x * z").unwrap();
+        s.eval("a = 50").unwrap();
+        s.eval("b = 1").unwrap();
+        assert_eq!(s.eval("g(1)").unwrap(), CalcResult::Real(4.0));
+        s.eval("b = 3").unwrap();
+        assert_eq!(s.eval("g(1)").unwrap(), CalcResult::Real(6.0)); // `b` stays dynamic
+        match s.eval("h(2)").unwrap() {
+            CalcResult::Complex(re, im) => assert!(re.abs() < 1e-12 && (im - 4.0).abs() < 1e-12),
+            other => panic!("expected complex, got {:?}", other),
+        }
+        assert!(s.define_fn_lexical("a + 1").is_err());
+    }
+
+    #[test]
+    fn eval_program_runs_statements_in_order() {
+        let mut s = Session::new(AngleMode::Rad);
+        let src = "# setup\nf(x) = x*x + 1; a = 3\n\nb = f(a)  # 10\nb * 2;";
+        assert_eq!(s.eval_program(src).unwrap(), CalcResult::Real(20.0));
+        assert_eq!(s.get_var("b"), Some(CalcResult::Real(10.0)));
+        assert!(s.eval_program("# only a comment\n ; ").is_err());
+        let err = s.eval_program("# comment only").unwrap_err();
+        assert_eq!(err.message, "Program contains no statements");
+        assert_eq!(s.eval("").unwrap_err().message, "Empty expression");
+        assert_eq!(s.eval("y =   ").unwrap_err().message, "Empty expression");
+        let err = s.eval_program("c = 1\nc + d\nc = 5").unwrap_err();
+        assert_eq!(err.kind, crate::error::ErrorKind::UndefinedName);
+        assert!(err.message.starts_with("line 2:"), "{}", err.message);
+        assert_eq!(s.get_var("c"), Some(CalcResult::Real(1.0)));
+    }
+
+    #[test]
+    fn recursive_fib_is_memoized() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let mut s = Session::new(AngleMode::Rad);
+        s.register_native("tick", 0, Box::new(move |_: &[Cx]| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok(Cx::real(0.0))
+        }))
+        .unwrap();
+        // The default runs in the caller once per call, memo hit or not,
+        // and leaves the body (and so fib's purity) untouched.
+        s.eval("fib(n, t = tick()) = if(n < 2, n, fib(n - 1) + fib(n - 2))").unwrap();
+        assert_eq!(s.eval("fib(30)").unwrap(), CalcResult::Real(832040.0));
+        // ~2.7 million calls without the memo; 1 + 2·29 with it.
+        assert_eq!(calls.load(Ordering::Relaxed), 59);
+    }
+
+    #[test]
+    fn memo_tells_negative_zero_apart() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.config.div_by_zero = crate::config::DivByZero::Ieee;
+        s.eval("f(x) = 1/x").unwrap();
+        assert!(s.eval("f(0) + f(-0)").unwrap().to_f64_lossy().is_nan());
+        assert_eq!(s.eval("f(-0)").unwrap(), CalcResult::Real(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn deep_recursion_layers_parameters() {
+        let mut s = Session::new(AngleMode::Rad);
+        for i in 0..200 {
+            s.eval(&format!("v{} = {}", i, i)).unwrap();
+        }
+        s.eval("fact(n) = if(n <= 1, 1, n * fact(n - 1))").unwrap();
+        let CalcResult::Real(fact) = s.eval("fact(100)").unwrap() else { panic!() };
+        let CalcResult::Real(expected) = s.eval("100!").unwrap() else { panic!() };
+        assert!((fact / expected - 1.0).abs() < 1e-12);
+        // A callee still sees its caller's parameters and the session's
+        // variables through the layers.
+        s.eval("g(x) = x + y + v199").unwrap();
+        s.eval("h(y) = g(1)").unwrap();
+        assert_eq!(s.eval("h(10)").unwrap(), CalcResult::Real(210.0));
+    }
+
+    #[test]
+    fn functions_reading_free_variables_are_not_memoized() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("f(x) = x + a").unwrap();
+        assert_eq!(s.eval("sum(f(1), a, 1, 3)").unwrap(), CalcResult::Real(9.0));
+    }
+
+    #[test]
+    fn try_eval_discards_changes() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("a = 2").unwrap();
+        assert_eq!(s.try_eval("a = a + 40").unwrap(), CalcResult::Real(42.0));
+        assert_eq!(s.try_eval("g(x) = x").unwrap(), CalcResult::Real(0.0));
+        assert_eq!(s.get_var("a"), Some(CalcResult::Real(2.0)));
+        assert!(s.fn_names().is_empty());
+
+        let mut copy = s.clone();
+        copy.eval("a = 7").unwrap();
+        assert_eq!(copy.get_var("a"), Some(CalcResult::Real(7.0)));
+        assert_eq!(s.get_var("a"), Some(CalcResult::Real(2.0)));
+    }
+
+    #[test]
+    fn var_change_callback_sees_every_change() {
+        use std::sync::{Arc, Mutex};
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&events);
+        let mut s = Session::new(AngleMode::Rad);
+        s.on_var_change(Box::new(move |name, value| {
+            log.lock().unwrap().push((name.to_string(), value));
+        }));
+        s.eval_program("a = 1; b = a + 1; f(x) = x; a + b").unwrap();
+        s.eval_line("g = diff(x^2, x)").unwrap();
+        s.eval_line("b = 3").unwrap();
+        s.set_var("z", 0.0, 1.0);
+        s.remove_var("a");
+        s.remove_var("missing");
+        s.try_eval("c = 9").unwrap();
+        s.clear_vars();
+
+        let real = |v: f64| Some(CalcResult::Real(v));
+        let expected = vec![
+            ("a".to_string(), real(1.0)),
+            ("b".to_string(), real(2.0)),
+            ("b".to_string(), real(3.0)),
+            ("z".to_string(), Some(CalcResult::Complex(0.0, 1.0))),
+            ("a".to_string(), None),
+            ("b".to_string(), None),
+            ("z".to_string(), None),
+        ];
+        assert_eq!(*events.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn switching_angle_mode_keeps_state() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.eval("a = 90").unwrap();
+        assert!((value(&mut s, "sin(a)") - 90f64.sin()).abs() < 1e-12);
+        s.angle_mode = AngleMode::Deg;
+        assert_eq!(value(&mut s, "sin(a)"), 1.0);
+    }
+
+    #[test]
+    fn strict_finite_rejects_non_finite_values() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.config.div_by_zero = crate::config::DivByZero::Ieee;
+        assert_eq!(value(&mut s, "1/0"), f64::INFINITY);
+        s.set_strict_finite(true);
+        for line in ["1/0", "0/0 + 1", "exp(1000)", "inf", "a = -1/0"] {
+            let err = s.eval(line).unwrap_err();
+            assert_eq!(err.kind, crate::ErrorKind::DomainError, "{}", line);
+        }
+        assert!(s.get_var("a").is_none());
+        assert_eq!(value(&mut s, "2 + 2"), 4.0);
+        s.set_strict_finite(false);
+        assert!(value(&mut s, "0/0").is_nan());
+    }
+
+    #[test]
+    fn native_functions() {
+        let mut s = Session::new(AngleMode::Rad);
+        s.register_native("double", 1, Box::new(|args: &[Cx]| Ok(args[0].mul(Cx::real(2.0)))))
+            .unwrap();
+        assert_eq!(value(&mut s, "double(21)"), 42.0);
+        assert_eq!(value(&mut s, "a = double(1) + 1"), 3.0);
+        s.eval("g(x) = double(x) + 1").unwrap();
+        assert_eq!(value(&mut s, "g(2)"), 5.0);
+        assert!(matches!(s.eval_line("double(3)").unwrap(), LineResult::Value(CalcResult::Real(v)) if v == 6.0));
+
+        let err = s.eval("double(1, 2)").unwrap_err();
+        assert_eq!(err.kind, crate::ErrorKind::ArgumentCount);
+        assert!(s.register_native("sin", 1, Box::new(|args: &[Cx]| Ok(args[0]))).is_err());
+        assert!(s.register_native("pi", 0, Box::new(|_: &[Cx]| Ok(Cx::real(3.0)))).is_err());
+
+        // User-defined functions shadow natives; clones keep them.
+        s.eval("double(x) = 3*x").unwrap();
+        assert_eq!(value(&mut s, "double(2)"), 6.0);
+        s.remove_fn("double");
+        assert_eq!(s.clone().eval("double(2)").unwrap(), CalcResult::Real(4.0));
+        s.remove_native("double");
+        assert!(s.eval("double(2)").is_err());
+    }
+
     #[test]
     fn legacy_eval_still_works() {
         let mut s = Session::new(AngleMode::Rad);
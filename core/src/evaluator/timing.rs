@@ -0,0 +1,63 @@
+//! Parse/eval timing for profiling (requires the `profiling` feature).
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use super::{to_result, CalcResult};
+use crate::angle_mode::AngleMode;
+use crate::ast::{eval_ast_with, parse_str, UserFns};
+use crate::config::EvalConfig;
+use crate::error::ExathError;
+
+/// Wall-clock time spent in each phase of [`evaluate_timed`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Tokenizing and parsing, in nanoseconds.
+    pub parse_ns: u64,
+    /// Evaluating the parsed expression, in nanoseconds.
+    pub eval_ns: u64,
+}
+
+/// Like [`evaluate_complex`](super::evaluate_complex), and also report how
+/// long parsing and evaluation took. If parsing dominates for an expression
+/// evaluated many times, parse it once with
+/// [`parse_str`](crate::ast::parse_str) and evaluate the AST instead.
+///
+/// ```
+/// use exath_engine::{evaluate_timed, AngleMode, CalcResult};
+/// let (result, timings) = evaluate_timed("sin(1)^2 + cos(1)^2", AngleMode::Rad).unwrap();
+/// assert!(matches!(result, CalcResult::Real(v) if (v - 1.0).abs() < 1e-12));
+/// println!("parse {} ns, eval {} ns", timings.parse_ns, timings.eval_ns);
+/// ```
+pub fn evaluate_timed(expr: &str, angle_mode: AngleMode) -> Result<(CalcResult, Timings), ExathError> {
+    let config = EvalConfig::default();
+    let start = Instant::now();
+    let ast = parse_str(expr)?;
+    let parsed = Instant::now();
+    let value = eval_ast_with(&ast, &HashMap::new(), &UserFns::new(), angle_mode, &config)?;
+    let evaluated = Instant::now();
+    let timings = Timings {
+        parse_ns: nanos(parsed - start),
+        eval_ns: nanos(evaluated - parsed),
+    };
+    Ok((to_result(&ast, value, &config), timings))
+}
+
+fn nanos(elapsed: std::time::Duration) -> u64 {
+    u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod timing_tests {
+    use super::*;
+
+    #[test]
+    fn both_phases_are_timed() {
+        let expr = "sum(sin(k)^2, k, 1, 2000) + (1 + 2 * (3 - 4 / (5 + 6)))";
+        let (result, timings) = evaluate_timed(expr, AngleMode::Rad).unwrap();
+        assert_eq!(result, crate::evaluate_complex(expr, AngleMode::Rad).unwrap());
+        assert!(timings.parse_ns > 0);
+        assert!(timings.eval_ns > 0);
+        assert!(evaluate_timed("1 +", AngleMode::Rad).is_err());
+    }
+}
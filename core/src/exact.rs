@@ -0,0 +1,230 @@
+//! Exact integer arithmetic for combinatorics (requires the `bigint` feature).
+//!
+//! `200!` overflows `f64` to `∞` and `25!` is already rounded. This module
+//! computes integer expressions with arbitrary precision instead: `+`, `-`,
+//! `*`, `^` with a non-negative exponent, postfix `!`, `binom(n, k)`,
+//! `sum(expr, var, a, b)` and `product(expr, var, a, b)`. Anything that can
+//! leave the integers (`/`, `sqrt`, constants, …) is an `ArgumentType` error.
+
+use crate::ast::{parse_str, Ast, BinOp};
+use crate::error::ExathError;
+use crate::numerics::{check_terms, MAX_TERMS};
+
+pub use num_bigint::{BigInt, BigUint};
+
+/// Largest intermediate value [`evaluate_exact`] builds, in bits (about
+/// 315 000 decimal digits); a larger one is a `RangeTooLarge` error.
+pub const MAX_BITS: u64 = 1 << 20;
+
+/// `n!` as an exact integer.
+///
+/// ```
+/// use exath_engine::exact::factorial_big;
+/// assert_eq!(factorial_big(25).to_string(), "15511210043330985984000000");
+/// ```
+pub fn factorial_big(n: u64) -> BigUint {
+    (2..=n).fold(BigUint::from(1u32), |acc, k| acc * k)
+}
+
+/// The binomial coefficient `n choose k` as an exact integer; 0 when `k > n`.
+pub fn binom_big(n: u64, k: u64) -> BigUint {
+    if k > n {
+        return BigUint::from(0u32);
+    }
+    let k = k.min(n - k);
+    // Each partial product is itself a binomial coefficient, so the
+    // division is exact.
+    (0..k).fold(BigUint::from(1u32), |acc, i| acc * (n - i) / (i + 1))
+}
+
+/// Evaluate an integer expression exactly.
+///
+/// ```
+/// use exath_engine::exact::evaluate_exact;
+/// assert!(evaluate_exact("30! / 1").is_err());
+/// assert_eq!(evaluate_exact("2^100 + 1").unwrap().to_string(), "1267650600228229401496703205377");
+/// assert_eq!(evaluate_exact("200!").unwrap().to_string().len(), 375);
+/// ```
+pub fn evaluate_exact(expr: &str) -> Result<BigInt, ExathError> {
+    let ast = parse_str(expr)?;
+    eval(&ast, &mut Vec::new())
+}
+
+/// Evaluate `ast` with the loop variables of enclosing `sum`/`product` calls
+/// in `bound`, innermost last.
+fn eval(ast: &Ast, bound: &mut Vec<(String, BigInt)>) -> Result<BigInt, ExathError> {
+    match ast {
+        Ast::Number(x) => {
+            // Literals above 2^53 were already rounded by the tokenizer.
+            if x.fract() != 0.0 || x.abs() > 9_007_199_254_740_992.0 {
+                return Err(ExathError::arg_type(format!(
+                    "Exact mode needs integer literals up to 2^53, got {}",
+                    x
+                )));
+            }
+            Ok(BigInt::from(*x as i64))
+        }
+        Ast::Var(name) => bound
+            .iter()
+            .rev()
+            .find(|(v, _)| v == name)
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| ExathError::undefined(format!("Undefined variable: {}", name))),
+        Ast::UnaryNeg(inner) => Ok(-eval(inner, bound)?),
+        Ast::Factorial(inner) => {
+            let n = eval(inner, bound)?;
+            let n = u64::try_from(&n).map_err(|_| {
+                ExathError::domain("Factorial only defined for non-negative integers")
+            })?;
+            // log2(n!) ≈ n·(log2 n − log2 e), checked before doing the work.
+            if n > 1 {
+                let n = n as f64;
+                check_bits((n * (n.log2() - std::f64::consts::LOG2_E)) as u64)?;
+            }
+            Ok(factorial_big(n).into())
+        }
+        Ast::BinOp(op, left, right) => {
+            let a = eval(left, bound)?;
+            let b = eval(right, bound)?;
+            let value = match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Pow => {
+                    let e = u32::try_from(&b).map_err(|_| {
+                        ExathError::domain("Exact mode needs a non-negative integer exponent")
+                    })?;
+                    check_bits(a.bits().saturating_mul(e as u64))?;
+                    a.pow(e)
+                }
+                _ => return Err(unsupported(ast)),
+            };
+            check_bits(value.bits())?;
+            Ok(value)
+        }
+        Ast::Call(name, args) => match (name.as_str(), args.as_slice()) {
+            ("binom", [n, k]) => {
+                let n = eval(n, bound)?;
+                let k = eval(k, bound)?;
+                // Like the float `binom`: 0 outside 0 <= k <= n.
+                match (u64::try_from(&n), u64::try_from(&k)) {
+                    (Ok(n), Ok(k)) => {
+                        // log2 C(n, k) from Stirling, checked before the
+                        // k-step product.
+                        if k <= n {
+                            let log2_fact = |m: u64| log2_factorial(m as f64);
+                            let bits = log2_fact(n) - log2_fact(k) - log2_fact(n - k);
+                            check_bits(bits as u64)?;
+                        }
+                        Ok(binom_big(n, k).into())
+                    }
+                    _ => Ok(BigInt::from(0)),
+                }
+            }
+            ("sum" | "product", [body, Ast::Var(var), from, to]) => {
+                let from = to_i64(&eval(from, bound)?)?;
+                let to = to_i64(&eval(to, bound)?)?;
+                let is_sum = name == "sum";
+                check_terms(if is_sum { "Sum" } else { "Product" }, from, to, MAX_TERMS)?;
+                let mut acc = BigInt::from(if is_sum { 0 } else { 1 });
+                for k in from..=to {
+                    bound.push((var.clone(), BigInt::from(k)));
+                    let term = eval(body, bound);
+                    bound.pop();
+                    if is_sum {
+                        acc += term?;
+                    } else {
+                        acc *= term?;
+                    }
+                    check_bits(acc.bits())?;
+                }
+                Ok(acc)
+            }
+            _ => Err(unsupported(ast)),
+        },
+        Ast::UnaryNot(_) | Ast::Chain(..) | Ast::Matrix(_) => Err(unsupported(ast)),
+    }
+}
+
+fn to_i64(x: &BigInt) -> Result<i64, ExathError> {
+    i64::try_from(x).map_err(|_| ExathError::range_too_large("Range bound too large"))
+}
+
+/// Stirling's approximation of log2(n!), accurate to well under a bit for
+/// n >= 2.
+fn log2_factorial(n: f64) -> f64 {
+    if n < 2.0 {
+        return 0.0;
+    }
+    n * (n.log2() - std::f64::consts::LOG2_E) + 0.5 * (std::f64::consts::TAU * n).log2()
+}
+
+fn check_bits(bits: u64) -> Result<(), ExathError> {
+    if bits > MAX_BITS {
+        return Err(ExathError::range_too_large(format!(
+            "Exact result too large (max {} bits)",
+            MAX_BITS
+        )));
+    }
+    Ok(())
+}
+
+fn unsupported(ast: &Ast) -> ExathError {
+    ExathError::arg_type(format!(
+        "Not exact integer arithmetic: {}",
+        crate::symbolic::render(ast)
+    ))
+}
+
+#[cfg(test)]
+mod exact_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn factorials_are_exact_where_f64_rounds() {
+        let f64_fact = |n: f64| crate::evaluator::factorial(n).unwrap();
+        // 20! still fits in 53 bits of mantissa (it has 18 factors of two)...
+        assert_eq!(factorial_big(20).to_string(), "2432902008176640000");
+        assert_eq!(f64_fact(20.0) as u128, 2_432_902_008_176_640_000);
+        // ...25! does not.
+        assert_eq!(factorial_big(25).to_string(), "15511210043330985984000000");
+        assert_ne!(f64_fact(25.0) as u128, 15_511_210_043_330_985_984_000_000);
+        assert_eq!(factorial_big(0), BigUint::from(1u32));
+        assert!(crate::evaluate("200!", crate::AngleMode::Rad).unwrap().is_infinite());
+        assert!(evaluate_exact("200!").unwrap().to_string().starts_with("7886578673647905035523632"));
+    }
+
+    #[test]
+    fn combinatorics() {
+        let exact = |e: &str| evaluate_exact(e).unwrap().to_string();
+        assert_eq!(exact("binom(100, 50)"), "100891344545564193334812497256");
+        assert_eq!(exact("binom(5, 7)"), "0");
+        assert_eq!(exact("binom(2^53, 1)"), "9007199254740992");
+        assert_eq!(exact("binom(2^53, 2^53 - 1)"), "9007199254740992");
+        // The size estimate is close enough not to reject results that fit.
+        let estimate = log2_factorial(2000.0) - 2.0 * log2_factorial(1000.0);
+        assert!((estimate - binom_big(2000, 1000).bits() as f64).abs() < 1.0);
+        assert_eq!(exact("product(k, k, 1, 25)"), factorial_big(25).to_string());
+        assert_eq!(exact("sum(k^2, k, 1, 100)"), "338350");
+        assert_eq!(exact("product(binom(n, 2), n, 2, 4)"), "18");
+        assert_eq!(exact("-3! + 2^64"), "18446744073709551610");
+    }
+
+    #[test]
+    fn rejects_what_is_not_exact() {
+        let kind = |e: &str| evaluate_exact(e).unwrap_err().kind;
+        assert_eq!(kind("1 / 2"), ErrorKind::ArgumentType);
+        assert_eq!(kind("sqrt(4)"), ErrorKind::ArgumentType);
+        assert_eq!(kind("0.5 * 2"), ErrorKind::ArgumentType);
+        assert_eq!(kind("pi"), ErrorKind::ArgumentType);
+        assert_eq!(kind("x + 1"), ErrorKind::UndefinedName);
+        assert_eq!(kind("(-1)!"), ErrorKind::DomainError);
+        assert_eq!(kind("2^(-1)"), ErrorKind::DomainError);
+        assert_eq!(kind("10^400000"), ErrorKind::RangeTooLarge);
+        assert_eq!(kind("100000!"), ErrorKind::RangeTooLarge);
+        assert_eq!(kind("product(k, k, 1, 10^9)"), ErrorKind::RangeTooLarge);
+        assert_eq!(kind("binom(2^53, 2^52)"), ErrorKind::RangeTooLarge);
+        assert_eq!(kind("binom(2^21, 2^20)"), ErrorKind::RangeTooLarge);
+    }
+}
@@ -1,24 +1,54 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("exath-engine needs the `std` feature, or `libm` for no_std builds");
+
+extern crate alloc;
+
+mod prelude;
+
 pub mod angle_mode;
 pub mod ast;
+pub mod config;
 pub mod error;
 pub mod evaluator;
+#[cfg(feature = "bigint")]
+pub mod exact;
+#[cfg(feature = "std")]
 pub mod analysis;
+#[cfg(feature = "std")]
 pub mod interval;
+#[cfg(feature = "std")]
 pub mod matrix;
+#[cfg(feature = "std")]
 pub mod numerics;
+#[cfg(feature = "std")]
 pub mod rational;
+#[cfg(feature = "std")]
 pub mod symbolic;
 pub mod units;
 
 // ── Convenience re-exports ────────────────────────────────────────────────────
 
 pub use angle_mode::AngleMode;
+pub use config::{BranchPolicy, DivByZero, EvalConfig, ModConvention, ParseConfig};
 pub use error::{ExathError, ErrorKind};
 pub use evaluator::{
-    CalcResult, Session, LineResult,
-    evaluate, evaluate_complex, evaluate_with_vars, evaluate_with_vars_and_fns,
+    CalcResult, EvalBuilder, ResultMode, evaluate, evaluate_batch, evaluate_complex,
+    evaluate_typed, evaluate_with_reals, evaluate_with_vars, evaluate_with_vars_and_fns,
+};
+#[cfg(feature = "std")]
+pub use evaluator::{Changes, Session, LineResult, VarChangeCallback};
+#[cfg(feature = "profiling")]
+pub use evaluator::{evaluate_timed, Timings};
+pub use ast::collect_vars;
+#[cfg(feature = "std")]
+pub use analysis::{
+    function_aliases, function_catalog, function_categories, function_help, is_valid,
+    supported_functions, tokens, TokenInfo, TokenKind,
 };
-pub use analysis::{is_valid, supported_functions};
+#[cfg(feature = "std")]
 pub use matrix::Matrix;
+#[cfg(feature = "std")]
 pub use interval::Interval;
 pub use units::Quantity;
@@ -603,33 +603,42 @@ pub fn eval_matrix_ast(
     fns: &UserFns,
     angle: AngleMode,
 ) -> Result<MValue, ExathError> {
+    eval_matrix_with(ast, &|e: &Ast| eval_ast(e, vars, fns, angle))
+}
+
+/// Like [`eval_matrix_ast`], evaluating scalar sub-expressions with `scalar`
+/// (a session passes its own evaluator, so natives and defaults apply).
+pub(crate) fn eval_matrix_with(ast: &Ast, scalar: &ScalarEval) -> Result<MValue, ExathError> {
     match ast {
         Ast::Matrix(rows) => {
             let mut data = Vec::with_capacity(rows.len());
             for r in rows {
                 let mut row = Vec::with_capacity(r.len());
                 for e in r {
-                    row.push(eval_ast(e, vars, fns, angle)?.re);
+                    row.push(scalar(e)?.re);
                 }
                 data.push(row);
             }
             Ok(MValue::Mat(Matrix::new(data)?))
         }
-        Ast::UnaryNeg(u) => match eval_matrix_ast(u, vars, fns, angle)? {
+        Ast::UnaryNeg(u) => match eval_matrix_with(u, scalar)? {
             MValue::Scalar(s) => Ok(MValue::Scalar(-s)),
             MValue::Mat(m) => Ok(MValue::Mat(m.scale(-1.0))),
         },
         Ast::BinOp(op, l, r) => {
-            let a = eval_matrix_ast(l, vars, fns, angle)?;
-            let b = eval_matrix_ast(r, vars, fns, angle)?;
+            let a = eval_matrix_with(l, scalar)?;
+            let b = eval_matrix_with(r, scalar)?;
             eval_matrix_binop(op, a, b)
         }
-        Ast::Call(name, args) => eval_matrix_call(name, args, vars, fns, angle),
+        Ast::Call(name, args) => eval_matrix_call(name, args, scalar),
         // Anything else is a scalar.
-        _ => Ok(MValue::Scalar(eval_ast(ast, vars, fns, angle)?.re)),
+        _ => Ok(MValue::Scalar(scalar(ast)?.re)),
     }
 }
 
+/// Evaluator for the scalar parts of a matrix expression.
+pub(crate) type ScalarEval<'a> = dyn Fn(&Ast) -> Result<Cx, ExathError> + 'a;
+
 fn eval_matrix_binop(op: &BinOp, a: MValue, b: MValue) -> Result<MValue, ExathError> {
     use MValue::*;
     match (op, a, b) {
@@ -654,18 +663,12 @@ fn eval_matrix_binop(op: &BinOp, a: MValue, b: MValue) -> Result<MValue, ExathEr
     }
 }
 
-fn eval_matrix_call(
-    name: &str,
-    args: &[Ast],
-    vars: &HashMap<String, Cx>,
-    fns: &UserFns,
-    angle: AngleMode,
-) -> Result<MValue, ExathError> {
+fn eval_matrix_call(name: &str, args: &[Ast], scalar: &ScalarEval) -> Result<MValue, ExathError> {
     let one_matrix = |a: &[Ast]| -> Result<Matrix, ExathError> {
         if a.len() != 1 {
             return Err(ExathError::arg_count(format!("{} expects 1 argument", name)));
         }
-        match eval_matrix_ast(&a[0], vars, fns, angle)? {
+        match eval_matrix_with(&a[0], scalar)? {
             MValue::Mat(m) => Ok(m),
             MValue::Scalar(_) => Err(ExathError::arg_type(format!("{} expects a matrix", name))),
         }
@@ -690,7 +693,7 @@ fn eval_matrix_call(
                 return Err(ExathError::arg_count("linsolve expects 2 arguments: linsolve(A, b)"));
             }
             let a = one_matrix(&args[..1])?;
-            let b = match eval_matrix_ast(&args[1], vars, fns, angle)? {
+            let b = match eval_matrix_with(&args[1], scalar)? {
                 MValue::Mat(m) => m,
                 MValue::Scalar(_) => {
                     return Err(ExathError::arg_type("linsolve: b must be a vector"))
@@ -705,7 +708,7 @@ fn eval_matrix_call(
             if args.len() != 1 {
                 return Err(ExathError::arg_count("identity expects 1 argument"));
             }
-            let n = eval_ast(&args[0], vars, fns, angle)?.re;
+            let n = scalar(&args[0])?.re;
             if n < 1.0 || n.fract() != 0.0 {
                 return Err(ExathError::domain("identity: size must be a positive integer"));
             }
@@ -7,22 +7,40 @@ use crate::angle_mode::AngleMode;
 use crate::ast::{parse_str, eval_ast, UserFns};
 use crate::error::ExathError;
 use crate::evaluator::Cx;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 // ── Helper: evaluate expr with one real variable ──────────────────────────────
 
-fn eval_at(
+/// `ast` as a real function of `var`, evaluated on top of `vars` and `fns`.
+fn real_fn<'a>(
+    ast: &'a crate::ast::Ast,
+    var: &'a str,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &'a UserFns,
+) -> impl Fn(f64) -> Result<f64, ExathError> + 'a {
+    let vars = RefCell::new(vars.clone());
+    move |x| {
+        vars.borrow_mut().insert(var.to_string(), Cx::real(x));
+        require_real(eval_ast(ast, &vars.borrow(), fns, angle_mode)?, x)
+    }
+}
+
+fn eval_real(
     ast: &crate::ast::Ast,
-    var: &str,
+    vars: &HashMap<String, Cx>,
     x: f64,
     angle_mode: AngleMode,
 ) -> Result<f64, ExathError> {
-    let mut vars = HashMap::new();
-    vars.insert(var.to_string(), Cx::real(x));
     let empty_fns = UserFns::new();
-    let result = eval_ast(ast, &vars, &empty_fns, angle_mode)?;
-    if result.is_real() {
-        Ok(result.re)
+    require_real(eval_ast(ast, vars, &empty_fns, angle_mode)?, x)
+}
+
+/// The real part of `value`, or a `ComplexResult` error naming `x`.
+pub(crate) fn require_real(value: Cx, x: f64) -> Result<f64, ExathError> {
+    if value.is_real() {
+        Ok(value.re)
     } else {
         Err(ExathError::complex_result(format!(
             "Expression produced a complex value at x={}",
@@ -43,18 +61,202 @@ pub fn deriv(
     x: f64,
     angle_mode: AngleMode,
 ) -> Result<f64, ExathError> {
+    deriv_with(expr, var, x, angle_mode, &HashMap::new(), &UserFns::new())
+}
+
+/// Like [`deriv`], but `expr` can also use the variables in `vars` and call
+/// the user functions in `fns`. `var` shadows an entry of the same name.
+pub fn deriv_with(
+    expr: &str,
+    var: &str,
+    x: f64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
+    let ast = parse_str(expr)?;
+    deriv_fn(real_fn(&ast, var, angle_mode, vars, fns), x)
+}
+
+/// Central difference of `f` at `x`, with the step size used by [`deriv`].
+pub(crate) fn deriv_fn(
+    f: impl Fn(f64) -> Result<f64, ExathError>,
+    x: f64,
+) -> Result<f64, ExathError> {
+    let h = deriv_step(x);
+    Ok((f(x + h)? - f(x - h)?) / (2.0 * h))
+}
+
+fn deriv_step(x: f64) -> f64 {
+    (x.abs() * 1e-7_f64).max(1e-10_f64)
+}
+
+/// Differentiate `expr` with respect to `var` at `x` by the complex step
+/// `f'(x) ≈ Im f(x + ih) / h` with `h = 1e-20`.
+///
+/// Nothing is subtracted, so for analytic expressions (`sin`, `exp`,
+/// polynomials, …) the result is accurate to nearly full `f64` precision,
+/// where [`deriv`] keeps only about half the digits.
+///
+/// The central difference of [`deriv`] is computed too and returned instead
+/// when the complex step disagrees with it: for expressions that are not
+/// analytic in `var` (`abs`, `floor`, comparisons, …), for trig functions in
+/// Deg or Grad mode, or where the complex step fails to evaluate. Errors of
+/// [`deriv`], such as a complex value at `x`, are returned as they are.
+///
+/// ```
+/// use exath_engine::{numerics::deriv_complex_step, AngleMode};
+/// let d = deriv_complex_step("sin(x)", "x", 1.0, AngleMode::Rad).unwrap();
+/// assert!((d - 1f64.cos()).abs() < 1e-15);
+/// ```
+pub fn deriv_complex_step(
+    expr: &str,
+    var: &str,
+    x: f64,
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    const H: f64 = 1e-20;
     let ast = parse_str(expr)?;
+    let fns = UserFns::new();
+    let central = deriv_fn(real_fn(&ast, var, angle_mode, &HashMap::new(), &fns), x)?;
+
+    let vars = HashMap::from([(var.to_string(), Cx::new(x, H))]);
+    let Ok(value) = eval_ast(&ast, &vars, &fns, angle_mode) else {
+        return Ok(central);
+    };
+    let step = value.im / H;
+    // Rounding error of the central difference: ε·|f(x)| / h, plus slack
+    // for its truncation error.
+    let tol = 1e-6 * (1.0 + central.abs()) + 4.0 * f64::EPSILON * value.re.abs() / deriv_step(x);
+    if step.is_finite() && (step - central).abs() <= tol {
+        Ok(step)
+    } else {
+        Ok(central)
+    }
+}
+
+/// Like [`deriv`], but first compares the forward and backward one-sided
+/// slopes at `x` and returns a `DomainError` when they disagree: the graph
+/// has a kink there, as `abs(x)` at `0`, and the symmetric difference would
+/// average the two slopes into a derivative that does not exist.
+///
+/// The slopes are taken at two step sizes. On a smooth graph their gap
+/// shrinks with the step (it is about `|f''|·h`); at a kink it stays. So a
+/// gap counts when it is above `1e-4` (relative, plus rounding noise) and
+/// does not shrink.
+///
+/// ```
+/// use exath_engine::{numerics::deriv_checked, AngleMode, ErrorKind};
+/// let err = deriv_checked("abs(x)", "x", 0.0, AngleMode::Rad).unwrap_err();
+/// assert_eq!(err.kind, ErrorKind::DomainError);
+/// assert!((deriv_checked("abs(x)", "x", 2.0, AngleMode::Rad).unwrap() - 1.0).abs() < 1e-6);
+/// ```
+pub fn deriv_checked(
+    expr: &str,
+    var: &str,
+    x: f64,
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    let ast = parse_str(expr)?;
+    let fns = UserFns::new();
+    let vars = HashMap::new();
+    let f = real_fn(&ast, var, angle_mode, &vars, &fns);
+    let f0 = f(x)?;
+    let slopes = |h: f64| -> Result<(f64, f64), ExathError> {
+        Ok(((f0 - f(x - h)?) / h, (f(x + h)? - f0) / h))
+    };
+    let h = 1e-5 * x.abs().max(1.0);
+    let (left_wide, right_wide) = slopes(h)?;
+    let (left, right) = slopes(h / 16.0)?;
+    let gap = (right - left).abs();
+    let tol = 1e-4 * left.abs().max(right.abs()).max(1.0) + 64.0 * f64::EPSILON * f0.abs() / h;
+    let shrinks = gap <= (right_wide - left_wide).abs() / 4.0;
+    if !(gap <= tol || shrinks) {
+        return Err(ExathError::domain(format!(
+            "Non-differentiable at {}={}: the slope is {} from the left and {} from the right",
+            var, x, left, right
+        )));
+    }
+    deriv_fn(f, x)
+}
+
+/// Partial derivative of `expr` with respect to `var` at `point`.
+///
+/// `point` gives a value for every variable of `expr`, including `var`; the
+/// others are held fixed while `var` is stepped by the same central finite
+/// difference as [`deriv`].
+///
+/// ```
+/// use exath_engine::{numerics::partial, AngleMode};
+/// use std::collections::HashMap;
+/// let point = HashMap::from([("x".to_string(), 2.0), ("y".to_string(), 3.0)]);
+/// let d = partial("x^2 * y", "x", &point, AngleMode::Rad).unwrap();
+/// assert!((d - 12.0).abs() < 1e-5);
+/// ```
+pub fn partial(
+    expr: &str,
+    var: &str,
+    point: &HashMap<String, f64>,
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    partial_ast(&parse_str(expr)?, var, point, angle_mode)
+}
+
+/// Gradient of `expr` at `point`: the partial derivative with respect to
+/// each of `vars`, in order. See [`partial`].
+pub fn gradient(
+    expr: &str,
+    vars: &[&str],
+    point: &HashMap<String, f64>,
+    angle_mode: AngleMode,
+) -> Result<Vec<f64>, ExathError> {
+    let ast = parse_str(expr)?;
+    vars.iter()
+        .map(|var| partial_ast(&ast, var, point, angle_mode))
+        .collect()
+}
+
+fn partial_ast(
+    ast: &crate::ast::Ast,
+    var: &str,
+    point: &HashMap<String, f64>,
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    let x = *point.get(var).ok_or_else(|| {
+        ExathError::undefined(format!("No value for '{}' in the evaluation point", var))
+    })?;
+    let mut vars: HashMap<String, Cx> = point
+        .iter()
+        .map(|(name, value)| (name.clone(), Cx::real(*value)))
+        .collect();
     let h = (x.abs() * 1e-7_f64).max(1e-10_f64);
-    let forward = eval_at(&ast, var, x + h, angle_mode)?;
-    let backward = eval_at(&ast, var, x - h, angle_mode)?;
+    vars.insert(var.to_string(), Cx::real(x + h));
+    let forward = eval_real(ast, &vars, x + h, angle_mode)?;
+    vars.insert(var.to_string(), Cx::real(x - h));
+    let backward = eval_real(ast, &vars, x - h, angle_mode)?;
     Ok((forward - backward) / (2.0 * h))
 }
 
-// ── Integral (composite Simpson's rule) ───────────────────────────────────────
+// ── Integral (composite Newton–Cotes rules) ──────────────────────────────────
+
+/// Quadrature rule used by [`integrate_method`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegrationMethod {
+    /// Composite Simpson's rule. Exact for cubics; assumes a smooth integrand.
+    /// An odd interval count is rounded up to the next even number.
+    #[default]
+    Simpson,
+    /// Composite trapezoidal rule. More robust for piecewise or noisy
+    /// integrands.
+    Trapezoid,
+    /// Composite midpoint rule. Never evaluates the endpoints, so it handles
+    /// integrands that are singular at a boundary, like `1/sqrt(x)` on `[0, 1]`.
+    Midpoint,
+}
 
 /// Numerically integrate `expr` with respect to `var` from `a` to `b`.
 ///
-/// Uses composite Simpson's rule with n=1000 intervals (must be even).
+/// Uses composite Simpson's rule with n=1000 intervals, see [`integrate_n`].
 pub fn integrate(
     expr: &str,
     var: &str,
@@ -62,27 +264,173 @@ pub fn integrate(
     b: f64,
     angle_mode: AngleMode,
 ) -> Result<f64, ExathError> {
-    const N: usize = 1000;
+    integrate_n(expr, var, a, b, angle_mode, 1000)
+}
+
+/// Like [`integrate`], also returning an estimate of the value's error.
+///
+/// The value is Simpson's rule with n=1000, as from [`integrate`]; the
+/// estimate compares it with n=500 by Richardson extrapolation,
+/// `|S₁₀₀₀ − S₅₀₀| / 15`. It assumes a smooth integrand: at a kink or an
+/// endpoint singularity the true error can be larger than estimated.
+///
+/// ```
+/// use exath_engine::numerics::integrate_with_error;
+/// use exath_engine::AngleMode;
+/// let (value, error) = integrate_with_error("exp(x)", "x", 0.0, 1.0, AngleMode::Rad).unwrap();
+/// assert!((value - (1f64.exp() - 1.0)).abs() < 1e-12 && error < 1e-12);
+/// ```
+pub fn integrate_with_error(
+    expr: &str,
+    var: &str,
+    a: f64,
+    b: f64,
+    angle_mode: AngleMode,
+) -> Result<(f64, f64), ExathError> {
+    let ast = parse_str(expr)?;
+    let empty_fns = UserFns::new();
+    let f = real_fn(&ast, var, angle_mode, &HashMap::new(), &empty_fns);
+    let fine = integrate_fn(&f, a, b, IntegrationMethod::Simpson, 1000)?;
+    let coarse = integrate_fn(&f, a, b, IntegrationMethod::Simpson, 500)?;
+    Ok((fine, (fine - coarse).abs() / 15.0))
+}
+
+/// Like [`integrate`], but `expr` can also use the variables in `vars` and
+/// call the user functions in `fns`. `var` shadows an entry of the same name.
+pub fn integrate_with(
+    expr: &str,
+    var: &str,
+    a: f64,
+    b: f64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
     let ast = parse_str(expr)?;
-    let step = (b - a) / N as f64;
+    let f = real_fn(&ast, var, angle_mode, vars, fns);
+    integrate_fn(f, a, b, IntegrationMethod::Simpson, 1000)
+}
+
+/// Composite Simpson's rule with `n` intervals, for callers that want to
+/// trade accuracy for speed (e.g. plotting).
+///
+/// Simpson's rule needs an even count, so an odd `n` is rounded up to `n + 1`.
+/// `n == 0` is a `DomainError`.
+pub fn integrate_n(
+    expr: &str,
+    var: &str,
+    a: f64,
+    b: f64,
+    angle_mode: AngleMode,
+    n: usize,
+) -> Result<f64, ExathError> {
+    integrate_method(expr, var, a, b, angle_mode, IntegrationMethod::Simpson, n)
+}
+
+/// Numerically integrate `expr` with respect to `var` from `a` to `b`
+/// using `method` over `n` equal intervals.
+///
+/// Reversed bounds follow `∫_b^a = -∫_a^b`, and `a == b` gives 0.
+/// Non-finite bounds and `n == 0` are a `DomainError`.
+pub fn integrate_method(
+    expr: &str,
+    var: &str,
+    a: f64,
+    b: f64,
+    angle_mode: AngleMode,
+    method: IntegrationMethod,
+    n: usize,
+) -> Result<f64, ExathError> {
+    let ast = parse_str(expr)?;
+    let empty_fns = UserFns::new();
+    let f = real_fn(&ast, var, angle_mode, &HashMap::new(), &empty_fns);
+    integrate_fn(f, a, b, method, n)
+}
 
-    let first = eval_at(&ast, var, a, angle_mode)?;
-    let last = eval_at(&ast, var, b, angle_mode)?;
+/// Integrate `expr` over several `(a, b)` segments and add the results, e.g.
+/// `[(0.0, 1.0), (2.0, 3.0)]` for `[0, 1] ∪ [2, 3]`.
+///
+/// Each segment gets its own Simpson pass with n=1000 as in [`integrate`],
+/// so splitting a piecewise integrand at its jumps avoids the error a single
+/// pass makes across the discontinuity. No segments give 0.
+pub fn integrate_segments(
+    expr: &str,
+    var: &str,
+    segments: &[(f64, f64)],
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    let ast = parse_str(expr)?;
+    let empty_fns = UserFns::new();
+    let f = real_fn(&ast, var, angle_mode, &HashMap::new(), &empty_fns);
+    let mut total = 0.0;
+    for &(a, b) in segments {
+        total += integrate_fn(&f, a, b, IntegrationMethod::Simpson, 1000)?;
+    }
+    Ok(total)
+}
 
-    let mut total = first + last;
-    for i in 1..N {
-        let x = a + i as f64 * step;
-        let value = eval_at(&ast, var, x, angle_mode)?;
-        total += if i % 2 == 0 { 2.0 * value } else { 4.0 * value };
+/// [`integrate_method`] over an arbitrary real function `f`.
+pub(crate) fn integrate_fn(
+    f: impl Fn(f64) -> Result<f64, ExathError>,
+    a: f64,
+    b: f64,
+    method: IntegrationMethod,
+    n: usize,
+) -> Result<f64, ExathError> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(ExathError::domain(format!(
+            "Integration bounds must be finite (got {} and {})",
+            a, b
+        )));
     }
-    Ok(total * step / 3.0)
+    if n == 0 {
+        return Err(ExathError::domain("Number of intervals must be positive"));
+    }
+    if a == b {
+        return Ok(0.0);
+    }
+    let (a, b, sign) = if a > b { (b, a, -1.0) } else { (a, b, 1.0) };
+
+    let total = match method {
+        IntegrationMethod::Simpson => {
+            let n = n + n % 2;
+            let step = (b - a) / n as f64;
+            let mut total = f(a)? + f(b)?;
+            for i in 1..n {
+                let value = f(a + i as f64 * step)?;
+                total += if i % 2 == 0 { 2.0 * value } else { 4.0 * value };
+            }
+            total * step / 3.0
+        }
+        IntegrationMethod::Trapezoid => {
+            let step = (b - a) / n as f64;
+            let mut total = (f(a)? + f(b)?) / 2.0;
+            for i in 1..n {
+                total += f(a + i as f64 * step)?;
+            }
+            total * step
+        }
+        IntegrationMethod::Midpoint => {
+            let step = (b - a) / n as f64;
+            let mut total = 0.0;
+            for i in 0..n {
+                total += f(a + (i as f64 + 0.5) * step)?;
+            }
+            total * step
+        }
+    };
+    Ok(sign * total)
 }
 
 // ── Sum / Product ─────────────────────────────────────────────────────────────
 
-const MAX_TERMS: i64 = 10_000_000;
+/// Default cap on the number of terms of [`sum`] and [`prod`]; see
+/// [`sum_limited`] to choose another.
+pub const MAX_TERMS: u64 = 10_000_000;
 
 /// Compute Σ expr for `var` = `from` to `to` (inclusive, integer steps).
+///
+/// An empty range (`to < from`) is the empty sum, 0.
 pub fn sum(
     expr: &str,
     var: &str,
@@ -90,21 +438,141 @@ pub fn sum(
     to: i64,
     angle_mode: AngleMode,
 ) -> Result<f64, ExathError> {
-    if to - from > MAX_TERMS {
+    sum_with(expr, var, from, to, angle_mode, &HashMap::new(), &UserFns::new())
+}
+
+/// Like [`sum`], but `expr` can also use the variables in `vars` and call
+/// the user functions in `fns`. `var` shadows an entry of the same name.
+pub fn sum_with(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
+    check_terms("Sum", from, to, MAX_TERMS)?;
+    sum_unchecked(expr, var, from, to, angle_mode, vars, fns)
+}
+
+/// Like [`sum`], with a caller-chosen cap on the number of terms instead of
+/// [`MAX_TERMS`]. A range with more terms is a `RangeTooLarge` error;
+/// `max_terms == 0` is a `DomainError`.
+pub fn sum_limited(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    max_terms: u64,
+) -> Result<f64, ExathError> {
+    check_terms("Sum", from, to, max_terms)?;
+    sum_unchecked(expr, var, from, to, angle_mode, &HashMap::new(), &UserFns::new())
+}
+
+/// [`sum`] over several `(from, to)` ranges, added together. A range is
+/// summed each time it is listed, so overlapping ranges count their common
+/// terms twice. The [`MAX_TERMS`] cap applies to all ranges together.
+pub fn sum_segments(
+    expr: &str,
+    var: &str,
+    ranges: &[(i64, i64)],
+    angle_mode: AngleMode,
+) -> Result<f64, ExathError> {
+    let terms: i128 = ranges
+        .iter()
+        .filter(|(from, to)| from <= to)
+        .map(|&(from, to)| to as i128 - from as i128 + 1)
+        .sum();
+    if terms > MAX_TERMS as i128 {
         return Err(ExathError::range_too_large(format!(
             "Sum range too large (max {} terms)",
             MAX_TERMS
         )));
     }
+    let mut total = 0.0;
+    for &(from, to) in ranges {
+        total += sum_unchecked(expr, var, from, to, angle_mode, &HashMap::new(), &UserFns::new())?;
+    }
+    Ok(total)
+}
+
+/// Σ expr for `var` = `from`, `from + 1`, … until a term's magnitude falls
+/// below `tol`, for estimating a convergent infinite series. That term is
+/// included in the partial sum returned.
+///
+/// The stopping rule only looks at the last term, so it suits series whose
+/// terms shrink steadily; the error of the estimate is the tail after it
+/// (about `1/n` for `1/k^2` stopped at `n`). Running `max_terms` terms
+/// without converging is a `RangeTooLarge` error; `max_terms == 0` or a
+/// `tol` that is not positive is a `DomainError`.
+///
+/// ```
+/// use exath_engine::numerics::sum_until;
+/// use exath_engine::AngleMode;
+/// let s = sum_until("1/2^k", "k", 0, AngleMode::Rad, 1e-12, 1000).unwrap();
+/// assert!((s - 2.0).abs() < 1e-11);
+/// ```
+pub fn sum_until(
+    expr: &str,
+    var: &str,
+    from: i64,
+    angle_mode: AngleMode,
+    tol: f64,
+    max_terms: u64,
+) -> Result<f64, ExathError> {
+    if max_terms == 0 {
+        return Err(ExathError::domain("max_terms must be at least 1"));
+    }
+    if tol.is_nan() || tol <= 0.0 {
+        return Err(ExathError::domain("tol must be positive"));
+    }
     let ast = parse_str(expr)?;
+    let empty_fns = UserFns::new();
+    let f = real_fn(&ast, var, angle_mode, &HashMap::new(), &empty_fns);
+    let mut accumulator = 0.0f64;
+    let mut k = from;
+    for _ in 0..max_terms {
+        let term = f(k as f64)?;
+        accumulator += term;
+        if term.abs() < tol {
+            return Ok(accumulator);
+        }
+        k = k.checked_add(1).ok_or_else(|| ExathError::range_too_large("Sum index overflowed i64"))?;
+    }
+    Err(ExathError::range_too_large(format!(
+        "Sum did not converge within {} terms",
+        max_terms
+    )))
+}
+
+fn sum_unchecked(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
+    let ast = parse_str(expr)?;
+    let f = real_fn(&ast, var, angle_mode, vars, fns);
     let mut accumulator = 0.0f64;
     for k in from..=to {
-        accumulator += eval_at(&ast, var, k as f64, angle_mode)?;
+        accumulator += f(k as f64)?;
     }
     Ok(accumulator)
 }
 
 /// Compute Π expr for `var` = `from` to `to` (inclusive, integer steps).
+///
+/// An empty range (`to < from`) is the empty product, 1.
+///
+/// Stops at the first factor that makes the product exactly zero and
+/// returns 0: later factors cannot change it, so they are not evaluated
+/// (nor can they error). The one difference from multiplying everything is
+/// that a later `inf` or `NaN` factor does not turn the 0 into `NaN`.
 pub fn prod(
     expr: &str,
     var: &str,
@@ -112,16 +580,405 @@ pub fn prod(
     to: i64,
     angle_mode: AngleMode,
 ) -> Result<f64, ExathError> {
-    if to - from > MAX_TERMS {
-        return Err(ExathError::range_too_large(format!(
-            "Product range too large (max {} terms)",
-            MAX_TERMS
-        )));
-    }
+    prod_with(expr, var, from, to, angle_mode, &HashMap::new(), &UserFns::new())
+}
+
+/// Like [`prod`], but `expr` can also use the variables in `vars` and call
+/// the user functions in `fns`. `var` shadows an entry of the same name.
+pub fn prod_with(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
+    check_terms("Product", from, to, MAX_TERMS)?;
+    prod_unchecked(expr, var, from, to, angle_mode, vars, fns)
+}
+
+/// Like [`prod`], with a caller-chosen cap on the number of terms, see
+/// [`sum_limited`].
+pub fn prod_limited(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    max_terms: u64,
+) -> Result<f64, ExathError> {
+    check_terms("Product", from, to, max_terms)?;
+    prod_unchecked(expr, var, from, to, angle_mode, &HashMap::new(), &UserFns::new())
+}
+
+fn prod_unchecked(
+    expr: &str,
+    var: &str,
+    from: i64,
+    to: i64,
+    angle_mode: AngleMode,
+    vars: &HashMap<String, Cx>,
+    fns: &UserFns,
+) -> Result<f64, ExathError> {
     let ast = parse_str(expr)?;
+    let f = real_fn(&ast, var, angle_mode, vars, fns);
     let mut accumulator = 1.0f64;
     for k in from..=to {
-        accumulator *= eval_at(&ast, var, k as f64, angle_mode)?;
+        accumulator *= f(k as f64)?;
+        if accumulator == 0.0 {
+            break;
+        }
     }
     Ok(accumulator)
 }
+
+/// Reject ranges `from..=to` with more than `max_terms` terms.
+pub(crate) fn check_terms(what: &str, from: i64, to: i64, max_terms: u64) -> Result<(), ExathError> {
+    if max_terms == 0 {
+        return Err(ExathError::domain("max_terms must be at least 1"));
+    }
+    // An empty range has no terms and is always within the cap.
+    if to < from {
+        return Ok(());
+    }
+    let terms = to as i128 - from as i128 + 1;
+    if terms > max_terms as i128 {
+        return Err(ExathError::range_too_large(format!(
+            "{} range too large (max {} terms)",
+            what, max_terms
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod integrate_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const RAD: AngleMode = AngleMode::Rad;
+
+    #[test]
+    fn reversed_bounds_negate() {
+        let forward = integrate("x", "x", 0.0, 2.0, RAD).unwrap();
+        let backward = integrate("x", "x", 2.0, 0.0, RAD).unwrap();
+        assert!((forward - 2.0).abs() < 1e-12);
+        assert_eq!(forward, -backward);
+        assert_eq!(integrate("x^2", "x", 3.0, 3.0, RAD).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn non_finite_bounds_are_domain_errors() {
+        for (a, b) in [(f64::NAN, 1.0), (0.0, f64::INFINITY), (f64::NEG_INFINITY, 0.0)] {
+            let err = integrate("x", "x", a, b, RAD).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::DomainError);
+        }
+    }
+
+    #[test]
+    fn methods_agree_on_x_squared() {
+        let exact = 1.0 / 3.0;
+        let run = |method| integrate_method("x^2", "x", 0.0, 1.0, RAD, method, 100).unwrap();
+        let simpson = run(IntegrationMethod::Simpson);
+        let trapezoid = run(IntegrationMethod::Trapezoid);
+        let midpoint = run(IntegrationMethod::Midpoint);
+        assert!((simpson - exact).abs() < 1e-12);
+        // With f'' = 2 the error terms are +h²/6 (trapezoid) and -h²/12 (midpoint).
+        assert!((trapezoid - exact - 1e-4 / 6.0).abs() < 1e-12);
+        assert!((midpoint - exact + 1e-4 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn midpoint_skips_singular_endpoint() {
+        let method = IntegrationMethod::Midpoint;
+        let approx = integrate_method("1/sqrt(x)", "x", 0.0, 1.0, RAD, method, 10_000).unwrap();
+        assert!((approx - 2.0).abs() < 0.05);
+        assert!(integrate("1/sqrt(x)", "x", 0.0, 1.0, RAD).is_err());
+        let err = integrate_method("x", "x", 0.0, 1.0, RAD, method, 0).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DomainError);
+    }
+
+    #[test]
+    fn simpson_interval_count() {
+        let coarse = integrate_n("sin(x)", "x", 0.0, 3.0, RAD, 10).unwrap();
+        let fine = integrate_n("sin(x)", "x", 0.0, 3.0, RAD, 1000).unwrap();
+        let exact = 1.0 - 3f64.cos();
+        assert!((coarse - exact).abs() < 1e-3);
+        assert!((fine - exact).abs() < (coarse - exact).abs());
+        assert_eq!(fine, integrate("sin(x)", "x", 0.0, 3.0, RAD).unwrap());
+        assert_eq!(
+            integrate_n("sin(x)", "x", 0.0, 3.0, RAD, 9).unwrap(),
+            coarse
+        );
+        let err = integrate_n("x", "x", 0.0, 1.0, RAD, 0).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DomainError);
+    }
+
+    #[test]
+    fn segments_split_at_a_jump() {
+        let expr = "sign(x - 1.5)";
+        let split = integrate_segments(expr, "x", &[(0.0, 1.5), (1.5, 3.0)], RAD).unwrap();
+        let naive = integrate(expr, "x", 0.0, 3.0, RAD).unwrap();
+        // Exact is 0. Only the endpoint at the jump is off (`sign(0)` is 1),
+        // costing 2h/3 with h = 0.0015; the single pass has h = 0.003.
+        assert!((split - 0.001).abs() < 1e-12, "{}", split);
+        assert!(naive.abs() > split.abs(), "{} vs {}", naive, split);
+        // A jump off the grid of the single pass.
+        let exact = 1.0;
+        let split = integrate_segments("sign(x - 1)", "x", &[(0.0, 1.0), (1.0, 3.0)], RAD).unwrap();
+        let naive = integrate("sign(x - 1)", "x", 0.0, 3.0, RAD).unwrap();
+        assert!((split - exact).abs() < (naive - exact).abs());
+        assert_eq!(integrate_segments("x", "x", &[], RAD).unwrap(), 0.0);
+        let union = integrate_segments("x^2", "x", &[(0.0, 1.0), (2.0, 3.0)], RAD).unwrap();
+        assert!((union - (1.0 + 19.0) / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sum_over_several_ranges() {
+        assert_eq!(sum_segments("k", "k", &[(1, 3), (10, 11), (5, 4)], RAD).unwrap(), 27.0);
+        assert_eq!(sum_segments("k", "k", &[], RAD).unwrap(), 0.0);
+        let err = sum_segments("k", "k", &[(1, 6_000_000), (1, 6_000_000)], RAD).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::RangeTooLarge);
+    }
+}
+
+#[cfg(test)]
+mod error_estimate_tests {
+    use super::*;
+
+    #[test]
+    fn estimate_grows_with_roughness() {
+        let (value, error) = integrate_with_error("x^2", "x", 0.0, 1.0, AngleMode::Rad).unwrap();
+        assert!((value - 1.0 / 3.0).abs() < 1e-14);
+        assert!(error < 1e-14, "{}", error);
+        assert_eq!(value, integrate("x^2", "x", 0.0, 1.0, AngleMode::Rad).unwrap());
+
+        // sqrt has an unbounded slope at 0, which Simpson's rule resolves slowly.
+        let (value, rough) = integrate_with_error("sqrt(x)", "x", 0.0, 1.0, AngleMode::Rad).unwrap();
+        assert!((value - 2.0 / 3.0).abs() < 1e-4);
+        assert!(rough > 1e-8 && rough > 1e6 * error, "{}", rough);
+    }
+
+    #[test]
+    fn reversed_and_invalid_bounds() {
+        let (value, _) = integrate_with_error("x", "x", 1.0, 0.0, AngleMode::Rad).unwrap();
+        assert!((value + 0.5).abs() < 1e-12);
+        assert!(integrate_with_error("x", "x", 0.0, f64::INFINITY, AngleMode::Rad).is_err());
+    }
+}
+
+#[cfg(test)]
+mod partial_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn point(values: &[(&str, f64)]) -> HashMap<String, f64> {
+        values.iter().map(|(name, v)| (name.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn gradient_of_paraboloid() {
+        let g = gradient("x^2 + y^2", &["x", "y"], &point(&[("x", 1.0), ("y", 1.0)]), AngleMode::Rad)
+            .unwrap();
+        assert_eq!(g.len(), 2);
+        assert!((g[0] - 2.0).abs() < 1e-6 && (g[1] - 2.0).abs() < 1e-6, "{:?}", g);
+    }
+
+    #[test]
+    fn partial_holds_other_variables_fixed() {
+        let p = point(&[("x", 2.0), ("y", 3.0)]);
+        let dy = partial("x*y + sin(y)", "y", &p, AngleMode::Rad).unwrap();
+        assert!((dy - (2.0 + 3f64.cos())).abs() < 1e-6);
+        let err = partial("x*y", "z", &p, AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UndefinedName);
+        assert!(partial("x*w", "x", &p, AngleMode::Rad).is_err());
+    }
+}
+
+#[cfg(test)]
+mod context_tests {
+    use super::*;
+    use crate::ast::parse_str;
+    use crate::error::ErrorKind;
+
+    const RAD: AngleMode = AngleMode::Rad;
+
+    fn context() -> (HashMap<String, Cx>, UserFns) {
+        let mut vars = HashMap::new();
+        vars.insert("c".to_string(), Cx::real(2.0));
+        vars.insert("k".to_string(), Cx::real(100.0));
+        let mut fns = UserFns::new();
+        fns.insert("f".to_string(), (vec!["t".to_string()], parse_str("t^2").unwrap()));
+        (vars, fns)
+    }
+
+    #[test]
+    fn sum_and_prod_see_vars_and_fns() {
+        let (vars, fns) = context();
+        // k is the summation variable here, shadowing the stored k = 100.
+        let total = sum_with("f(k) + c", "k", 1, 10, RAD, &vars, &fns).unwrap();
+        assert!((total - (385.0 + 20.0)).abs() < 1e-9);
+        let product = prod_with("c * k", "k", 1, 4, RAD, &vars, &fns).unwrap();
+        assert!((product - 16.0 * 24.0).abs() < 1e-9);
+        assert_eq!(
+            sum("f(k) + c", "k", 1, 10, RAD).unwrap_err().kind,
+            ErrorKind::UndefinedName
+        );
+    }
+
+    #[test]
+    fn deriv_and_integrate_see_vars_and_fns() {
+        let (vars, fns) = context();
+        let slope = deriv_with("c * f(x)", "x", 3.0, RAD, &vars, &fns).unwrap();
+        assert!((slope - 12.0).abs() < 1e-5);
+        let area = integrate_with("c * f(x)", "x", 0.0, 3.0, RAD, &vars, &fns).unwrap();
+        assert!((area - 18.0).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod deriv_checked_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn kinks_are_errors() {
+        let err = deriv_checked("abs(x)", "x", 0.0, AngleMode::Rad).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DomainError);
+        assert!(err.message.starts_with("Non-differentiable at x=0"), "{}", err.message);
+        assert!(deriv_checked("abs(x - 3)", "x", 3.0, AngleMode::Rad).is_err());
+        assert!(deriv_checked("max(x, 2x)", "x", 0.0, AngleMode::Rad).is_err());
+        // A jump is caught as well: its one-sided slopes grow as h shrinks.
+        assert!(deriv_checked("floor(x)", "x", 0.0, AngleMode::Rad).is_err());
+        // The plain central difference hides the kink.
+        assert!(deriv("abs(x)", "x", 0.0, AngleMode::Rad).unwrap().abs() < 1e-6);
+    }
+
+    #[test]
+    fn smooth_points_match_deriv() {
+        for (expr, x, slope) in [
+            ("abs(x)", -2.0, -1.0),
+            ("x^2", 3.0, 6.0),
+            ("1e6 + x^3", 2.0, 12.0),
+            ("exp(5x)", 1.0, 5.0 * 5f64.exp()),
+            ("sin(x)", 1e4, 1e4f64.cos()),
+        ] {
+            let d = deriv_checked(expr, "x", x, AngleMode::Rad).unwrap();
+            assert!((d - slope).abs() < 1e-5 * slope.abs().max(1.0), "{}: {}", expr, d);
+        }
+    }
+}
+
+#[cfg(test)]
+mod complex_step_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const RAD: AngleMode = AngleMode::Rad;
+
+    #[test]
+    fn analytic_expressions_are_near_exact() {
+        let cs = |e: &str, x: f64| deriv_complex_step(e, "x", x, RAD).unwrap();
+        assert!((cs("sin(x)", 1.0) - 1f64.cos()).abs() < 1e-15);
+        assert!((cs("exp(x)", 10.0) / 10f64.exp() - 1.0).abs() < 1e-14);
+        assert!((cs("x^3 - 2x", 2.0) - 10.0).abs() < 1e-13);
+        assert!((cs("ln(x) * x", 3.0) - (3f64.ln() + 1.0)).abs() < 1e-15);
+        // The central difference is visibly less accurate on the same input.
+        let fd = deriv("exp(x)", "x", 10.0, RAD).unwrap();
+        assert!((fd / 10f64.exp() - 1.0).abs() > 1e-12);
+    }
+
+    #[test]
+    fn falls_back_to_central_difference() {
+        let cs = |e: &str, x: f64, mode| deriv_complex_step(e, "x", x, mode).unwrap();
+        // abs is not analytic: Im|x + ih| is 0, not the slope.
+        assert!((cs("abs(x)", -2.0, RAD) + 1.0).abs() < 1e-6);
+        assert!((cs("floor(x) + x", 0.5, RAD) - 1.0).abs() < 1e-6);
+        let deg = cs("sin(x)", 60.0, AngleMode::Deg);
+        assert!((deg - 0.5 * std::f64::consts::PI / 180.0).abs() < 1e-9);
+        let err = deriv_complex_step("sqrt(x)", "x", -1.0, RAD).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ComplexResult);
+    }
+}
+
+#[cfg(test)]
+mod prod_tests {
+    use super::*;
+
+    #[test]
+    fn zero_factor_stops_the_product() {
+        assert_eq!(prod("0", "k", 1, 1_000_000, AngleMode::Rad).unwrap(), 0.0);
+        // k = 2 gives a zero factor; k = 3 would divide by zero.
+        assert_eq!(prod("(k - 2) / (k - 3)", "k", 1, 5, AngleMode::Rad).unwrap(), 0.0);
+        assert!(prod("(k - 2) / (k - 3)", "k", 3, 5, AngleMode::Rad).is_err());
+        assert_eq!(prod("k", "k", 1, 5, AngleMode::Rad).unwrap(), 120.0);
+        let dsl = crate::evaluate("product((k - 2) / (k - 3), k, 1, 5)", AngleMode::Rad);
+        assert_eq!(dsl.unwrap(), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod limit_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const RAD: AngleMode = AngleMode::Rad;
+
+    #[test]
+    fn custom_cap_rejects_longer_ranges() {
+        assert_eq!(sum_limited("k", "k", 1, 10, RAD, 10).unwrap(), 55.0);
+        assert_eq!(prod_limited("k", "k", 1, 5, RAD, 5).unwrap(), 120.0);
+        let err = sum_limited("k", "k", 1, 11, RAD, 10).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::RangeTooLarge);
+        assert_eq!(err.message, "Sum range too large (max 10 terms)");
+        assert_eq!(prod_limited("k", "k", 0, 5, RAD, 5).unwrap_err().kind, ErrorKind::RangeTooLarge);
+    }
+
+    #[test]
+    fn empty_ranges_are_sum_zero_and_product_one() {
+        assert_eq!(sum("k", "k", 5, 3, RAD).unwrap(), 0.0);
+        assert_eq!(prod("k", "k", 5, 3, RAD).unwrap(), 1.0);
+        assert_eq!(sum_limited("k", "k", i64::MAX, i64::MIN, RAD, 1).unwrap(), 0.0);
+        assert_eq!(prod_limited("1/0", "k", 1, 0, RAD, 1).unwrap(), 1.0);
+        assert_eq!(crate::evaluate("sum(k, k, 5, 3)", RAD).unwrap(), 0.0);
+        assert_eq!(crate::evaluate("product(k, k, 5, 3)", RAD).unwrap(), 1.0);
+        assert_eq!(crate::evaluate("sum(k, k, 1, -1000000000000)", RAD).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn cap_must_be_positive_and_can_exceed_default() {
+        assert_eq!(sum_limited("k", "k", 1, 1, RAD, 0).unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(sum("1", "k", i64::MIN, i64::MAX, RAD).unwrap_err().kind, ErrorKind::RangeTooLarge);
+        let cap = MAX_TERMS + 10;
+        let err = sum_limited("1", "k", 0, MAX_TERMS as i64 + 10, RAD, cap).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::RangeTooLarge, "one past the larger cap");
+    }
+}
+
+#[cfg(test)]
+mod series_tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    const RAD: AngleMode = AngleMode::Rad;
+
+    #[test]
+    fn basel_series_approaches_pi_squared_over_six() {
+        let s = sum_until("1/k^2", "k", 1, RAD, 1e-10, 1_000_000).unwrap();
+        let exact = std::f64::consts::PI.powi(2) / 6.0;
+        // Stops at k = 10^5; the tail after it is about 1e-5.
+        assert!(s < exact && exact - s < 2e-5, "{}", s);
+    }
+
+    #[test]
+    fn divergence_and_bad_arguments_are_errors() {
+        let err = sum_until("1/k", "k", 1, RAD, 1e-3, 100).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::RangeTooLarge);
+        assert_eq!(err.message, "Sum did not converge within 100 terms");
+        assert_eq!(sum_until("1/k", "k", 1, RAD, 0.0, 10).unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(sum_until("1/k", "k", 1, RAD, f64::NAN, 10).unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(sum_until("1/k", "k", 1, RAD, 1e-3, 0).unwrap_err().kind, ErrorKind::DomainError);
+        assert_eq!(sum_until("0", "k", i64::MAX, RAD, 1e-3, 10).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,62 @@
+//! Names the `std` prelude would provide, for the modules that also build
+//! under `no_std + alloc`. Without `std`, float math comes from libm through
+//! [`F64Ext`] and `HashMap` from hashbrown.
+
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::format;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec;
+pub(crate) use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::HashMap;
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::HashMap;
+
+/// The `f64` methods `core` lacks, with the same names and signatures so
+/// call sites read the same in both builds.
+#[cfg(not(feature = "std"))]
+pub(crate) trait F64Ext {
+    fn sqrt(self) -> f64;
+    fn exp(self) -> f64;
+    fn ln(self) -> f64;
+    fn log10(self) -> f64;
+    fn ln_1p(self) -> f64;
+    fn powf(self, n: f64) -> f64;
+    fn powi(self, n: i32) -> f64;
+    fn sin(self) -> f64;
+    fn cos(self) -> f64;
+    fn tan(self) -> f64;
+    fn sinh(self) -> f64;
+    fn cosh(self) -> f64;
+    fn tanh(self) -> f64;
+    fn atan2(self, other: f64) -> f64;
+    fn floor(self) -> f64;
+    fn ceil(self) -> f64;
+    fn round(self) -> f64;
+    fn trunc(self) -> f64;
+    fn fract(self) -> f64;
+}
+
+#[cfg(not(feature = "std"))]
+impl F64Ext for f64 {
+    fn sqrt(self) -> f64 { libm::sqrt(self) }
+    fn exp(self) -> f64 { libm::exp(self) }
+    fn ln(self) -> f64 { libm::log(self) }
+    fn log10(self) -> f64 { libm::log10(self) }
+    fn ln_1p(self) -> f64 { libm::log1p(self) }
+    fn powf(self, n: f64) -> f64 { libm::pow(self, n) }
+    fn powi(self, n: i32) -> f64 { libm::pow(self, n as f64) }
+    fn sin(self) -> f64 { libm::sin(self) }
+    fn cos(self) -> f64 { libm::cos(self) }
+    fn tan(self) -> f64 { libm::tan(self) }
+    fn sinh(self) -> f64 { libm::sinh(self) }
+    fn cosh(self) -> f64 { libm::cosh(self) }
+    fn tanh(self) -> f64 { libm::tanh(self) }
+    fn atan2(self, other: f64) -> f64 { libm::atan2(self, other) }
+    fn floor(self) -> f64 { libm::floor(self) }
+    fn ceil(self) -> f64 { libm::ceil(self) }
+    fn round(self) -> f64 { libm::round(self) }
+    fn trunc(self) -> f64 { libm::trunc(self) }
+    fn fract(self) -> f64 { self - libm::trunc(self) }
+}
@@ -11,7 +11,7 @@
 //! Panic-free by contract: no `unwrap`, `expect` or `panic!`; every fallible
 //! path returns `ExathError`.
 
-use crate::ast::{collect_vars, eval_ast, parse_str, Ast, BinOp, UserFns};
+use crate::ast::{collect_vars, eval_ast, parse_str, Ast, BinOp, FnDefaults, UserFns};
 use crate::error::ExathError;
 use crate::evaluator::Cx;
 use crate::rational::Num;
@@ -204,6 +204,11 @@ fn collect_candidates(ast: &Ast, var: &str, out: &mut Vec<Ast>, seen: &mut std::
             // …and the function application itself (e.g. u = sin(x))
             push_candidate(ast, var, out, seen);
         }
+        Ast::Chain(_, operands) => {
+            for a in operands {
+                collect_candidates(a, var, out, seen);
+            }
+        }
         Ast::Matrix(_) => {}
     }
 }
@@ -240,6 +245,10 @@ fn replace_subtree(ast: &Ast, target: &str, repl: &Ast) -> Ast {
             name.clone(),
             args.iter().map(|a| replace_subtree(a, target, repl)).collect(),
         ),
+        Ast::Chain(ops, operands) => Ast::Chain(
+            ops.clone(),
+            operands.iter().map(|a| replace_subtree(a, target, repl)).collect(),
+        ),
         Ast::Matrix(rows) => Ast::Matrix(
             rows.iter()
                 .map(|r| r.iter().map(|e| replace_subtree(e, target, repl)).collect())
@@ -1533,6 +1542,7 @@ fn expand_ast(a: &Ast) -> Ast {
         Ast::BinOp(op, l, r) => {
             Ast::BinOp(op.clone(), boxed(expand_ast(l)), boxed(expand_ast(r)))
         }
+        Ast::Chain(ops, operands) => Ast::Chain(ops.clone(), operands.iter().map(expand_ast).collect()),
         Ast::Call(name, args) => {
             let ea: Vec<Ast> = args.iter().map(expand_ast).collect();
             if ea.len() == 1 {
@@ -2093,6 +2103,10 @@ pub fn substitute(ast: &Ast, name: &str, replacement: &Ast) -> Ast {
             fname.clone(),
             args.iter().map(|a| substitute(a, name, replacement)).collect(),
         ),
+        Ast::Chain(ops, operands) => Ast::Chain(
+            ops.clone(),
+            operands.iter().map(|a| substitute(a, name, replacement)).collect(),
+        ),
     }
 }
 
@@ -2100,10 +2114,20 @@ pub fn substitute(ast: &Ast, name: &str, replacement: &Ast) -> Ast {
 /// functions are left intact (their arguments are still inlined). Returns an
 /// error on arity mismatch or excessive recursion depth.
 pub fn inline_user_fns(ast: &Ast, fns: &UserFns) -> Result<Ast, ExathError> {
-    inline_rec(ast, fns, 0)
+    inline_rec(ast, fns, &FnDefaults::new(), 0)
 }
 
-fn inline_rec(ast: &Ast, fns: &UserFns, depth: usize) -> Result<Ast, ExathError> {
+/// Like [`inline_user_fns`], filling left-out trailing arguments from
+/// `defaults` as a session call would.
+pub(crate) fn inline_user_fns_with(
+    ast: &Ast,
+    fns: &UserFns,
+    defaults: &FnDefaults,
+) -> Result<Ast, ExathError> {
+    inline_rec(ast, fns, defaults, 0)
+}
+
+fn inline_rec(ast: &Ast, fns: &UserFns, defaults: &FnDefaults, depth: usize) -> Result<Ast, ExathError> {
     if depth > INLINE_DEPTH_LIMIT {
         return Err(ExathError::domain(
             "function inlining too deep (recursive definition?)",
@@ -2116,7 +2140,7 @@ fn inline_rec(ast: &Ast, fns: &UserFns, depth: usize) -> Result<Ast, ExathError>
             for r in rows {
                 let mut nr = Vec::with_capacity(r.len());
                 for e in r {
-                    nr.push(inline_rec(e, fns, depth + 1)?);
+                    nr.push(inline_rec(e, fns, defaults, depth + 1)?);
                 }
                 out.push(nr);
             }
@@ -2124,32 +2148,48 @@ fn inline_rec(ast: &Ast, fns: &UserFns, depth: usize) -> Result<Ast, ExathError>
         }
         Ast::BinOp(op, l, r) => Ok(Ast::BinOp(
             op.clone(),
-            boxed(inline_rec(l, fns, depth + 1)?),
-            boxed(inline_rec(r, fns, depth + 1)?),
+            boxed(inline_rec(l, fns, defaults, depth + 1)?),
+            boxed(inline_rec(r, fns, defaults, depth + 1)?),
+        )),
+        Ast::UnaryNeg(u) => Ok(Ast::UnaryNeg(boxed(inline_rec(u, fns, defaults, depth + 1)?))),
+        Ast::UnaryNot(u) => Ok(Ast::UnaryNot(boxed(inline_rec(u, fns, defaults, depth + 1)?))),
+        Ast::Factorial(u) => Ok(Ast::Factorial(boxed(inline_rec(u, fns, defaults, depth + 1)?))),
+        Ast::Chain(ops, operands) => Ok(Ast::Chain(
+            ops.clone(),
+            operands.iter().map(|a| inline_rec(a, fns, defaults, depth + 1)).collect::<Result<_, _>>()?,
         )),
-        Ast::UnaryNeg(u) => Ok(Ast::UnaryNeg(boxed(inline_rec(u, fns, depth + 1)?))),
-        Ast::UnaryNot(u) => Ok(Ast::UnaryNot(boxed(inline_rec(u, fns, depth + 1)?))),
-        Ast::Factorial(u) => Ok(Ast::Factorial(boxed(inline_rec(u, fns, depth + 1)?))),
         Ast::Call(name, args) => {
             let mut inlined_args = Vec::with_capacity(args.len());
             for a in args {
-                inlined_args.push(inline_rec(a, fns, depth + 1)?);
+                inlined_args.push(inline_rec(a, fns, defaults, depth + 1)?);
             }
             match fns.get(name) {
                 Some((params, body)) => {
-                    if params.len() != inlined_args.len() {
+                    let fn_defaults = defaults.get(name).map_or(&[][..], Vec::as_slice);
+                    let required = params.len() - fn_defaults.len();
+                    let given = inlined_args.len();
+                    if given > params.len() || (fn_defaults.is_empty() && given != params.len()) {
                         return Err(ExathError::arg_count(format!(
                             "{}() expects {} argument(s), got {}",
                             name,
                             params.len(),
-                            inlined_args.len()
+                            given
+                        )));
+                    }
+                    if given < required {
+                        return Err(ExathError::arg_count(format!(
+                            "{}() is missing argument '{}'",
+                            name, params[given]
                         )));
                     }
+                    for default in &fn_defaults[given - required..] {
+                        inlined_args.push(inline_rec(default, fns, defaults, depth + 1)?);
+                    }
                     let mut b = body.clone();
                     for (param, arg) in params.iter().zip(inlined_args.iter()) {
                         b = substitute(&b, param, arg);
                     }
-                    inline_rec(&b, fns, depth + 1)
+                    inline_rec(&b, fns, defaults, depth + 1)
                 }
                 None => Ok(Ast::Call(name.clone(), inlined_args)),
             }
@@ -2199,7 +2239,7 @@ fn contains_var(ast: &Ast, var: &str) -> bool {
         Ast::Var(name) => name == var,
         Ast::BinOp(_, l, r) => contains_var(l, var) || contains_var(r, var),
         Ast::UnaryNeg(u) | Ast::UnaryNot(u) | Ast::Factorial(u) => contains_var(u, var),
-        Ast::Call(_, args) => args.iter().any(|a| contains_var(a, var)),
+        Ast::Call(_, args) | Ast::Chain(_, args) => args.iter().any(|a| contains_var(a, var)),
     }
 }
 
@@ -2222,7 +2262,7 @@ fn diff(ast: &Ast, var: &str) -> Result<Ast, ExathError> {
         Ast::Factorial(_) => Err(ExathError::domain(
             "symbolic derivative of factorial is not supported",
         )),
-        Ast::UnaryNot(_) => Err(ExathError::domain(
+        Ast::UnaryNot(_) | Ast::Chain(..) => Err(ExathError::domain(
             "symbolic derivative of a logical expression is not supported",
         )),
     }
@@ -2739,6 +2779,13 @@ fn build(ast: &Ast) -> Result<Poly, ExathError> {
         }
         Ast::Factorial(u) => Ok(poly_atom(Ast::Factorial(boxed(rebuild_poly(&build(u)?))))),
         Ast::UnaryNot(u) => Ok(poly_atom(Ast::UnaryNot(boxed(rebuild_poly(&build(u)?))))),
+        Ast::Chain(ops, operands) => {
+            let mut rebuilt = Vec::with_capacity(operands.len());
+            for a in operands {
+                rebuilt.push(rebuild_poly(&build(a)?));
+            }
+            Ok(poly_atom(Ast::Chain(ops.clone(), rebuilt)))
+        }
     }
 }
 
@@ -2944,6 +2991,9 @@ fn rewrite_inverses(a: &Ast) -> Ast {
         Ast::BinOp(op, l, r) => {
             Ast::BinOp(op.clone(), boxed(rewrite_inverses(l)), boxed(rewrite_inverses(r)))
         }
+        Ast::Chain(ops, operands) => {
+            Ast::Chain(ops.clone(), operands.iter().map(rewrite_inverses).collect())
+        }
         Ast::Call(name, args) => {
             let args: Vec<Ast> = args.iter().map(rewrite_inverses).collect();
             if args.len() == 1 {
@@ -2996,7 +3046,9 @@ fn node_count(a: &Ast) -> usize {
         Ast::Number(_) | Ast::Var(_) => 1,
         Ast::UnaryNeg(u) | Ast::UnaryNot(u) | Ast::Factorial(u) => 1 + node_count(u),
         Ast::BinOp(_, l, r) => 1 + node_count(l) + node_count(r),
-        Ast::Call(_, args) => 1 + args.iter().map(node_count).sum::<usize>(),
+        Ast::Call(_, args) | Ast::Chain(_, args) => {
+            1 + args.iter().map(node_count).sum::<usize>()
+        }
     }
 }
 
@@ -3016,6 +3068,9 @@ fn rewrite_reciprocal_trig(a: &Ast) -> Ast {
             boxed(rewrite_reciprocal_trig(l)),
             boxed(rewrite_reciprocal_trig(r)),
         ),
+        Ast::Chain(ops, operands) => {
+            Ast::Chain(ops.clone(), operands.iter().map(rewrite_reciprocal_trig).collect())
+        }
         Ast::Call(name, args) => {
             let args: Vec<Ast> = args.iter().map(rewrite_reciprocal_trig).collect();
             if args.len() == 1 {
@@ -3188,17 +3243,19 @@ fn prec(a: &Ast) -> u8 {
     match a {
         Ast::Matrix(_) => 5,
         Ast::Number(_) | Ast::Var(_) | Ast::Call(_, _) | Ast::Factorial(_) => 5,
-        Ast::UnaryNeg(_) | Ast::UnaryNot(_) => 4,
+        // Unary minus binds looser than `^` (`-x^2` is `-(x^2)`).
+        Ast::BinOp(BinOp::Pow, _, _) => 4,
+        Ast::UnaryNeg(_) | Ast::UnaryNot(_) => 3,
         Ast::BinOp(op, _, _) => match op {
-            BinOp::Pow => 3,
             BinOp::Mul | BinOp::Div | BinOp::Mod => 2,
             BinOp::Add | BinOp::Sub => 1,
             _ => 0,
         },
+        Ast::Chain(..) => 0,
     }
 }
 
-fn op_symbol(op: &BinOp) -> &'static str {
+pub(crate) fn op_symbol(op: &BinOp) -> &'static str {
     match op {
         BinOp::Add => " + ",
         BinOp::Sub => " - ",
@@ -3241,8 +3298,11 @@ fn unparse(a: &Ast) -> String {
         Ast::Number(n) => fmt_num(*n),
         Ast::Var(name) => name.clone(),
         Ast::UnaryNeg(u) => format!("-{}", paren(u, 4)),
-        Ast::UnaryNot(u) => format!("!{}", paren(u, 4)),
+        Ast::UnaryNot(u) => format!("!{}", paren(u, 5)),
         Ast::Factorial(u) => format!("{}!", paren(u, 5)),
+        Ast::Call(name, args) if name == "\u{00b0}" && args.len() == 1 => {
+            format!("{}\u{00b0}", paren(&args[0], 5))
+        }
         Ast::Call(name, args) => {
             let inner: Vec<String> = args.iter().map(unparse).collect();
             format!("{}({})", name, inner.join(", "))
@@ -3259,6 +3319,16 @@ fn unparse(a: &Ast) -> String {
             };
             format!("{}{}{}", paren(l, lmin), op_symbol(op), paren(r, rmin))
         }
+        Ast::Chain(ops, operands) => {
+            // Operands binding looser than `+` (comparisons, `&&`) need parens
+            // so they are not read as links of the chain.
+            let mut out = paren(&operands[0], 1);
+            for (op, operand) in ops.iter().zip(&operands[1..]) {
+                out.push_str(op_symbol(op));
+                out.push_str(&paren(operand, 1));
+            }
+            out
+        }
     }
 }
 
@@ -3276,6 +3346,7 @@ mod tests {
         match s.eval(expr) {
             Ok(CalcResult::Real(v)) => v,
             Ok(CalcResult::Complex(re, _)) => re,
+            Ok(result @ CalcResult::Bool(_)) => result.to_f64_lossy(),
             Err(e) => {
                 assert!(false, "eval failed for '{}': {}", expr, e);
                 f64::NAN
@@ -4024,6 +4095,7 @@ mod tests {
                     match s.eval(src) {
                         Ok(CalcResult::Real(v)) => Some(v),
                         Ok(CalcResult::Complex(re, _)) => Some(re),
+                        Ok(result @ CalcResult::Bool(_)) => Some(result.to_f64_lossy()),
                         Err(_) => None,
                     }
                 };
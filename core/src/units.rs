@@ -6,6 +6,7 @@
 //! dimensions. Conversion supports affine units (°C, °F). Panic-free.
 
 use crate::error::ExathError;
+use crate::prelude::*;
 
 /// SI base-dimension exponents: [length, mass, time, current, temperature,
 /// amount, luminous intensity].
@@ -48,6 +48,7 @@ fn eval_xy(src: &str, x: f64, y: f64) -> Option<f64> {
     match s.eval(src) {
         Ok(CalcResult::Real(v)) => Some(v),
         Ok(CalcResult::Complex(re, _)) => Some(re),
+        Ok(result @ CalcResult::Bool(_)) => Some(result.to_f64_lossy()),
         Err(_) => None,
     }
 }
@@ -17,6 +17,8 @@ final class _CResult extends Struct {
   @Int32()
   external int isError;
   external Pointer<Utf8> errorMsg;
+  @Int32()
+  external int errorKind;
 }
 
 final class _CLineResult extends Struct {
@@ -32,6 +34,8 @@ final class _CLineResult extends Struct {
   @Int32()
   external int isError;
   external Pointer<Utf8> errorMsg;
+  @Int32()
+  external int errorKind;
 }
 
 final class _CSession extends Opaque {}
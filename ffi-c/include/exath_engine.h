@@ -19,6 +19,21 @@ typedef enum ExathAngleMode {
     Grad = 2,
 } ExathAngleMode;
 
+/**
+ * Error category codes stored in the `error_kind` field of result structs.
+ * The field is -1 when is_error == 0. Codes are stable and never renumbered.
+ */
+typedef enum ExathErrorKind {
+    ParseError = 0,
+    UndefinedName = 1,
+    ArgumentCount = 2,
+    ArgumentType = 3,
+    DomainError = 4,
+    Overflow = 5,
+    ComplexResult = 6,
+    RangeTooLarge = 7,
+} ExathErrorKind;
+
 /**
  * Opaque session handle.  Allocate with exath_session_new(), free with exath_session_free().
  */
@@ -39,6 +54,15 @@ typedef struct ExathResult {
     int32_t is_complex;
     int32_t is_error;
     char *error_msg;
+    /**
+     * An ExathErrorKind code if is_error == 1, else -1.
+     */
+    int32_t error_kind;
+    /**
+     * 1 if the value is a truth value (re is 1 or 0), else 0. See
+     * exath_session_set_bool_results.
+     */
+    int32_t is_bool;
 } ExathResult;
 
 /**
@@ -59,6 +83,15 @@ typedef struct ExathLineResult {
     int32_t is_complex;
     int32_t is_error;
     char *error_msg;
+    /**
+     * An ExathErrorKind code if is_error == 1, else -1.
+     */
+    int32_t error_kind;
+    /**
+     * 1 if the value is a truth value (re is 1 or 0), else 0. See
+     * exath_session_set_bool_results.
+     */
+    int32_t is_bool;
 } ExathLineResult;
 
 /**
@@ -83,6 +116,33 @@ int32_t exath_is_valid(const char *expr);
  */
 char *exath_supported_functions(void);
 
+/**
+ * Returns the one-line help text for a built-in function (aliases such as
+ * "arcsin" resolve), or NULL if the name is unknown. Free the result with
+ * exath_free_string().
+ */
+char *exath_function_help(const char *name);
+
+/**
+ * Returns a null-terminated, comma-separated, sorted list of the variables an
+ * expression references ("" for constant-only expressions), or NULL if the
+ * expression does not parse. Free the result with exath_free_string().
+ */
+char *exath_variables_of(const char *expr);
+
+/**
+ * Integrate `expr` over `var` from `a` to `b` with Simpson's rule on `n`
+ * intervals (odd `n` is rounded up, `n == 0` is a DomainError). A smaller `n`
+ * is faster but less accurate; `integral(...)` via eval_line uses an adaptive
+ * rule instead. Free error_msg with exath_free_string() if is_error == 1.
+ */
+struct ExathResult exath_integrate_n(const char *expr,
+                                     const char *var,
+                                     double a,
+                                     double b,
+                                     enum ExathAngleMode angle_mode,
+                                     uint32_t n);
+
 /**
  * Create a new session.
  */
@@ -93,6 +153,23 @@ struct ExathSession *exath_session_new(enum ExathAngleMode angle_mode);
  */
 void exath_session_free(struct ExathSession *session);
 
+/**
+ * Switch the angle mode of an existing session; later evaluations use it.
+ */
+void exath_session_set_angle_mode(struct ExathSession *session, enum ExathAngleMode angle_mode);
+
+/**
+ * Report comparisons and logical expressions (`3 > 2`, `a && b`) as
+ * truth values: is_bool is 1 and re is 1 or 0. Pass 0 to turn it off
+ * again (the default).
+ */
+void exath_session_set_bool_results(struct ExathSession *session, int32_t on);
+
+/**
+ * Returns the current angle mode of a session.
+ */
+enum ExathAngleMode exath_session_angle_mode(struct ExathSession *session);
+
 /**
  * Evaluate one line in a session (may be `var = expr` or a plain expression).
  * Returns ExathResult, free error_msg with exath_free_string() if is_error == 1.
@@ -133,6 +210,14 @@ void exath_session_remove_fn(struct ExathSession *session, const char *name);
  */
 char *exath_session_fn_names(struct ExathSession *session);
 
+/**
+ * Returns a null-terminated, comma-separated list of the parameter names of
+ * the user function `name`, in order ("" for a function without
+ * parameters), or NULL if there is no such function. Free the result with
+ * exath_free_string().
+ */
+char *exath_session_fn_params(struct ExathSession *session, const char *name);
+
 /**
  * Returns a null-terminated, comma-separated list of variable names.
  * Free the result with exath_free_string().
@@ -7,10 +7,15 @@
 //! and the WASM build. Strings returned by the API must be freed with
 //! [`exath_free_string`].
 
+// Handles are raw pointers owned by the C caller; null checks and lifetimes
+// are the caller's contract, as documented on each function.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 use exath_engine::{
-    AngleMode, CalcResult, Session, LineResult,
-    evaluate_complex, is_valid,
+    AngleMode, CalcResult, ErrorKind, ExathError, Session, LineResult,
+    collect_vars, evaluate_complex, is_valid,
 };
+use exath_engine::numerics::integrate_n;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
@@ -32,6 +37,34 @@ fn to_angle_mode(mode: &ExathAngleMode) -> AngleMode {
     }
 }
 
+fn from_angle_mode(mode: AngleMode) -> ExathAngleMode {
+    match mode {
+        AngleMode::Deg  => ExathAngleMode::Deg,
+        AngleMode::Rad  => ExathAngleMode::Rad,
+        AngleMode::Grad => ExathAngleMode::Grad,
+    }
+}
+
+// ── Error kind ────────────────────────────────────────────────────────────────
+
+/// Error category codes stored in the `error_kind` field of result structs.
+/// The field is -1 when is_error == 0. Values are `ErrorKind::as_code`, so a
+/// host can rebuild the error with `ErrorKind::from_code`.
+#[repr(C)]
+pub enum ExathErrorKind {
+    ParseError    = 0,
+    UndefinedName = 1,
+    ArgumentCount = 2,
+    ArgumentType  = 3,
+    DomainError   = 4,
+    Overflow      = 5,
+    ComplexResult = 6,
+    RangeTooLarge = 7,
+}
+
+/// Sentinel stored in `error_kind` when there is no error.
+const NO_ERROR_KIND: i32 = -1;
+
 // ── Result type ───────────────────────────────────────────────────────────────
 
 /// Result returned from evaluation functions.
@@ -46,6 +79,11 @@ pub struct ExathResult {
     pub is_complex: i32,
     pub is_error: i32,
     pub error_msg: *mut c_char,
+    /// An ExathErrorKind code if is_error == 1, else -1.
+    pub error_kind: i32,
+    /// 1 if the value is a truth value (re is 1 or 0), else 0. See
+    /// exath_session_set_bool_results.
+    pub is_bool: i32,
 }
 
 fn ok_result(re: f64, im: f64) -> ExathResult {
@@ -55,10 +93,12 @@ fn ok_result(re: f64, im: f64) -> ExathResult {
         is_complex: if im != 0.0 { 1 } else { 0 },
         is_error: 0,
         error_msg: std::ptr::null_mut(),
+        error_kind: NO_ERROR_KIND,
+        is_bool: 0,
     }
 }
 
-fn error_result(msg: &str) -> ExathResult {
+fn error_result(msg: &str, kind: ErrorKind) -> ExathResult {
     let sanitized = msg.replace('\0', "");
     let c_msg = match CString::new(sanitized) {
         Ok(cstring) => cstring,
@@ -73,14 +113,20 @@ fn error_result(msg: &str) -> ExathResult {
         is_complex: 0,
         is_error: 1,
         error_msg: c_msg.into_raw(),
+        error_kind: kind.as_code(),
+        is_bool: 0,
     }
 }
 
-fn calc_to_result(result: Result<CalcResult, exath_engine::ExathError>) -> ExathResult {
+fn calc_to_result(result: Result<CalcResult, ExathError>) -> ExathResult {
     match result {
         Ok(CalcResult::Real(re)) => ok_result(re, 0.0),
         Ok(CalcResult::Complex(re, im)) => ok_result(re, im),
-        Err(err) => error_result(&err.to_string()),
+        Ok(result @ CalcResult::Bool(_)) => ExathResult {
+            is_bool: 1,
+            ..ok_result(result.to_f64_lossy(), 0.0)
+        },
+        Err(err) => error_result(&err.to_string(), err.kind),
     }
 }
 
@@ -107,7 +153,7 @@ pub extern "C" fn exath_evaluate(
 ) -> ExathResult {
     let expr_str = match parse_cstr(expr) {
         Ok(str) => str,
-        Err(err) => return error_result(&err),
+        Err(err) => return error_result(&err, ErrorKind::ParseError),
     };
     calc_to_result(evaluate_complex(expr_str, to_angle_mode(&angle_mode)))
 }
@@ -135,6 +181,58 @@ pub extern "C" fn exath_supported_functions() -> *mut c_char {
     to_c_string(&list).into_raw()
 }
 
+/// Returns the one-line help text for a built-in function (aliases such as
+/// "arcsin" resolve), or NULL if the name is unknown. Free the result with
+/// exath_free_string().
+#[no_mangle]
+pub extern "C" fn exath_function_help(name: *const c_char) -> *mut c_char {
+    match parse_cstr(name).map(exath_engine::function_help) {
+        Ok(Some(help)) => to_c_string(help).into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+// ── Variables of an expression ────────────────────────────────────────────────
+
+/// Returns a null-terminated, comma-separated, sorted list of the variables an
+/// expression references ("" for constant-only expressions), or NULL if the
+/// expression does not parse. Free the result with exath_free_string().
+#[no_mangle]
+pub extern "C" fn exath_variables_of(expr: *const c_char) -> *mut c_char {
+    let ast = match parse_cstr(expr).map(exath_engine::analysis::parse) {
+        Ok(Ok(ast)) => ast,
+        _ => return std::ptr::null_mut(),
+    };
+    to_c_string(&collect_vars(&ast).join(",")).into_raw()
+}
+
+// ── Numeric integration ───────────────────────────────────────────────────────
+
+/// Integrate `expr` over `var` from `a` to `b` with Simpson's rule on `n`
+/// intervals (odd `n` is rounded up, `n == 0` is a DomainError). A smaller `n`
+/// is faster but less accurate; `integral(...)` via eval_line uses an adaptive
+/// rule instead. Free error_msg with exath_free_string() if is_error == 1.
+#[no_mangle]
+pub extern "C" fn exath_integrate_n(
+    expr: *const c_char,
+    var: *const c_char,
+    a: f64,
+    b: f64,
+    angle_mode: ExathAngleMode,
+    n: u32,
+) -> ExathResult {
+    let (expr_str, var_str) = match (parse_cstr(expr), parse_cstr(var)) {
+        (Ok(expr_str), Ok(var_str)) => (expr_str, var_str),
+        (Err(err), _) | (_, Err(err)) => {
+            return error_result(&err, ErrorKind::ParseError)
+        }
+    };
+    let mode = to_angle_mode(&angle_mode);
+    calc_to_result(
+        integrate_n(expr_str, var_str, a, b, mode, n as usize).map(CalcResult::Real),
+    )
+}
+
 // ── Session ───────────────────────────────────────────────────────────────────
 
 /// Opaque session handle.  Allocate with exath_session_new(), free with exath_session_free().
@@ -157,6 +255,34 @@ pub extern "C" fn exath_session_free(session: *mut ExathSession) {
     }
 }
 
+/// Switch the angle mode of an existing session; later evaluations use it.
+#[no_mangle]
+pub extern "C" fn exath_session_set_angle_mode(
+    session: *mut ExathSession,
+    angle_mode: ExathAngleMode,
+) {
+    unsafe {
+        (*session).0.angle_mode = to_angle_mode(&angle_mode);
+    }
+}
+
+/// Report comparisons and logical expressions (`3 > 2`, `a && b`) as
+/// truth values: is_bool is 1 and re is 1 or 0. Pass 0 to turn it off
+/// again (the default).
+#[no_mangle]
+pub extern "C" fn exath_session_set_bool_results(session: *mut ExathSession, on: i32) {
+    unsafe {
+        (*session).0.set_bool_results(on != 0);
+    }
+}
+
+/// Returns the current angle mode of a session.
+#[no_mangle]
+pub extern "C" fn exath_session_angle_mode(session: *mut ExathSession) -> ExathAngleMode {
+    let mode = unsafe { (*session).0.angle_mode };
+    from_angle_mode(mode)
+}
+
 /// Evaluate one line in a session (may be `var = expr` or a plain expression).
 /// Returns ExathResult, free error_msg with exath_free_string() if is_error == 1.
 #[no_mangle]
@@ -166,7 +292,7 @@ pub extern "C" fn exath_session_eval(
 ) -> ExathResult {
     let line_str = match parse_cstr(line) {
         Ok(str) => str,
-        Err(err) => return error_result(&err),
+        Err(err) => return error_result(&err, ErrorKind::ParseError),
     };
     let inner = unsafe { &mut (*session).0 };
     calc_to_result(inner.eval(line_str))
@@ -187,6 +313,11 @@ pub struct ExathLineResult {
     pub is_complex: i32,
     pub is_error: i32,
     pub error_msg: *mut c_char,
+    /// An ExathErrorKind code if is_error == 1, else -1.
+    pub error_kind: i32,
+    /// 1 if the value is a truth value (re is 1 or 0), else 0. See
+    /// exath_session_set_bool_results.
+    pub is_bool: i32,
 }
 
 fn line_value(re: f64, im: f64) -> ExathLineResult {
@@ -198,10 +329,12 @@ fn line_value(re: f64, im: f64) -> ExathLineResult {
         is_complex: if im != 0.0 { 1 } else { 0 },
         is_error: 0,
         error_msg: std::ptr::null_mut(),
+        error_kind: NO_ERROR_KIND,
+        is_bool: 0,
     }
 }
 
-fn line_error(msg: &str) -> ExathLineResult {
+fn line_error(msg: &str, kind: ErrorKind) -> ExathLineResult {
     let c_msg = CString::new(msg.replace('\0', ""))
         .unwrap_or_else(|_| CString::new("Unknown error").expect("static literal"));
     ExathLineResult {
@@ -212,6 +345,8 @@ fn line_error(msg: &str) -> ExathLineResult {
         is_complex: 0,
         is_error: 1,
         error_msg: c_msg.into_raw(),
+        error_kind: kind.as_code(),
+        is_bool: 0,
     }
 }
 
@@ -226,12 +361,16 @@ pub extern "C" fn exath_session_eval_line(
 ) -> ExathLineResult {
     let line_str = match parse_cstr(line) {
         Ok(str) => str,
-        Err(err) => return line_error(&err),
+        Err(err) => return line_error(&err, ErrorKind::ParseError),
     };
     let inner = unsafe { &mut (*session).0 };
     match inner.eval_line(line_str) {
         Ok(LineResult::Value(CalcResult::Real(re))) => line_value(re, 0.0),
         Ok(LineResult::Value(CalcResult::Complex(re, im))) => line_value(re, im),
+        Ok(LineResult::Value(result @ CalcResult::Bool(_))) => ExathLineResult {
+            is_bool: 1,
+            ..line_value(result.to_f64_lossy(), 0.0)
+        },
         Ok(LineResult::Expression(s)) => ExathLineResult {
             is_expression: 1,
             expression: to_c_string(&s).into_raw(),
@@ -240,8 +379,10 @@ pub extern "C" fn exath_session_eval_line(
             is_complex: 0,
             is_error: 0,
             error_msg: std::ptr::null_mut(),
+            error_kind: NO_ERROR_KIND,
+            is_bool: 0,
         },
-        Err(e) => line_error(&e.to_string()),
+        Err(e) => line_error(&e.to_string(), e.kind),
     }
 }
 
@@ -302,6 +443,25 @@ pub extern "C" fn exath_session_fn_names(session: *mut ExathSession) -> *mut c_c
     to_c_string(&names.join(",")).into_raw()
 }
 
+/// Returns a null-terminated, comma-separated list of the parameter names of
+/// the user function `name`, in order ("" for a function without
+/// parameters), or NULL if there is no such function. Free the result with
+/// exath_free_string().
+#[no_mangle]
+pub extern "C" fn exath_session_fn_params(
+    session: *mut ExathSession,
+    name: *const c_char,
+) -> *mut c_char {
+    let params = match parse_cstr(name) {
+        Ok(name_str) => unsafe { (*session).0.fn_params(name_str) },
+        Err(_) => None,
+    };
+    match params {
+        Some(params) => to_c_string(&params.join(",")).into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
 /// Returns a null-terminated, comma-separated list of variable names.
 /// Free the result with exath_free_string().
 #[no_mangle]
@@ -329,3 +489,29 @@ fn to_c_string(input: &str) -> CString {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_angle_mode_round_trips() {
+        let session = exath_session_new(ExathAngleMode::Deg);
+        assert_eq!(exath_session_angle_mode(session) as i32, ExathAngleMode::Deg as i32);
+
+        let line = CString::new("sin(90)").unwrap();
+        let deg = exath_session_eval(session, line.as_ptr());
+        assert_eq!(deg.is_error, 0);
+        assert!((deg.re - 1.0).abs() < 1e-12);
+
+        exath_session_set_angle_mode(session, ExathAngleMode::Rad);
+        assert_eq!(exath_session_angle_mode(session) as i32, ExathAngleMode::Rad as i32);
+        let rad = exath_session_eval(session, line.as_ptr());
+        assert_eq!(rad.is_error, 0);
+        assert!((rad.re - 90f64.sin()).abs() < 1e-12);
+
+        exath_session_set_angle_mode(session, ExathAngleMode::Grad);
+        assert_eq!(exath_session_angle_mode(session) as i32, ExathAngleMode::Grad as i32);
+        exath_session_free(session);
+    }
+}
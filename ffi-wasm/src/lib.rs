@@ -6,9 +6,11 @@
 //! This surface mirrors the Rust crate and the C-FFI.
 
 use exath_engine::{
-    AngleMode, CalcResult, Session, LineResult,
-    evaluate_complex, is_valid, supported_functions,
+    AngleMode, CalcResult, ResultMode, Session, LineResult,
+    collect_vars, evaluate_typed, function_catalog, function_categories, function_help, is_valid,
+    supported_functions,
 };
+use exath_engine::numerics::integrate_n;
 use wasm_bindgen::prelude::*;
 
 // ── Helpers ───────────────────────────────────────────────────────────────────
@@ -21,6 +23,14 @@ fn parse_angle_mode(input: &str) -> AngleMode {
     }
 }
 
+fn parse_result_mode(input: Option<String>) -> ResultMode {
+    match input.map(|s| s.to_lowercase()).as_deref() {
+        Some("real")        => ResultMode::RealOnly,
+        Some("realorerror") => ResultMode::RealOrError,
+        _                   => ResultMode::Complex,
+    }
+}
+
 // ── ExathResult ───────────────────────────────────────────────────────────────
 
 /// Result object returned to JavaScript.
@@ -29,6 +39,7 @@ pub struct ExathResult {
     re: f64,
     im: f64,
     is_complex: bool,
+    is_bool: bool,
     error: Option<String>,
 }
 
@@ -49,6 +60,13 @@ impl ExathResult {
         self.is_complex
     }
 
+    /// True if the result is a truth value (`.re` is 1 or 0); see
+    /// `ExathSession.setBoolResults`.
+    #[wasm_bindgen(getter, js_name = isBool)]
+    pub fn is_bool(&self) -> bool {
+        self.is_bool
+    }
+
     #[wasm_bindgen(getter, js_name = isError)]
     pub fn is_error(&self) -> bool {
         self.error.is_some()
@@ -66,18 +84,28 @@ fn calc_to_result(result: Result<CalcResult, exath_engine::ExathError>) -> Exath
             re,
             im: 0.0,
             is_complex: false,
+            is_bool: false,
             error: None,
         },
         Ok(CalcResult::Complex(re, im)) => ExathResult {
             re,
             im,
             is_complex: true,
+            is_bool: false,
+            error: None,
+        },
+        Ok(result @ CalcResult::Bool(_)) => ExathResult {
+            re: result.to_f64_lossy(),
+            im: 0.0,
+            is_complex: false,
+            is_bool: true,
             error: None,
         },
         Err(err) => ExathResult {
             re: 0.0,
             im: 0.0,
             is_complex: false,
+            is_bool: false,
             error: Some(err.to_string()),
         },
     }
@@ -89,11 +117,13 @@ fn calc_to_result(result: Result<CalcResult, exath_engine::ExathError>) -> Exath
 ///
 /// - expr: expression string, e.g. `"sqrt(-4) + 2*pi"`
 /// - angle_mode: `"deg"`, `"rad"`, or `"grad"` (case-insensitive, defaults to `"rad"`)
+/// - want (optional): `"complex"` (default), `"real"` (complex results become
+///   NaN) or `"realOrError"` (complex results are errors)
 ///
 /// Returns an ExathResult with `.re`, `.im`, `.isComplex`, `.isError`, `.errorMessage`.
 #[wasm_bindgen]
-pub fn evaluate(expr: &str, angle_mode: &str) -> ExathResult {
-    calc_to_result(evaluate_complex(expr, parse_angle_mode(angle_mode)))
+pub fn evaluate(expr: &str, angle_mode: &str, want: Option<String>) -> ExathResult {
+    calc_to_result(evaluate_typed(expr, parse_angle_mode(angle_mode), parse_result_mode(want)))
 }
 
 // ── Validation ────────────────────────────────────────────────────────────────
@@ -115,6 +145,80 @@ pub fn js_supported_functions() -> Vec<JsValue> {
         .collect()
 }
 
+/// Returns the supported functions grouped for display, as an object of
+/// arrays: `{ "Trigonometric": ["sin", …], "Hyperbolic": [...], … }`.
+#[wasm_bindgen(js_name = functionCategories)]
+pub fn js_function_categories() -> js_sys::Object {
+    let groups = js_sys::Object::new();
+    for (category, names) in function_categories() {
+        let names: js_sys::Array = names.iter().map(|name| JsValue::from_str(name)).collect();
+        js_sys::Reflect::set(&groups, &JsValue::from_str(category), &names)
+            .expect("a plain object accepts new properties");
+    }
+    groups
+}
+
+/// A built-in function name with its one-line help, see `functionCatalog`.
+#[wasm_bindgen]
+pub struct FunctionInfo {
+    name: &'static str,
+    help: &'static str,
+}
+
+#[wasm_bindgen]
+impl FunctionInfo {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn help(&self) -> String {
+        self.help.to_string()
+    }
+}
+
+/// Returns every supported function as `{ name, help }` objects for hover help.
+#[wasm_bindgen(js_name = functionCatalog)]
+pub fn js_function_catalog() -> Vec<FunctionInfo> {
+    function_catalog()
+        .into_iter()
+        .map(|(name, help)| FunctionInfo { name, help })
+        .collect()
+}
+
+/// Returns the one-line help text for a built-in function, or `undefined`.
+#[wasm_bindgen(js_name = functionHelp)]
+pub fn js_function_help(name: &str) -> Option<String> {
+    function_help(name).map(str::to_string)
+}
+
+// ── Variables of an expression ────────────────────────────────────────────────
+
+/// Returns the sorted names of the variables an expression references, e.g.
+/// `["a", "x"]` for `"a*x^2 + pi"`. Empty for constant-only or invalid input.
+#[wasm_bindgen(js_name = variablesOf)]
+pub fn js_variables_of(expr: &str) -> Vec<JsValue> {
+    match exath_engine::analysis::parse(expr) {
+        Ok(ast) => collect_vars(&ast)
+            .iter()
+            .map(|name| JsValue::from_str(name))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// ── Numeric integration ───────────────────────────────────────────────────────
+
+/// Integrate `expr` over `var` from `a` to `b` with Simpson's rule on `n`
+/// intervals (odd `n` is rounded up, `0` is an error). A smaller `n` is faster
+/// but less accurate, e.g. for plotting.
+#[wasm_bindgen(js_name = integrateN)]
+pub fn js_integrate_n(expr: &str, var: &str, a: f64, b: f64, angle_mode: &str, n: u32) -> ExathResult {
+    let mode = parse_angle_mode(angle_mode);
+    calc_to_result(integrate_n(expr, var, a, b, mode, n as usize).map(CalcResult::Real))
+}
+
 // ── Session ───────────────────────────────────────────────────────────────────
 
 /// A stateful session that persists variables between eval calls.
@@ -134,9 +238,30 @@ pub struct ExathSession {
 impl ExathSession {
     #[wasm_bindgen(constructor)]
     pub fn new(angle_mode: &str) -> ExathSession {
-        ExathSession {
-            inner: Session::new(parse_angle_mode(angle_mode)),
-        }
+        let mut inner = Session::new(parse_angle_mode(angle_mode));
+        // wasm32 has no clock for the core to seed `rand()` from.
+        inner.seed((js_sys::Math::random() * (1u64 << 53) as f64) as u64);
+        ExathSession { inner }
+    }
+
+    /// The current angle mode: `"deg"`, `"rad"` or `"grad"`.
+    #[wasm_bindgen(getter, js_name = angleMode)]
+    pub fn angle_mode(&self) -> String {
+        self.inner.angle_mode.label().to_lowercase()
+    }
+
+    /// Switch the angle mode (`"deg"`, `"rad"` or `"grad"`); later
+    /// evaluations use it. Variables and functions are kept.
+    #[wasm_bindgen(js_name = setAngleMode)]
+    pub fn set_angle_mode(&mut self, angle_mode: &str) {
+        self.inner.angle_mode = parse_angle_mode(angle_mode);
+    }
+
+    /// Report comparisons and logical expressions (`3 > 2`, `a && b`) as
+    /// booleans: `.isBool` is true and `.re` is 1 or 0. Off by default.
+    #[wasm_bindgen(js_name = setBoolResults)]
+    pub fn set_bool_results(&mut self, on: bool) {
+        self.inner.set_bool_results(on);
     }
 
     /// Evaluate one line (may be `var = expr` or a plain expression).
@@ -154,6 +279,10 @@ impl ExathSession {
         match self.inner.eval_line(line) {
             Ok(LineResult::Value(CalcResult::Real(re))) => ExathLine::from_value(re, 0.0),
             Ok(LineResult::Value(CalcResult::Complex(re, im))) => ExathLine::from_value(re, im),
+            Ok(LineResult::Value(result @ CalcResult::Bool(_))) => ExathLine {
+                is_bool: true,
+                ..ExathLine::from_value(result.to_f64_lossy(), 0.0)
+            },
             Ok(LineResult::Expression(s)) => ExathLine::from_expression(s),
             Err(e) => ExathLine::from_error(e.to_string()),
         }
@@ -197,6 +326,30 @@ impl ExathSession {
             .collect()
     }
 
+    /// Parameter names of a user-defined function as a JS Array of strings,
+    /// or `undefined` if there is no such function.
+    #[wasm_bindgen(js_name = fnParams)]
+    pub fn fn_params(&self, name: &str) -> Option<Vec<JsValue>> {
+        self.inner
+            .fn_params(name)
+            .map(|params| params.iter().map(|p| JsValue::from_str(p)).collect())
+    }
+
+    /// Differentiate the one-argument session function `fnName` numerically
+    /// at `x`. The body sees the session's variables and other functions,
+    /// so after `f(x) = a*x^2` this is the slope to plot.
+    #[wasm_bindgen(js_name = derivOf)]
+    pub fn deriv_of(&self, fn_name: &str, x: f64) -> ExathResult {
+        calc_to_result(self.inner.deriv(fn_name, x).map(CalcResult::Real))
+    }
+
+    /// Integrate the one-argument session function `fnName` numerically
+    /// from `a` to `b`, with the session's variables and functions in scope.
+    #[wasm_bindgen(js_name = integrateOf)]
+    pub fn integrate_of(&self, fn_name: &str, a: f64, b: f64) -> ExathResult {
+        calc_to_result(self.inner.integrate(fn_name, a, b).map(CalcResult::Real))
+    }
+
     /// Remove a user-defined function.
     #[wasm_bindgen(js_name = removeFn)]
     pub fn remove_fn(&mut self, name: &str) {
@@ -214,19 +367,20 @@ pub struct ExathLine {
     expression: String,
     re: f64,
     im: f64,
+    is_bool: bool,
     is_error: bool,
     error_message: Option<String>,
 }
 
 impl ExathLine {
     fn from_value(re: f64, im: f64) -> ExathLine {
-        ExathLine { is_expression: false, expression: String::new(), re, im, is_error: false, error_message: None }
+        ExathLine { is_expression: false, expression: String::new(), re, im, is_bool: false, is_error: false, error_message: None }
     }
     fn from_expression(s: String) -> ExathLine {
-        ExathLine { is_expression: true, expression: s, re: 0.0, im: 0.0, is_error: false, error_message: None }
+        ExathLine { is_expression: true, expression: s, re: 0.0, im: 0.0, is_bool: false, is_error: false, error_message: None }
     }
     fn from_error(msg: String) -> ExathLine {
-        ExathLine { is_expression: false, expression: String::new(), re: 0.0, im: 0.0, is_error: true, error_message: Some(msg) }
+        ExathLine { is_expression: false, expression: String::new(), re: 0.0, im: 0.0, is_bool: false, is_error: true, error_message: Some(msg) }
     }
 }
 
@@ -259,6 +413,12 @@ impl ExathLine {
         self.im != 0.0
     }
 
+    /// True if the value is a truth value (`.re` is 1 or 0).
+    #[wasm_bindgen(getter, js_name = isBool)]
+    pub fn is_bool(&self) -> bool {
+        self.is_bool
+    }
+
     #[wasm_bindgen(getter, js_name = isError)]
     pub fn is_error(&self) -> bool {
         self.is_error
@@ -0,0 +1,18 @@
+//! JS-facing tests, run with `wasm-pack test --node ffi-wasm`.
+#![cfg(target_arch = "wasm32")]
+
+use exath_engine_wasm::ExathSession;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn deriv_and_integrate_session_functions() {
+    let mut s = ExathSession::new("rad");
+    s.eval("a = 3");
+    s.eval("f(x) = a*x^2");
+    let d = s.deriv_of("f", 2.0);
+    assert!(!d.is_error());
+    assert!((d.re() - 12.0).abs() < 1e-5);
+    let i = s.integrate_of("f", 0.0, 1.0);
+    assert!((i.re() - 1.0).abs() < 1e-9);
+    assert!(s.deriv_of("g", 0.0).is_error());
+}
@@ -0,0 +1,27 @@
+//! Compiles exath-engine's numeric evaluator in a `no_std + alloc` crate.
+//! Build it on its own so the workspace's `std` users don't switch the
+//! feature back on:
+//!
+//! ```text
+//! cargo build -p exath-engine-no-std-check
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use exath_engine::evaluator::Cx;
+use exath_engine::{evaluate, evaluate_with_vars, AngleMode, CalcResult, ExathError};
+
+/// A real-valued expression, as firmware would evaluate a configured formula.
+pub fn eval_real(expr: &str) -> Result<f64, ExathError> {
+    evaluate(expr, AngleMode::Rad)
+}
+
+/// An expression in one complex variable `z`.
+pub fn eval_at(expr: &str, z: Cx) -> Result<CalcResult, ExathError> {
+    // The map type is std's or hashbrown's depending on the `std` feature.
+    let vars = [(String::from("z"), z)].into_iter().collect();
+    evaluate_with_vars(expr, AngleMode::Rad, &vars)
+}